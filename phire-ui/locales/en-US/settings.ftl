@@ -16,8 +16,10 @@ item-mp-sub = Enable multiplayer mode
 item-mp-addr = Multiplayer server
 item-mp-addr-sub = Server address, 'host:port'
 item-mp-addr-invalid = Invalid server address
-item-lowq = Low quality mode
-item-lowq-sub = Enable this if the UI is laggy
+item-sample-count = Anti-aliasing
+item-sample-count-sub = Higher values look smoother but cost more performance
+sample-count-off = Off
+sample-count-unsupported = { $requested }x anti-aliasing is not supported by this device, using { $supported }x
 item-insecure = Insecure mode
 item-insecure-sub = Enable this if you can't use online functionalities. Makes your connection insecure!
 
@@ -32,15 +34,60 @@ item-cali = Adjust offset
 item-exclusive-audio = Exclusive Audio (Only Android)
 item-exclusive-audio-sub = Use exclusive access to reduce latency, but may prevent the sound from being recorded
 item-audio-compatibility = Audio Compatibility Mode (Only Android)
+item-normalize = Normalize music volume
+item-normalize-sub = Keep the loudness of chart music consistent
+item-mute-music = Mute music
+item-mute-music-sub = Practice with only the metronome and sound effects
+item-hold-tick = Hold tick sound
+item-hold-tick-sub = Play a tick on every beat while holding a Hold note
+item-metronome = Metronome
+item-metronome-sub = Click on every beat of the chart
+item-metronome-sub-division = Metronome subdivision
+item-music-filter = Music filter
+item-music-filter-sub = Muffle the music to hear the beat, sound effects are not affected
+music-filter-off = Off
+music-filter-lowpass = Low-pass
+music-filter-highpass = High-pass
+item-music-filter-cutoff = Filter cutoff
 
 item-show-acc = Show real-time accuracy
+item-note-progress = Show note progress
 item-dc-pause = Double tap to pause
+item-blur-pause = Pause on focus loss
+item-blur-pause-sub = Pause when switching away from the game
 item-dhint = Highlight simul. notes
 item-dhint-sub = Notes that touch line simultaneously will be highlighted
 item-opt = Aggressive optimization
 item-opt-sub = Improves performance, but can cause incorrect behavior
 item-speed = Speed
+item-speed-bpm = Practice Speed in BPM
+item-speed-bpm-sub = Set the speed in Exercise mode as a target BPM
+item-note-speed = Note speed
+item-note-speed-sub = Only changes how fast notes scroll, not the music or timing
 item-note-size = Note size
+item-note-size-hold = Hold Note Size
+item-note-size-drag = Drag Note Size
+item-note-size-click = Tap Note Size
+item-note-size-flick = Flick Note Size
+item-bad-note = Bad Notes
+item-bad-note-sub = How notes judged as Bad are shown
+bad-note-default = Default
+bad-note-fade-only = Fade only
+bad-note-hidden = Hidden
+item-combo-display = Combo Number
+item-combo-display-sub = When the number above the combo label is shown; milestones are every { $step } combos
+combo-display-always = Always
+combo-display-milestones = Milestones
+combo-display-hidden = Hidden
+item-touch-debounce = Touch Debounce
+item-touch-debounce-sub = Ignore touches that end right after starting
+item-touch-dead-zone = Flick Dead Zone
+item-touch-dead-zone-sub = Minimum movement before a touch can flick
+item-lenient-position = Tap Anywhere
+item-lenient-position-sub = Taps judge the next note wherever they land, scores are not recorded
+item-forgiving-holds = Forgiving Holds
+item-forgiving-holds-sub = Releasing a hold shortly before its end still completes it, scores are not recorded
+item-forgiving-hold-window = Hold Release Window
 
 item-chart-debug-line = Chart Debug Mode - Line
 item-chart-debug-line-sub = Display line properties
@@ -48,14 +95,83 @@ item-chart-debug-note = Chart Debug Mode - Note
 item-chart-debug-note-sub = Display note properties
 item-touch-debug = Touch Debug Mode
 item-touch-debug-sub = Display touch points
+item-show-fps = Frame Stats
+item-show-fps-sub = Display FPS and a frame time graph
+item-hold-keep-head = Hold: Keep Head
+item-hold-repeat = Hold: Repeat Body
+item-hold-compact = Hold: Compact
+hold-flag-pack = Pack
+hold-flag-on = On
+hold-flag-off = Off
 item-chart_ratio = Chart Zoom Ratio
 item-fade = Note Fade Out/Fade In
 item-fade-sub = Fade out when less than 0 Fade in when greater than 0
+item-bg-dim = Background Dim
+item-bg-dim-sub = Slide to the far left to use the chart's dim
+bg-dim-chart = Chart
+item-bg-blur = Blur Background
+item-bg-parallax = Background Parallax
+item-bg-parallax-sub = The background follows the movement of the lines a little
 item-all-good = Force Good judgment
 item-watermark = Watermark
 item-combo = COMBO Text
 item-roman = Roman Mode
 item-chinese = Chinese Mode
+item-long-number = Long Numbers
+item-long-number-sub = How scores and combos too wide for their space are shown
+long-number-shrink = Shrink
+long-number-truncate = Truncate
+long-number-scroll = Scroll
+item-color-filter = Color Filter
+item-color-filter-sub = Remap colors for color vision deficiencies
+color-filter-none = None
+color-filter-protanopia = Protanopia
+color-filter-deuteranopia = Deuteranopia
+color-filter-tritanopia = Tritanopia
+item-particle-quality = Particle Quality
+item-particle-quality-sub = Lower it if hit effects cause lag
+particle-quality-off = Off
+particle-quality-low = Low
+particle-quality-medium = Medium
+particle-quality-high = High
+item-ap-aura = All Perfect Aura
+item-ap-aura-sub = Glow around the screen while every hit is perfect
+item-line-feedback = Judge Line Feedback
+item-line-feedback-sub = Color the judge line while the play is all perfect or full combo
+item-line-thickness = Judge Line Thickness
+item-line-glow = Judge Line Glow
+item-line-glow-sub = Soft glow around judge lines, makes them easier to see on busy backgrounds
+item-visual-flip = Mirror View
+item-visual-flip-sub = Show charts mirrored when watching, without changing judgement
+item-combo-break = Combo Break Feedback
+item-combo-break-sub = Shake the combo when it breaks
+item-hit-popup = Early/Late Indicator
+item-hit-popup-sub = Show EARLY or LATE next to good hits
+item-hit-ms = Hit Timing in ms
+item-hit-ms-sub = Show how early (-) or late (+) every tap was, in milliseconds
+item-timing-meter = Timing Meter
+item-timing-meter-sub = Plot recent taps on a meter at the bottom, early on the left and late on the right
+item-accuracy-target = Accuracy Target
+item-accuracy-target-sub = Warn when the live accuracy drops below it
+item-accuracy-target-off = Off
+item-accuracy-target-input = Target accuracy in percent, leave empty to disable
+item-accuracy-target-invalid = Enter a number between 0 and 100
+item-accuracy-target-pause = Pause Below Target
+item-score-mode = Score Formula
+item-score-mode-sub = How the score is computed from judgements
+score-mode-phigros = Phigros
+score-mode-millions = Millions
+score-mode-percentage = Percentage
+item-note-tint-hold = Hold Note Tint
+item-note-tint-drag = Drag Note Tint
+item-note-tint-click = Tap Note Tint
+item-note-tint-flick = Flick Note Tint
+item-note-tint-none = Default
+item-note-tint-input = Tint color (RRGGBB or AARRGGBB, empty to reset)
+item-note-tint-invalid = Invalid color
+item-color-perfect = Perfect Effect Color
+item-color-good = Good Effect Color
+item-tint-bad = Tint Bad Notes
 
 load-cali-failed = Failed to load audio
 not-combo = Cannot be COMBO
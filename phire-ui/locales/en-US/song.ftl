@@ -28,6 +28,7 @@ delete = Delete
 rate = Rate
 exercise = Exercise
 offset = Adjust offset
+practice = Practice from last stop
 
 edit-cancel = Cancel
 edit-save = Save
@@ -15,8 +15,6 @@ item-mp-sub = Activer le mode multijoueur
 item-mp-addr = Serveur multijoueur
 item-mp-addr-sub = Adresse de serveur, «hôte:port»
 item-mp-addr-invalid = Adresse de serveur invalide
-item-lowq = Mode basse qualité
-item-lowq-sub = Activez cette option si l'IU est lente
 
 item-adjust = Ajustement automatique du temps
 item-adjust-sub = Cela ajuste le temps dynamiquement pour synchroniser la musique et la partition
@@ -15,8 +15,6 @@ item-mp-sub = Hidupkan mode multiplayer
 item-mp-addr = Multiplayer server
 item-mp-addr-sub = Server address, 'host:port'
 item-mp-addr-invalid = Server address tidak valid
-item-lowq = Mode Kualitas Rendah
-item-lowq-sub = Aktifkan ini jika UI nge-lag
 item-insecure = Mode insecure
 item-insecure-sub = Aktifkan ini jika Anda tidak dapat menggunakan fungsi online. Membuat koneksi Anda tidak aman!
 
@@ -8,8 +8,6 @@ chart = Chart
 item-lang = 言語
 item-offline = Offline mode
 item-offline-sub = オフラインモードでの記録はアップロードできません
-item-lowq = 低画質モード
-item-lowq-sub = UIがラグい場合に有効化する
 
 item-adjust = 自動時刻
 item-adjust-sub = 音楽とビートマップを同期させるために、動的に時間を調整する
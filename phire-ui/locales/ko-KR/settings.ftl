@@ -14,8 +14,6 @@ item-mp-sub = 멀티플레이어 모드 활성화
 item-mp-addr = 멀티플레이어 서버
 item-mp-addr-sub = 서버 주소, '호스트:포트'
 item-mp-addr-invalid = 잘못된 서버 주소
-item-lowq = 저화질 모드
-item-lowq-sub = UI가 느릴 때 이 기능을 활성화하세요.
 item-insecure = 보안 모드 끄기
 item-insecure-sub = 온라인 기능을 사용할 수 없는 경우 이를 활성화하십시오. 연결이 보안되지 않습니다!
 
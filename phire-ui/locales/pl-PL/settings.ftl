@@ -8,8 +8,6 @@ chart = Beatmapa
 item-lang = Język
 item-offline = Tryb offline
 item-offline-sub = Nie możesz wysłać swojego rekordu w trybie offline
-item-lowq = Niska jakość
-item-lowq-sub = Włącz tą opcje, jeśli UI się zacina
 
 item-adjust = Automatyczne ustawienie opóźnienia
 item-adjust-sub = Ustaw opóźnienie automatycznie, aby zsynchronizować muzykę i beatmapę
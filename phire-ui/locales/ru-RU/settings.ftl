@@ -15,8 +15,6 @@ item-mp-sub = Включить мультиплеер
 item-mp-addr = Сервер мультиплеера
 item-mp-addr-sub = Адрес сервера в формате 'IP-адрес:порт'
 item-mp-addr-invalid = Неверный адрес сервера
-item-lowq = Пониженное качество
-item-lowq-sub = Включите, если интерфейс подлагивает
 item-insecure = Незащищëнный режим
 item-insecure-sub = Включите, если у вас возникают проблемы с подключением. !Делает подключение незащищëнным! 
 
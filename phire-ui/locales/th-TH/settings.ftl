@@ -9,8 +9,6 @@ debug = Debug
 item-lang = ภาษา
 item-offline = โหมด Offline
 item-offline-sub = Score จะไม่บันทึกถ้าเล่นโหมด Offline
-item-lowq = โหมด Low quality
-item-lowq-sub = เปิดตัวนี้ถ้าหน้า UI lag
 
 item-adjust = ปรับเวลาอัตโนมัติ
 item-adjust-sub = ปรับเพลงให้ตรงกับ Chart
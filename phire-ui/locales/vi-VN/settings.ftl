@@ -14,8 +14,6 @@ item-mp-sub = Bật chế độ đa người chơi
 item-mp-addr = Máy chủ đa người chơi
 item-mp-addr-sub = Địa chỉ máy chủ, 'host:port'
 item-mp-addr-invalid = Địa chỉ máy chủ không tồn tại
-item-lowq = Độ hoạ thấp
-item-lowq-sub = Bật khi thấy fps không ổn định.
 item-insecure = Chế độ không an toàn
 item-insecure-sub = Kích hoạt tính năng này nếu bạn không thể sử dụng các chức năng trực tuyến. Làm cho kết nối của bạn không an toàn!
 
@@ -16,8 +16,10 @@ item-mp-sub = 启用多人游戏
 item-mp-addr = 多人游戏服务器
 item-mp-addr-sub = 服务器地址，'主机:端口'
 item-mp-addr-invalid = 无效的服务器地址
-item-lowq = 低画质模式
-item-lowq-sub = 建议在画面卡顿时启用
+item-sample-count = 抗锯齿
+item-sample-count-sub = 数值越高画面越平滑，但性能消耗更大
+sample-count-off = 关闭
+sample-count-unsupported = 此设备不支持 { $requested }x 抗锯齿，已改为 { $supported }x
 item-insecure = 不安全模式
 item-insecure-sub = 当无法使用在线功能时可尝试该功能。这会使得你的连接不安全！
 
@@ -32,15 +34,60 @@ item-cali = 调整延迟
 item-exclusive-audio = 独占音频 (仅 Android)
 item-exclusive-audio-sub = 尝试独占输出设备，可以降低音频延时，但会导致声音无法被录制
 item-audio-compatibility = 音频兼容模式 (仅 Android)
+item-normalize = 音量标准化
+item-normalize-sub = 使不同谱面的音乐响度保持一致
+item-mute-music = 静音音乐
+item-mute-music-sub = 仅使用节拍器与打击音效练习
+item-hold-tick = Hold 节拍音效
+item-hold-tick-sub = 按住 Hold 音符时每拍播放一次音效
+item-metronome = 节拍器
+item-metronome-sub = 在谱面的每一拍播放音效
+item-metronome-sub-division = 节拍器细分
+item-music-filter = 音乐滤波
+item-music-filter-sub = 让音乐变闷以便听清节拍，不影响打击音效
+music-filter-off = 关闭
+music-filter-lowpass = 低通
+music-filter-highpass = 高通
+item-music-filter-cutoff = 截止频率
 
 item-show-acc = 显示实时准度
+item-note-progress = 显示音符进度
 item-dc-pause = 双击暂停
+item-blur-pause = 失去焦点时暂停
+item-blur-pause-sub = 切换到其他窗口或应用时暂停游戏
 item-dhint = 双押提示
 item-dhint-sub = 同时触线的音符将会被高亮
 item-opt = 激进优化
 item-opt-sub = 采用激进的优化策略，提升性能但可能导致部分谱面显示出错
 item-speed = 速度
+item-speed-bpm = 以 BPM 设置练习速度
+item-speed-bpm-sub = 在练习模式中以目标 BPM 设置速度
+item-note-speed = 流速
+item-note-speed-sub = 仅改变音符下落速度，不影响音乐与判定
 item-note-size = 音符大小
+item-note-size-hold = Hold 音符大小
+item-note-size-drag = Drag 音符大小
+item-note-size-click = Tap 音符大小
+item-note-size-flick = Flick 音符大小
+item-bad-note = Bad 音符
+item-bad-note-sub = 判定为 Bad 的音符的显示方式
+bad-note-default = 默认
+bad-note-fade-only = 仅淡出
+bad-note-hidden = 隐藏
+item-combo-display = 连击数字
+item-combo-display-sub = 连击文字上方数字的显示时机，里程碑为每 { $step } 连击
+combo-display-always = 总是
+combo-display-milestones = 里程碑
+combo-display-hidden = 隐藏
+item-touch-debounce = 触摸防抖
+item-touch-debounce-sub = 忽略刚按下就抬起的触摸
+item-touch-dead-zone = 划动死区
+item-touch-dead-zone-sub = 触摸移动超过此距离后才能划动
+item-lenient-position = 任意位置判定
+item-lenient-position-sub = 点击任意位置即可判定最近的音符，成绩不会被记录
+item-forgiving-holds = 宽容长条
+item-forgiving-holds-sub = 在长条结束前不久松手仍算作完成，成绩不会被记录
+item-forgiving-hold-window = 长条松手窗口
 
 item-chart-debug-line = 谱面调试 - 判定线
 item-chart-debug-line-sub = 显示判定线属性
@@ -48,14 +95,83 @@ item-chart-debug-note = 谱面调试 - 音符
 item-chart-debug-note-sub = 显示音符属性
 item-touch-debug = 触摸调试
 item-touch-debug-sub = 游玩过程中显示触摸点
+item-show-fps = 帧率统计
+item-show-fps-sub = 显示帧率与帧时间图表
+item-hold-keep-head = Hold：保留头部
+item-hold-repeat = Hold：重复绘制主体
+item-hold-compact = Hold：紧凑
+hold-flag-pack = 资源包
+hold-flag-on = 开启
+hold-flag-off = 关闭
 item-chart_ratio = 谱面缩放倍率
 item-fade = 音符淡入淡出
 item-fade-sub = 小于 0 时下隐 大于 0 时上隐
+item-bg-dim = 背景暗度
+item-bg-dim-sub = 拖到最左侧以使用谱面设置
+bg-dim-chart = 谱面
+item-bg-blur = 背景模糊
+item-bg-parallax = 背景视差
+item-bg-parallax-sub = 背景随判定线的移动轻微偏移
 item-all-good = 强制 Good 判定
 item-watermark = 水印
 item-combo = COMBO 文字
 item-roman = 罗马模式
 item-chinese = 中文模式
+item-long-number = 过长数字
+item-long-number-sub = 分数与连击数超出显示宽度时的处理方式
+long-number-shrink = 缩小
+long-number-truncate = 截断
+long-number-scroll = 滚动
+item-color-filter = 色觉辅助滤镜
+item-color-filter-sub = 为色觉障碍重新映射画面颜色
+color-filter-none = 无
+color-filter-protanopia = 红色盲
+color-filter-deuteranopia = 绿色盲
+color-filter-tritanopia = 蓝色盲
+item-particle-quality = 粒子效果质量
+item-particle-quality-sub = 打击特效卡顿时可调低
+particle-quality-off = 关闭
+particle-quality-low = 低
+particle-quality-medium = 中
+particle-quality-high = 高
+item-ap-aura = All Perfect 光晕
+item-ap-aura-sub = 全部判定为 Perfect 时在屏幕边缘显示光晕
+item-line-feedback = 判定线反馈
+item-line-feedback-sub = AP 或 FC 时为判定线着色
+item-line-thickness = 判定线粗细
+item-line-glow = 判定线光晕
+item-line-glow-sub = 判定线周围的柔和光晕，在复杂背景上更易辨认
+item-visual-flip = 镜像观看
+item-visual-flip-sub = 观看时镜像显示谱面，不影响判定
+item-combo-break = 断连提示
+item-combo-break-sub = 连击中断时抖动连击数
+item-hit-popup = 快慢提示
+item-hit-popup-sub = Good 判定时显示过早或过晚
+item-hit-ms = 毫秒偏差显示
+item-hit-ms-sub = 以毫秒显示每次点击的偏差，负数为过早，正数为过晚
+item-timing-meter = 判定偏差条
+item-timing-meter-sub = 在底部的刻度条上标出最近点击的偏差，左侧为过早，右侧为过晚
+item-accuracy-target = 目标准确率
+item-accuracy-target-sub = 实时准确率低于目标时发出警告
+item-accuracy-target-off = 关闭
+item-accuracy-target-input = 目标准确率（百分比），留空以关闭
+item-accuracy-target-invalid = 请输入 0 到 100 之间的数字
+item-accuracy-target-pause = 低于目标时暂停
+item-score-mode = 分数计算方式
+item-score-mode-sub = 根据判定计算分数的方式
+score-mode-phigros = Phigros
+score-mode-millions = 百万分制
+score-mode-percentage = 百分比
+item-note-tint-hold = Hold 音符染色
+item-note-tint-drag = Drag 音符染色
+item-note-tint-click = Tap 音符染色
+item-note-tint-flick = Flick 音符染色
+item-note-tint-none = 默认
+item-note-tint-input = 染色颜色（RRGGBB 或 AARRGGBB，留空以重置）
+item-note-tint-invalid = 无效的颜色
+item-color-perfect = Perfect 打击特效颜色
+item-color-good = Good 打击特效颜色
+item-tint-bad = 染色 Bad 音符
 
 load-cali-failed = 加载音频失败
 not-combo = 不能是 COMBO
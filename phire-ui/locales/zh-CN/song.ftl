@@ -28,6 +28,7 @@ delete = 删除
 rate = 评分
 exercise = 练习
 offset = 调整延迟
+practice = 从上次中断处练习
 
 edit-cancel = 取消
 edit-save = 保存
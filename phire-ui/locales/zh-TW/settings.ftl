@@ -16,8 +16,6 @@ item-mp-sub = 啟用多人遊戲
 item-mp-addr = 多人遊戲伺服器
 item-mp-addr-sub = 伺服器地址，'主機位址:埠號'
 item-mp-addr-invalid = 無效的伺服器地址
-item-lowq = 低畫質模式
-item-lowq-sub = 建議在畫面卡頓時啟用
 item-insecure = 不安全模式
 item-insecure-sub = 當無法使用在線功能時可嘗試該功能。這會使得你的連線不安全！
 
@@ -28,6 +28,7 @@ delete = 刪除
 rate = 評分
 exercise = 練習
 offset = 調整延遲
+practice = 從上次中斷處練習
 
 edit-cancel = 取消
 edit-save = 保存
@@ -5,7 +5,7 @@ use crate::{
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use phire::{
-    config::{Config, Mods},
+    config::{ChartPreset, Config, Mods},
     info::ChartInfo,
     scene::SimpleRecord,
 };
@@ -57,6 +57,10 @@ pub struct LocalChart {
     pub record: Option<SimpleRecord>,
     #[serde(default)]
     pub mods: Mods,
+    #[serde(default)]
+    pub resume_time: Option<f32>,
+    #[serde(default)]
+    pub preset: Option<ChartPreset>,
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -98,6 +102,8 @@ impl Data {
                     local_path: filename,
                     record: None,
                     mods: Mods::default(),
+                    resume_time: None,
+                    preset: None,
                 });
             }
         }
@@ -121,6 +127,8 @@ impl Data {
                     local_path: filename,
                     record: None,
                     mods: Mods::default(),
+                    resume_time: None,
+                    preset: None,
                 });
             }
         }
@@ -130,7 +138,7 @@ impl Data {
                 *res_pack_path = "chart.zip".to_owned();
             }
         }
-        self.config.init();
+        self.config.validate_and_migrate();
         Ok(())
     }
 
@@ -297,6 +297,7 @@ pub extern "C" fn quad_main() {
 }
 
 fn on_pause_resume(pause: bool) {
+    phire::scene::set_window_focused(!pause);
     if let Some(tx) = MESSAGES_TX.lock().unwrap().as_mut() {
         let _ = tx.send(pause);
     }
@@ -5,6 +5,8 @@ use crate::{get_data, get_data_mut, popup::ChooseButton, save_data, scene::BGM_V
 use anyhow::Result;
 use macroquad::prelude::*;
 use phire::{
+    config::{AudioFilter, BadNoteStyle, ColorFilter, ComboDisplay, LongNumberMode, ParticleQuality, ScoreMode},
+    core::supported_sample_count,
     ext::{poll_future, semi_black, validate_combo, LocalTask, RectExt, SafeTexture, ScaleType},
     l10n::{LanguageIdentifier, LANG_IDENTS, LANG_NAMES},
     scene::{request_input, return_input, show_error, show_message, take_input},
@@ -13,6 +15,8 @@ use phire::{
 use std::{borrow::Cow, net::ToSocketAddrs, sync::atomic::Ordering};
 
 const ITEM_HEIGHT: f32 = 0.15;
+const METRONOME_SUBDIVISIONS: [u32; 3] = [1, 2, 4];
+const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum SettingListType {
@@ -265,6 +269,19 @@ fn render_switch(ui: &mut Ui, r: Rect, t: f32, c: Color, btn: &mut DRectButton,
     btn.render_text(ui, r, t, c.a, if on { ttl!("switch-on") } else { ttl!("switch-off") }, 0.5, on);
 }
 
+/// Parses `RRGGBB` or `AARRGGBB`, with an optional `#`. Empty input resets the color.
+fn parse_color_input(text: &str) -> Option<Option<u32>> {
+    let text = text.trim().trim_start_matches('#');
+    if text.is_empty() {
+        return Some(None);
+    }
+    match u32::from_str_radix(text, 16) {
+        Ok(value) if text.len() == 6 => Some(Some(0xff000000 | value)),
+        Ok(value) if text.len() == 8 => Some(Some(value)),
+        _ => None,
+    }
+}
+
 #[inline]
 fn right_rect(w: f32) -> Rect {
     let rh = ITEM_HEIGHT * 2. / 3.;
@@ -278,7 +295,7 @@ struct GeneralList {
     offline_btn: DRectButton,
     mp_btn: DRectButton,
     mp_addr_btn: DRectButton,
-    lowq_btn: DRectButton,
+    sample_count_btn: ChooseButton,
     insecure_btn: DRectButton,
 }
 
@@ -300,7 +317,19 @@ impl GeneralList {
             offline_btn: DRectButton::new(),
             mp_btn: DRectButton::new(),
             mp_addr_btn: DRectButton::new(),
-            lowq_btn: DRectButton::new(),
+            sample_count_btn: ChooseButton::new()
+                .with_options(
+                    SAMPLE_COUNTS
+                        .iter()
+                        .map(|it| if *it == 1 { tl!("sample-count-off").into_owned() } else { format!("{it}x") })
+                        .collect(),
+                )
+                .with_selected(
+                    SAMPLE_COUNTS
+                        .iter()
+                        .position(|it| *it == get_data().config.sample_count)
+                        .unwrap_or_default(),
+                ),
             insecure_btn: DRectButton::new(),
         }
     }
@@ -309,6 +338,9 @@ impl GeneralList {
         if self.lang_btn.top_touch(touch, t) {
             return true;
         }
+        if self.sample_count_btn.top_touch(touch, t) {
+            return true;
+        }
         false
     }
 
@@ -330,9 +362,8 @@ impl GeneralList {
             request_input("mp_addr", &config.mp_address, tl!("item-mp-addr"));
             return Ok(Some(true));
         }
-        if self.lowq_btn.touch(touch, t) {
-            config.sample_count = if config.sample_count == 1 { 2 } else { 1 };
-            return Ok(Some(true));
+        if self.sample_count_btn.touch(touch, t) {
+            return Ok(Some(false));
         }
         if self.insecure_btn.touch(touch, t) {
             data.accept_invalid_cert ^= true;
@@ -349,6 +380,18 @@ impl GeneralList {
             sync_data();
             return Ok(true);
         }
+        self.sample_count_btn.update(t);
+        if self.sample_count_btn.changed() {
+            let requested = SAMPLE_COUNTS[self.sample_count_btn.selected()];
+            let supported = supported_sample_count(requested);
+            if supported != requested {
+                show_message(tl!("sample-count-unsupported", "requested" => requested, "supported" => supported)).error();
+                self.sample_count_btn
+                    .set_selected(SAMPLE_COUNTS.iter().position(|it| *it == supported).unwrap_or_default());
+            }
+            data.config.sample_count = supported;
+            return Ok(true);
+        }
         if let Some((id, text)) = take_input() {
             if id == "mp_addr" {
                 if let Err(err) = text.to_socket_addrs() {
@@ -399,14 +442,15 @@ impl GeneralList {
             self.mp_addr_btn.render_text(ui, rr, t, c.a, &config.mp_address, 0.4, false);
         }
         item! {
-            render_title(ui, c, tl!("item-lowq"), Some(tl!("item-lowq-sub")));
-            render_switch(ui, rr, t, c, &mut self.lowq_btn, config.sample_count == 1);
+            render_title(ui, c, tl!("item-sample-count"), Some(tl!("item-sample-count-sub")));
+            self.sample_count_btn.render(ui, rr, t, c.a);
         }
         item! {
             render_title(ui, c, tl!("item-insecure"), Some(tl!("item-insecure-sub")));
             render_switch(ui, rr, t, c, &mut self.insecure_btn, data.accept_invalid_cert);
         }
         self.lang_btn.render_top(ui, t, c.a);
+        self.sample_count_btn.render_top(ui, t, c.a);
         (w, h)
     }
 }
@@ -417,6 +461,14 @@ struct AudioList {
     sfx_slider: Slider,
     bgm_slider: Slider,
     audio_compatibility_btn: DRectButton,
+    normalize_btn: DRectButton,
+    mute_music_btn: DRectButton,
+    hold_tick_btn: DRectButton,
+    metronome_btn: DRectButton,
+    metronome_sub_btn: ChooseButton,
+    music_filter_btn: ChooseButton,
+    filter_cutoff_slider: Slider,
+    filter_cutoff: f32,
     cali_btn: DRectButton,
 
     cali_task: LocalTask<Result<OffsetPage>>,
@@ -431,6 +483,32 @@ impl AudioList {
             sfx_slider: Slider::new(0.0..2.0, 0.05),
             bgm_slider: Slider::new(0.0..2.0, 0.05),
             audio_compatibility_btn: DRectButton::new(),
+            normalize_btn: DRectButton::new(),
+            mute_music_btn: DRectButton::new(),
+            hold_tick_btn: DRectButton::new(),
+            metronome_btn: DRectButton::new(),
+            metronome_sub_btn: ChooseButton::new()
+                .with_options(METRONOME_SUBDIVISIONS.iter().map(|it| format!("1/{it}")).collect())
+                .with_selected(
+                    METRONOME_SUBDIVISIONS
+                        .iter()
+                        .position(|it| *it == get_data().config.metronome_subdivision)
+                        .unwrap_or_default(),
+                ),
+            music_filter_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("music-filter-off"), tl!("music-filter-lowpass"), tl!("music-filter-highpass")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(match get_data().config.music_filter {
+                    None => 0,
+                    Some(AudioFilter::LowPass(_)) => 1,
+                    Some(AudioFilter::HighPass(_)) => 2,
+                }),
+            filter_cutoff_slider: Slider::new(50.0..5000.0, 50.),
+            filter_cutoff: get_data().config.music_filter.map_or(800., |it| it.cutoff()),
             cali_btn: DRectButton::new(),
 
             cali_task: None,
@@ -438,13 +516,25 @@ impl AudioList {
         }
     }
 
-    pub fn top_touch(&mut self, _touch: &Touch, _t: f32) -> bool {
+    pub fn top_touch(&mut self, touch: &Touch, t: f32) -> bool {
+        if self.metronome_sub_btn.top_touch(touch, t) {
+            return true;
+        }
+        if self.music_filter_btn.top_touch(touch, t) {
+            return true;
+        }
         false
     }
 
     pub fn touch(&mut self, touch: &Touch, t: f32) -> Result<Option<bool>> {
         let data = get_data_mut();
         let config = &mut data.config;
+        if self.metronome_sub_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if self.music_filter_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
         if self.adjust_btn.touch(touch, t) {
             config.adjust_time ^= true;
             return Ok(Some(true));
@@ -466,6 +556,26 @@ impl AudioList {
             config.audio_compatibility ^= true;
             return Ok(Some(true));
         }
+        if self.normalize_btn.touch(touch, t) {
+            config.normalize_audio ^= true;
+            return Ok(Some(true));
+        }
+        if self.mute_music_btn.touch(touch, t) {
+            config.mute_music ^= true;
+            return Ok(Some(true));
+        }
+        if self.hold_tick_btn.touch(touch, t) {
+            config.hold_tick_sound ^= true;
+            return Ok(Some(true));
+        }
+        if self.metronome_btn.touch(touch, t) {
+            config.metronome ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.filter_cutoff_slider.touch(touch, t, &mut self.filter_cutoff) {
+            config.music_filter = config.music_filter.map(|it| it.with_cutoff(self.filter_cutoff));
+            return Ok(wt);
+        }
         if self.cali_btn.touch(touch, t) {
             self.cali_task = Some(Box::pin(OffsetPage::new()));
             return Ok(Some(false));
@@ -473,7 +583,21 @@ impl AudioList {
         Ok(None)
     }
 
-    pub fn update(&mut self, _t: f32) -> Result<bool> {
+    pub fn update(&mut self, t: f32) -> Result<bool> {
+        self.metronome_sub_btn.update(t);
+        if self.metronome_sub_btn.changed() {
+            get_data_mut().config.metronome_subdivision = METRONOME_SUBDIVISIONS[self.metronome_sub_btn.selected()];
+            return Ok(true);
+        }
+        self.music_filter_btn.update(t);
+        if self.music_filter_btn.changed() {
+            get_data_mut().config.music_filter = match self.music_filter_btn.selected() {
+                1 => Some(AudioFilter::LowPass(self.filter_cutoff)),
+                2 => Some(AudioFilter::HighPass(self.filter_cutoff)),
+                _ => None,
+            };
+            return Ok(true);
+        }
         if let Some(task) = &mut self.cali_task {
             if let Some(res) = poll_future(task.as_mut()) {
                 match res {
@@ -522,10 +646,40 @@ impl AudioList {
             render_title(ui, c, tl!("item-audio-compatibility"), None);
             render_switch(ui, rr, t, c, &mut self.audio_compatibility_btn, config.audio_compatibility);
         }
+        item! {
+            render_title(ui, c, tl!("item-normalize"), Some(tl!("item-normalize-sub")));
+            render_switch(ui, rr, t, c, &mut self.normalize_btn, config.normalize_audio);
+        }
+        item! {
+            render_title(ui, c, tl!("item-mute-music"), Some(tl!("item-mute-music-sub")));
+            render_switch(ui, rr, t, c, &mut self.mute_music_btn, config.mute_music);
+        }
+        item! {
+            render_title(ui, c, tl!("item-hold-tick"), Some(tl!("item-hold-tick-sub")));
+            render_switch(ui, rr, t, c, &mut self.hold_tick_btn, config.hold_tick_sound);
+        }
+        item! {
+            render_title(ui, c, tl!("item-metronome"), Some(tl!("item-metronome-sub")));
+            render_switch(ui, rr, t, c, &mut self.metronome_btn, config.metronome);
+        }
+        item! {
+            render_title(ui, c, tl!("item-metronome-sub-division"), None);
+            self.metronome_sub_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-music-filter"), Some(tl!("item-music-filter-sub")));
+            self.music_filter_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-music-filter-cutoff"), None);
+            self.filter_cutoff_slider.render(ui, rr, t, c, self.filter_cutoff, format!("{:.0}Hz", self.filter_cutoff));
+        }
         item! {
             render_title(ui, c, tl!("item-cali"), None);
             self.cali_btn.render_text(ui, rr, t, c.a, format!("{:.0}ms", config.offset * 1000.), 0.5, true);
         }
+        self.metronome_sub_btn.render_top(ui, t, c.a);
+        self.music_filter_btn.render_top(ui, t, c.a);
         (w, h)
     }
 
@@ -536,26 +690,80 @@ impl AudioList {
 
 struct ChartList {
     show_acc_btn: DRectButton,
+    note_progress_btn: DRectButton,
     dc_pause_btn: DRectButton,
+    blur_pause_btn: DRectButton,
     dhint_btn: DRectButton,
     opt_btn: DRectButton,
     speed_slider: Slider,
+    speed_bpm_btn: DRectButton,
+    note_speed_slider: Slider,
     size_slider: Slider,
+    kind_size_sliders: [Slider; 4],
+    bad_note_btn: ChooseButton,
+    combo_display_btn: ChooseButton,
+    debounce_slider: Slider,
+    dead_zone_slider: Slider,
+    lenient_btn: DRectButton,
+    forgiving_holds_btn: DRectButton,
+    hold_window_slider: Slider,
 }
 
 impl ChartList {
     pub fn new() -> Self {
         Self {
             show_acc_btn: DRectButton::new(),
+            note_progress_btn: DRectButton::new(),
             dc_pause_btn: DRectButton::new(),
+            blur_pause_btn: DRectButton::new(),
             dhint_btn: DRectButton::new(),
             opt_btn: DRectButton::new(),
             speed_slider: Slider::new(0.5..2., 0.05),
+            speed_bpm_btn: DRectButton::new(),
+            note_speed_slider: Slider::new(0.5..3., 0.05),
             size_slider: Slider::new(0.8..1.2, 0.005),
+            kind_size_sliders: [(); 4].map(|_| Slider::new(0.5..1.5, 0.05)),
+            bad_note_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("bad-note-default"), tl!("bad-note-fade-only"), tl!("bad-note-hidden")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(
+                    BadNoteStyle::ALL
+                        .iter()
+                        .position(|it| *it == get_data().config.bad_note_style)
+                        .unwrap_or_default(),
+                ),
+            combo_display_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("combo-display-always"), tl!("combo-display-milestones"), tl!("combo-display-hidden")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(
+                    ComboDisplay::ALL
+                        .iter()
+                        .position(|it| *it == get_data().config.combo_display_mode)
+                        .unwrap_or_default(),
+                ),
+            debounce_slider: Slider::new(0.0..100.0, 5.),
+            dead_zone_slider: Slider::new(0.0..0.1, 0.005),
+            lenient_btn: DRectButton::new(),
+            forgiving_holds_btn: DRectButton::new(),
+            hold_window_slider: Slider::new(0.2..0.6, 0.02),
         }
     }
 
-    pub fn top_touch(&mut self, _touch: &Touch, _t: f32) -> bool {
+    pub fn top_touch(&mut self, touch: &Touch, t: f32) -> bool {
+        if self.bad_note_btn.top_touch(touch, t) {
+            return true;
+        }
+        if self.combo_display_btn.top_touch(touch, t) {
+            return true;
+        }
         false
     }
 
@@ -566,10 +774,18 @@ impl ChartList {
             config.show_acc ^= true;
             return Ok(Some(true));
         }
+        if self.note_progress_btn.touch(touch, t) {
+            config.show_note_progress ^= true;
+            return Ok(Some(true));
+        }
         if self.dc_pause_btn.touch(touch, t) {
             config.double_click_to_pause ^= true;
             return Ok(Some(true));
         }
+        if self.blur_pause_btn.touch(touch, t) {
+            config.pause_on_blur ^= true;
+            return Ok(Some(true));
+        }
         if self.dhint_btn.touch(touch, t) {
             config.double_hint ^= true;
             return Ok(Some(true));
@@ -581,13 +797,58 @@ impl ChartList {
         if let wt @ Some(_) = self.speed_slider.touch(touch, t, &mut config.speed) {
             return Ok(wt);
         }
+        if self.speed_bpm_btn.touch(touch, t) {
+            config.speed_as_bpm ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.note_speed_slider.touch(touch, t, &mut config.note_speed_mult) {
+            return Ok(wt);
+        }
         if let wt @ Some(_) = self.size_slider.touch(touch, t, &mut config.note_scale) {
             return Ok(wt);
         }
+        for (slider, scale) in self.kind_size_sliders.iter_mut().zip(config.note_scale_per_kind.iter_mut()) {
+            if let wt @ Some(_) = slider.touch(touch, t, scale) {
+                return Ok(wt);
+            }
+        }
+        if self.bad_note_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if self.combo_display_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if let wt @ Some(_) = self.debounce_slider.touch(touch, t, &mut config.touch_debounce_ms) {
+            return Ok(wt);
+        }
+        if let wt @ Some(_) = self.dead_zone_slider.touch(touch, t, &mut config.touch_dead_zone) {
+            return Ok(wt);
+        }
+        if self.lenient_btn.touch(touch, t) {
+            config.lenient_position ^= true;
+            return Ok(Some(true));
+        }
+        if self.forgiving_holds_btn.touch(touch, t) {
+            config.forgiving_holds ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.hold_window_slider.touch(touch, t, &mut config.forgiving_hold_window) {
+            return Ok(wt);
+        }
         Ok(None)
     }
 
-    pub fn update(&mut self, _t: f32) -> Result<bool> {
+    pub fn update(&mut self, t: f32) -> Result<bool> {
+        self.bad_note_btn.update(t);
+        if self.bad_note_btn.changed() {
+            get_data_mut().config.bad_note_style = BadNoteStyle::ALL[self.bad_note_btn.selected()];
+            return Ok(true);
+        }
+        self.combo_display_btn.update(t);
+        if self.combo_display_btn.changed() {
+            get_data_mut().config.combo_display_mode = ComboDisplay::ALL[self.combo_display_btn.selected()];
+            return Ok(true);
+        }
         Ok(false)
     }
 
@@ -609,10 +870,18 @@ impl ChartList {
             render_title(ui, c, tl!("item-show-acc"), None);
             render_switch(ui, rr, t, c, &mut self.show_acc_btn, config.show_acc);
         }
+        item! {
+            render_title(ui, c, tl!("item-note-progress"), None);
+            render_switch(ui, rr, t, c, &mut self.note_progress_btn, config.show_note_progress);
+        }
         item! {
             render_title(ui, c, tl!("item-dc-pause"), None);
             render_switch(ui, rr, t, c, &mut self.dc_pause_btn, config.double_click_to_pause);
         }
+        item! {
+            render_title(ui, c, tl!("item-blur-pause"), Some(tl!("item-blur-pause-sub")));
+            render_switch(ui, rr, t, c, &mut self.blur_pause_btn, config.pause_on_blur);
+        }
         item! {
             render_title(ui, c, tl!("item-dhint"), Some(tl!("item-dhint-sub")));
             render_switch(ui, rr, t, c, &mut self.dhint_btn, config.double_hint);
@@ -625,10 +894,61 @@ impl ChartList {
             render_title(ui, c, tl!("item-speed"), None);
             self.speed_slider.render(ui, rr, t,c, config.speed, format!("{:.2}", config.speed));
         }
+        item! {
+            render_title(ui, c, tl!("item-speed-bpm"), Some(tl!("item-speed-bpm-sub")));
+            render_switch(ui, rr, t, c, &mut self.speed_bpm_btn, config.speed_as_bpm);
+        }
+        item! {
+            render_title(ui, c, tl!("item-note-speed"), Some(tl!("item-note-speed-sub")));
+            self.note_speed_slider.render(ui, rr, t, c, config.note_speed_mult, format!("{:.2}x", config.note_speed_mult));
+        }
         item! {
             render_title(ui, c, tl!("item-note-size"), None);
             self.size_slider.render(ui, rr, t,c, config.note_scale, format!("{:.3}", config.note_scale));
         }
+        for (order, (slider, title)) in self
+            .kind_size_sliders
+            .iter_mut()
+            .zip([tl!("item-note-size-hold"), tl!("item-note-size-drag"), tl!("item-note-size-click"), tl!("item-note-size-flick")])
+            .enumerate()
+        {
+            item! {
+                render_title(ui, c, title, None);
+                let scale = config.note_scale_per_kind[order];
+                slider.render(ui, rr, t, c, scale, format!("{scale:.2}x"));
+            }
+        }
+        item! {
+            render_title(ui, c, tl!("item-bad-note"), Some(tl!("item-bad-note-sub")));
+            self.bad_note_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-combo-display"), Some(tl!("item-combo-display-sub", "step" => config.combo_display_step)));
+            self.combo_display_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-touch-debounce"), Some(tl!("item-touch-debounce-sub")));
+            self.debounce_slider.render(ui, rr, t, c, config.touch_debounce_ms, format!("{:.0}ms", config.touch_debounce_ms));
+        }
+        item! {
+            render_title(ui, c, tl!("item-touch-dead-zone"), Some(tl!("item-touch-dead-zone-sub")));
+            self.dead_zone_slider.render(ui, rr, t, c, config.touch_dead_zone, format!("{:.3}", config.touch_dead_zone));
+        }
+        item! {
+            render_title(ui, c, tl!("item-lenient-position"), Some(tl!("item-lenient-position-sub")));
+            render_switch(ui, rr, t, c, &mut self.lenient_btn, config.lenient_position);
+        }
+        item! {
+            render_title(ui, c, tl!("item-forgiving-holds"), Some(tl!("item-forgiving-holds-sub")));
+            render_switch(ui, rr, t, c, &mut self.forgiving_holds_btn, config.forgiving_holds);
+        }
+        item! {
+            render_title(ui, c, tl!("item-forgiving-hold-window"), None);
+            self.hold_window_slider
+                .render(ui, rr, t, c, config.forgiving_hold_window, format!("{:.0}ms", config.forgiving_hold_window * 1000.));
+        }
+        self.bad_note_btn.render_top(ui, t, c.a);
+        self.combo_display_btn.render_top(ui, t, c.a);
         (w, h)
     }
 }
@@ -637,12 +957,36 @@ struct OtherList {
     chart_debug_line_slider: Slider,
     chart_debug_note_slider: Slider,
     touch_debug_btn: DRectButton,
+    show_fps_btn: DRectButton,
+    hold_flag_btns: [ChooseButton; 3],
     chart_ratio_slider: Slider,
     fade_slider: Slider,
+    bg_dim_slider: Slider,
+    bg_blur_btn: DRectButton,
+    bg_parallax_slider: Slider,
+    particle_quality_btn: ChooseButton,
+    ap_aura_btn: DRectButton,
+    line_feedback_btn: DRectButton,
+    line_thickness_slider: Slider,
+    line_glow_slider: Slider,
+    visual_flip_btn: DRectButton,
+    combo_break_btn: DRectButton,
+    hit_popup_btn: DRectButton,
+    hit_ms_btn: DRectButton,
+    timing_meter_btn: DRectButton,
+    accuracy_target_btn: DRectButton,
+    accuracy_target_pause_btn: DRectButton,
+    score_mode_btn: ChooseButton,
     watermark: DRectButton,
     combo_btn: DRectButton,
     roman_btn: DRectButton,
     chinese_btn: DRectButton,
+    long_number_btn: ChooseButton,
+    color_filter_btn: ChooseButton,
+    note_tint_btns: [DRectButton; 4],
+    tint_bad_btn: DRectButton,
+    color_perfect_btn: DRectButton,
+    color_good_btn: DRectButton,
 }
 
 impl OtherList {
@@ -651,22 +995,136 @@ impl OtherList {
             chart_debug_line_slider: Slider::new(0.0..1.0, 0.05),
             chart_debug_note_slider: Slider::new(0.0..1.0, 0.05),
             touch_debug_btn: DRectButton::new(),
+            show_fps_btn: DRectButton::new(),
+            hold_flag_btns: {
+                let config = &get_data().config;
+                [config.hold_keep_head, config.hold_repeat, config.hold_compact].map(|flag| {
+                    ChooseButton::new()
+                        .with_options(
+                            [tl!("hold-flag-pack"), tl!("hold-flag-on"), tl!("hold-flag-off")]
+                                .into_iter()
+                                .map(|it| it.into_owned())
+                                .collect(),
+                        )
+                        .with_selected(match flag {
+                            None => 0,
+                            Some(true) => 1,
+                            Some(false) => 2,
+                        })
+                })
+            },
             chart_ratio_slider: Slider::new(0.05..1.0, 0.05),
             fade_slider: Slider::new(-2.0..2.0, 0.05),
+            // values below zero keep the chart's dim
+            bg_dim_slider: Slider::new(-0.05..1.0, 0.05),
+            bg_blur_btn: DRectButton::new(),
+            bg_parallax_slider: Slider::new(0.0..1.0, 0.05),
+            particle_quality_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("particle-quality-off"), tl!("particle-quality-low"), tl!("particle-quality-medium"), tl!("particle-quality-high")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(
+                    ParticleQuality::ALL
+                        .iter()
+                        .position(|it| *it == get_data().config.particle_quality)
+                        .unwrap_or_default(),
+                ),
+            ap_aura_btn: DRectButton::new(),
+            line_feedback_btn: DRectButton::new(),
+            line_thickness_slider: Slider::new(0.5..4.0, 0.1),
+            line_glow_slider: Slider::new(0.0..2.0, 0.1),
+            visual_flip_btn: DRectButton::new(),
+            combo_break_btn: DRectButton::new(),
+            hit_popup_btn: DRectButton::new(),
+            hit_ms_btn: DRectButton::new(),
+            timing_meter_btn: DRectButton::new(),
+            accuracy_target_btn: DRectButton::new(),
+            accuracy_target_pause_btn: DRectButton::new(),
+            score_mode_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("score-mode-phigros"), tl!("score-mode-millions"), tl!("score-mode-percentage")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(ScoreMode::ALL.iter().position(|it| *it == get_data().config.score_mode).unwrap_or_default()),
             watermark: DRectButton::new(),
             combo_btn: DRectButton::new(),
             roman_btn: DRectButton::new(),
             chinese_btn: DRectButton::new(),
+            long_number_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("long-number-shrink"), tl!("long-number-truncate"), tl!("long-number-scroll")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(
+                    LongNumberMode::ALL
+                        .iter()
+                        .position(|it| *it == get_data().config.long_number_mode)
+                        .unwrap_or_default(),
+                ),
+            color_filter_btn: ChooseButton::new()
+                .with_options(
+                    [tl!("color-filter-none"), tl!("color-filter-protanopia"), tl!("color-filter-deuteranopia"), tl!("color-filter-tritanopia")]
+                        .into_iter()
+                        .map(|it| it.into_owned())
+                        .collect(),
+                )
+                .with_selected(
+                    ColorFilter::ALL
+                        .iter()
+                        .position(|it| *it == get_data().config.color_filter)
+                        .unwrap_or_default(),
+                ),
+            note_tint_btns: [(); 4].map(|_| DRectButton::new()),
+            tint_bad_btn: DRectButton::new(),
+            color_perfect_btn: DRectButton::new(),
+            color_good_btn: DRectButton::new(),
         }
     }
 
-    pub fn top_touch(&mut self, _touch: &Touch, _t: f32) -> bool {
+    pub fn top_touch(&mut self, touch: &Touch, t: f32) -> bool {
+        if self.color_filter_btn.top_touch(touch, t) {
+            return true;
+        }
+        if self.hold_flag_btns.iter_mut().any(|it| it.top_touch(touch, t)) {
+            return true;
+        }
+        if self.particle_quality_btn.top_touch(touch, t) {
+            return true;
+        }
+        if self.score_mode_btn.top_touch(touch, t) {
+            return true;
+        }
+        if self.long_number_btn.top_touch(touch, t) {
+            return true;
+        }
         false
     }
 
     pub fn touch(&mut self, touch: &Touch, t: f32) -> Result<Option<bool>> {
         let data = get_data_mut();
         let config = &mut data.config;
+        if self.color_filter_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if self.long_number_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if self.hold_flag_btns.iter_mut().any(|it| it.touch(touch, t)) {
+            return Ok(Some(false));
+        }
+        if self.particle_quality_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
+        if self.score_mode_btn.touch(touch, t) {
+            return Ok(Some(false));
+        }
         if let wt @ Some(_) = self.chart_debug_line_slider.touch(touch, t, &mut config.chart_debug_line) {
             return Ok(wt);
         }
@@ -677,9 +1135,71 @@ impl OtherList {
             config.touch_debug ^= true;
             return Ok(Some(true));
         }
+        if self.show_fps_btn.touch(touch, t) {
+            config.show_fps ^= true;
+            return Ok(Some(true));
+        }
+        if self.ap_aura_btn.touch(touch, t) {
+            config.ap_aura ^= true;
+            return Ok(Some(true));
+        }
+        if self.line_feedback_btn.touch(touch, t) {
+            config.judge_line_feedback ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.line_thickness_slider.touch(touch, t, &mut config.judge_line_thickness) {
+            return Ok(wt);
+        }
+        if let wt @ Some(_) = self.line_glow_slider.touch(touch, t, &mut config.judge_line_glow) {
+            return Ok(wt);
+        }
+        if self.visual_flip_btn.touch(touch, t) {
+            config.visual_flip_x ^= true;
+            return Ok(Some(true));
+        }
+        if self.combo_break_btn.touch(touch, t) {
+            config.combo_break_feedback ^= true;
+            return Ok(Some(true));
+        }
+        if self.hit_popup_btn.touch(touch, t) {
+            config.hit_error_popup ^= true;
+            return Ok(Some(true));
+        }
+        if self.hit_ms_btn.touch(touch, t) {
+            config.show_hit_ms ^= true;
+            return Ok(Some(true));
+        }
+        if self.timing_meter_btn.touch(touch, t) {
+            config.timing_meter ^= true;
+            return Ok(Some(true));
+        }
+        if self.accuracy_target_btn.touch(touch, t) {
+            request_input(
+                "accuracy_target",
+                &config.accuracy_target.map(|it| format!("{:.2}", it * 100.)).unwrap_or_default(),
+                tl!("item-accuracy-target-input"),
+            );
+            return Ok(Some(true));
+        }
+        if self.accuracy_target_pause_btn.touch(touch, t) {
+            config.accuracy_target_pause ^= true;
+            return Ok(Some(true));
+        }
         if let wt @ Some(_) = self.chart_ratio_slider.touch(touch, t, &mut config.chart_ratio) {
             return Ok(wt);
         }
+        let mut bg_dim = config.background_dim.unwrap_or(-0.05);
+        if let wt @ Some(_) = self.bg_dim_slider.touch(touch, t, &mut bg_dim) {
+            config.background_dim = (bg_dim >= 0.).then_some(bg_dim);
+            return Ok(wt);
+        }
+        if self.bg_blur_btn.touch(touch, t) {
+            config.blur_background ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.bg_parallax_slider.touch(touch, t, &mut config.bg_parallax) {
+            return Ok(wt);
+        }
         if let wt @ Some(_) = self.fade_slider.touch(touch, t, &mut config.fade) {
             return Ok(wt);
         }
@@ -705,11 +1225,65 @@ impl OtherList {
             }
             return Ok(Some(true));
         }
+        for (order, btn) in self.note_tint_btns.iter_mut().enumerate() {
+            if btn.touch(touch, t) {
+                request_input(
+                    &format!("note_tint_{order}"),
+                    &config.note_tints[order].map(|it| format!("{it:08x}")).unwrap_or_default(),
+                    tl!("item-note-tint-input"),
+                );
+                return Ok(Some(true));
+            }
+        }
+        if self.tint_bad_btn.touch(touch, t) {
+            config.tint_bad_notes ^= true;
+            return Ok(Some(true));
+        }
+        for (id, btn, color) in [
+            ("color_perfect", &mut self.color_perfect_btn, config.color_perfect),
+            ("color_good", &mut self.color_good_btn, config.color_good),
+        ] {
+            if btn.touch(touch, t) {
+                request_input(id, &color.map(|it| format!("{it:08x}")).unwrap_or_default(), tl!("item-note-tint-input"));
+                return Ok(Some(true));
+            }
+        }
         Ok(None)
     }
 
-    pub fn update(&mut self, _t: f32) -> Result<bool> {
+    pub fn update(&mut self, t: f32) -> Result<bool> {
+        self.color_filter_btn.update(t);
         let data = get_data_mut();
+        if self.color_filter_btn.changed() {
+            data.config.color_filter = ColorFilter::ALL[self.color_filter_btn.selected()];
+            return Ok(true);
+        }
+        for (btn, flag) in self
+            .hold_flag_btns
+            .iter_mut()
+            .zip([&mut data.config.hold_keep_head, &mut data.config.hold_repeat, &mut data.config.hold_compact])
+        {
+            btn.update(t);
+            if btn.changed() {
+                *flag = [None, Some(true), Some(false)][btn.selected()];
+                return Ok(true);
+            }
+        }
+        self.particle_quality_btn.update(t);
+        if self.particle_quality_btn.changed() {
+            data.config.particle_quality = ParticleQuality::ALL[self.particle_quality_btn.selected()];
+            return Ok(true);
+        }
+        self.score_mode_btn.update(t);
+        if self.score_mode_btn.changed() {
+            data.config.score_mode = ScoreMode::ALL[self.score_mode_btn.selected()];
+            return Ok(true);
+        }
+        self.long_number_btn.update(t);
+        if self.long_number_btn.changed() {
+            data.config.long_number_mode = LongNumberMode::ALL[self.long_number_btn.selected()];
+            return Ok(true);
+        }
         if let Some((id, text)) = take_input() {
             if id == "watermark" {
                 data.config.watermark = text;
@@ -730,6 +1304,48 @@ impl OtherList {
                 return_input(id, text);
             }
         }
+        if let Some((id, text)) = take_input() {
+            if id == "accuracy_target" {
+                let text = text.trim().trim_end_matches('%');
+                if text.is_empty() {
+                    data.config.accuracy_target = None;
+                    return Ok(true);
+                }
+                match text.parse::<f32>() {
+                    Ok(value) if (0.0..=100.0).contains(&value) => {
+                        data.config.accuracy_target = Some(value / 100.);
+                        return Ok(true);
+                    }
+                    _ => {
+                        show_message(tl!("item-accuracy-target-invalid")).error();
+                        return Ok(false);
+                    }
+                }
+            } else {
+                return_input(id, text);
+            }
+        }
+        if let Some((id, text)) = take_input() {
+            let slot = if let Some(order) = id.strip_prefix("note_tint_").and_then(|it| it.parse::<usize>().ok()) {
+                Some(&mut data.config.note_tints[order])
+            } else if id == "color_perfect" {
+                Some(&mut data.config.color_perfect)
+            } else if id == "color_good" {
+                Some(&mut data.config.color_good)
+            } else {
+                None
+            };
+            if let Some(slot) = slot {
+                let Some(color) = parse_color_input(&text) else {
+                    show_message(tl!("item-note-tint-invalid")).error();
+                    return Ok(false);
+                };
+                *slot = color;
+                return Ok(true);
+            } else {
+                return_input(id, text);
+            }
+        }
         Ok(false)
     }
 
@@ -759,14 +1375,90 @@ impl OtherList {
             render_title(ui, c, tl!("item-touch-debug"), Some(tl!("item-touch-debug-sub")));
             render_switch(ui, rr, t, c, &mut self.touch_debug_btn, config.touch_debug);
         }
+        item! {
+            render_title(ui, c, tl!("item-show-fps"), Some(tl!("item-show-fps-sub")));
+            render_switch(ui, rr, t, c, &mut self.show_fps_btn, config.show_fps);
+        }
+        for (btn, title) in self
+            .hold_flag_btns
+            .iter_mut()
+            .zip([tl!("item-hold-keep-head"), tl!("item-hold-repeat"), tl!("item-hold-compact")])
+        {
+            item! {
+                render_title(ui, c, title, None);
+                btn.render(ui, rr, t, c.a);
+            }
+        }
         item! {
             render_title(ui, c, tl!("item-chart_ratio"), None);
             self.chart_ratio_slider.render(ui, rr, t,c, config.chart_ratio, format!("{:.2}", config.chart_ratio));
         }
+        item! {
+            render_title(ui, c, tl!("item-bg-dim"), Some(tl!("item-bg-dim-sub")));
+            let text = config.background_dim.map_or_else(|| tl!("bg-dim-chart").into_owned(), |it| format!("{it:.2}"));
+            self.bg_dim_slider.render(ui, rr, t, c, config.background_dim.unwrap_or(-0.05), text);
+        }
+        item! {
+            render_title(ui, c, tl!("item-bg-blur"), None);
+            render_switch(ui, rr, t, c, &mut self.bg_blur_btn, config.blur_background);
+        }
+        item! {
+            render_title(ui, c, tl!("item-bg-parallax"), Some(tl!("item-bg-parallax-sub")));
+            self.bg_parallax_slider.render(ui, rr, t, c, config.bg_parallax, format!("{:.2}", config.bg_parallax));
+        }
         item! {
             render_title(ui, c, tl!("item-fade"), Some(tl!("item-fade-sub")));
             self.fade_slider.render(ui, rr, t,c, config.fade, format!("{:.2}", config.fade));
         }
+        item! {
+            render_title(ui, c, tl!("item-particle-quality"), Some(tl!("item-particle-quality-sub")));
+            self.particle_quality_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-ap-aura"), Some(tl!("item-ap-aura-sub")));
+            render_switch(ui, rr, t, c, &mut self.ap_aura_btn, config.ap_aura);
+        }
+        item! {
+            render_title(ui, c, tl!("item-line-feedback"), Some(tl!("item-line-feedback-sub")));
+            render_switch(ui, rr, t, c, &mut self.line_feedback_btn, config.judge_line_feedback);
+        }
+        item! {
+            render_title(ui, c, tl!("item-line-thickness"), None);
+            self.line_thickness_slider.render(ui, rr, t, c, config.judge_line_thickness, format!("{:.1}x", config.judge_line_thickness));
+        }
+        item! {
+            render_title(ui, c, tl!("item-line-glow"), Some(tl!("item-line-glow-sub")));
+            self.line_glow_slider.render(ui, rr, t, c, config.judge_line_glow, format!("{:.1}", config.judge_line_glow));
+        }
+        item! {
+            render_title(ui, c, tl!("item-visual-flip"), Some(tl!("item-visual-flip-sub")));
+            render_switch(ui, rr, t, c, &mut self.visual_flip_btn, config.visual_flip_x);
+        }
+        item! {
+            render_title(ui, c, tl!("item-combo-break"), Some(tl!("item-combo-break-sub")));
+            render_switch(ui, rr, t, c, &mut self.combo_break_btn, config.combo_break_feedback);
+        }
+        item! {
+            render_title(ui, c, tl!("item-hit-popup"), Some(tl!("item-hit-popup-sub")));
+            render_switch(ui, rr, t, c, &mut self.hit_popup_btn, config.hit_error_popup);
+        }
+        item! {
+            render_title(ui, c, tl!("item-hit-ms"), Some(tl!("item-hit-ms-sub")));
+            render_switch(ui, rr, t, c, &mut self.hit_ms_btn, config.show_hit_ms);
+        }
+        item! {
+            render_title(ui, c, tl!("item-timing-meter"), Some(tl!("item-timing-meter-sub")));
+            render_switch(ui, rr, t, c, &mut self.timing_meter_btn, config.timing_meter);
+        }
+        item! {
+            render_title(ui, c, tl!("item-accuracy-target"), Some(tl!("item-accuracy-target-sub")));
+            let text = config.accuracy_target.map(|it| format!("{:.2}%", it * 100.)).unwrap_or_else(|| tl!("item-accuracy-target-off").into_owned());
+            self.accuracy_target_btn.render_text(ui, rr, t, c.a, text, 0.4, false);
+        }
+        item! {
+            render_title(ui, c, tl!("item-accuracy-target-pause"), None);
+            render_switch(ui, rr, t, c, &mut self.accuracy_target_pause_btn, config.accuracy_target_pause);
+        }
         item! {
             render_title(ui, c, tl!("item-watermark"), None);
             self.watermark.render_text(ui, rr, t, c.a, &config.watermark, 0.4, false);
@@ -783,6 +1475,51 @@ impl OtherList {
             render_title(ui, c, tl!("item-chinese"), None);
             render_switch(ui, rr, t, c, &mut self.chinese_btn, config.chinese);
         }
+        item! {
+            render_title(ui, c, tl!("item-long-number"), Some(tl!("item-long-number-sub")));
+            self.long_number_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-score-mode"), Some(tl!("item-score-mode-sub")));
+            self.score_mode_btn.render(ui, rr, t, c.a);
+        }
+        item! {
+            render_title(ui, c, tl!("item-color-filter"), Some(tl!("item-color-filter-sub")));
+            self.color_filter_btn.render(ui, rr, t, c.a);
+        }
+        for (order, (btn, title)) in self
+            .note_tint_btns
+            .iter_mut()
+            .zip([tl!("item-note-tint-hold"), tl!("item-note-tint-drag"), tl!("item-note-tint-click"), tl!("item-note-tint-flick")])
+            .enumerate()
+        {
+            item! {
+                render_title(ui, c, title, None);
+                let text = config.note_tints[order].map(|it| format!("#{it:08X}")).unwrap_or_else(|| tl!("item-note-tint-none").into_owned());
+                btn.render_text(ui, rr, t, c.a, text, 0.4, false);
+            }
+        }
+        item! {
+            render_title(ui, c, tl!("item-tint-bad"), None);
+            render_switch(ui, rr, t, c, &mut self.tint_bad_btn, config.tint_bad_notes);
+        }
+        for (btn, title, color) in [
+            (&mut self.color_perfect_btn, tl!("item-color-perfect"), config.color_perfect),
+            (&mut self.color_good_btn, tl!("item-color-good"), config.color_good),
+        ] {
+            item! {
+                render_title(ui, c, title, None);
+                let text = color.map(|it| format!("#{it:08X}")).unwrap_or_else(|| tl!("item-note-tint-none").into_owned());
+                btn.render_text(ui, rr, t, c.a, text, 0.4, false);
+            }
+        }
+        self.color_filter_btn.render_top(ui, t, c.a);
+        for btn in &mut self.hold_flag_btns {
+            btn.render_top(ui, t, c.a);
+        }
+        self.particle_quality_btn.render_top(ui, t, c.a);
+        self.score_mode_btn.render_top(ui, t, c.a);
+        self.long_number_btn.render_top(ui, t, c.a);
         (w, h)
     }
 }
@@ -207,6 +207,11 @@ impl ChooseButton {
         self.popup.selected
     }
 
+    #[inline]
+    pub fn set_selected(&mut self, selected: usize) {
+        self.popup.set_selected(selected);
+    }
+
     #[inline]
     pub fn changed(&mut self) -> bool {
         self.popup.changed()
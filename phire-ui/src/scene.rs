@@ -82,6 +82,8 @@ pub async fn import_chart(path: String) -> Result<LocalChart> {
             local_path,
             record: None,
             mods: Mods::default(),
+            resume_time: None,
+            preset: None,
         })
     }
     let dir = dir::custom_charts()?;
@@ -28,7 +28,7 @@ use phire::{
     judge::{icon_index, Judge},
     scene::{
         request_input, return_input, show_error, show_message, take_input, BasicPlayer, GameMode, LoadingScene, LocalSceneTask, NextScene,
-        RecordUpdateState, Scene, SimpleRecord, UpdateFn,
+        set_preset_callback, RecordUpdateState, ResumePoint, Scene, SimpleRecord, UpdateFn,
     },
     task::Task,
     time::TimeManager,
@@ -309,11 +309,10 @@ impl SongScene {
         } else {
             chart.illu
         };
-        let record = get_data()
-            .charts
-            .iter()
-            .find(|it| Some(&it.local_path) == local_path.as_ref())
-            .and_then(|it| it.record.clone());
+        let local = get_data().charts.iter().find(|it| Some(&it.local_path) == local_path.as_ref());
+        let record = local.and_then(|it| it.record.clone());
+        // the mods of a preset show up in the picker, so what is played is always what the player sees
+        let mods = local.and_then(|it| it.preset).map_or(mods, |it| it.mods);
         let fetch_best_task = if get_data().me.is_some() {
             chart.info.id.map(|id| Task::new(Client::best_record(id)))
         } else {
@@ -540,6 +539,8 @@ impl SongScene {
                         local_path,
                         record: None,
                         mods: Mods::default(),
+                        resume_time: None,
+                        preset: None,
                     })
                 }
             }),
@@ -596,6 +597,7 @@ impl SongScene {
         }
         if self.local_path.is_some() {
             self.menu_options.push("exercise");
+            self.menu_options.push("practice");
             self.menu_options.push("offset");
         }
         let perms = get_data().me.as_ref().map(|it| it.perms()).unwrap_or_default();
@@ -647,6 +649,32 @@ impl SongScene {
         client: Option<Arc<phira_mp_client::Client>>,
     ) -> Result<LocalSceneTask> {
         let mut fs = fs_from_path(local_path)?;
+        let resume_time = get_data()
+            .find_chart_by_path(local_path)
+            .and_then(|index| get_data().charts[index].resume_time);
+        // everyone in a multiplayer room plays with their global settings
+        let multiplayer = client.is_some();
+        let preset = if multiplayer {
+            None
+        } else {
+            get_data().find_chart_by_path(local_path).and_then(|index| get_data().charts[index].preset)
+        };
+        set_preset_callback(if multiplayer {
+            None
+        } else {
+            Some(Box::new({
+                let local_path = local_path.to_owned();
+                move |preset| {
+                    let Some(index) = get_data().find_chart_by_path(&local_path) else {
+                        return;
+                    };
+                    get_data_mut().charts[index].preset = preset;
+                    if let Err(err) = save_data() {
+                        warn!("failed to save chart preset: {:?}", err);
+                    }
+                }
+            }))
+        });
         #[cfg(feature = "closed")]
         let rated = {
             let config = &get_data().config;
@@ -773,6 +801,10 @@ impl SongScene {
                 }
             };
             let chart_updated = info.chart_updated;
+            if let Some(preset) = preset {
+                preset.apply(&mut config);
+            }
+            // the picker starts from the preset's mods, anything changed there since wins
             config.mods = mods;
             LoadingScene::new(
                 mode,
@@ -823,6 +855,8 @@ impl SongScene {
                     })
                 })),
                 update_fn,
+                None,
+                resume_time,
             )
             .await
             .map(|it| NextScene::Overlay(Box::new(it)))
@@ -1120,6 +1154,15 @@ impl SongScene {
         Self::global_update_chart_info(self.local_path.as_ref().unwrap(), self.info.clone())
     }
 
+    fn set_resume_time(&mut self, time: Option<f32>) -> Result<()> {
+        let Some(index) = self.local_path.as_ref().and_then(|path| get_data().find_chart_by_path(path)) else {
+            return Ok(());
+        };
+        get_data_mut().charts[index].resume_time = time;
+        save_data()?;
+        Ok(())
+    }
+
     fn global_update_chart_info(local_path: &str, info: BriefChartInfo) -> Result<()> {
         get_data_mut().charts[get_data().find_chart_by_path(local_path).unwrap()].info = info;
         NEED_UPDATE.store(true, Ordering::Relaxed);
@@ -1136,6 +1179,7 @@ impl Scene for SongScene {
                 if self.my_rate_score == Some(0) && thread_rng().gen_ratio(2, 5) {
                     self.rate_dialog.enter(tm.real_time() as _);
                 }
+                self.set_resume_time(None)?;
                 self.update_record(*rec)?;
                 self.load_ldb();
                 return Ok(());
@@ -1148,6 +1192,13 @@ impl Scene for SongScene {
             }
             Err(res) => res,
         };
+        let res = match res.downcast::<ResumePoint>() {
+            Ok(point) => {
+                self.set_resume_time(Some(point.0))?;
+                return Ok(());
+            }
+            Err(res) => res,
+        };
         let _res = match res.downcast::<Option<f32>>() {
             Ok(offset) => {
                 if let Some(offset) = *offset {
@@ -1234,6 +1285,9 @@ impl Scene for SongScene {
                         let chart = &mut get_data_mut().charts[get_data().find_chart_by_path(self.local_path.as_deref().unwrap()).unwrap()];
                         if chart.mods != self.mods {
                             chart.mods = self.mods;
+                            if let Some(preset) = &mut chart.preset {
+                                preset.mods = self.mods;
+                            }
                             save_data()?;
                         }
                     }
@@ -1507,6 +1561,9 @@ impl Scene for SongScene {
                 "exercise" => {
                     self.launch(GameMode::Exercise)?;
                 }
+                "practice" => {
+                    self.launch(GameMode::Practice)?;
+                }
                 "offset" => {
                     self.launch(GameMode::TweakOffset)?;
                 }
@@ -1,8 +1,10 @@
 
 uploading = Uploading result…
+uploading-attempt = Uploading result… (attempt { $attempt }/{ $total })
 uploaded = Result uploaded
 upload-failed = Failed to upload
 upload-cancel = Cancel upload
 upload-retry = Retry
 
 still-uploading = Uploading result, please wait…
+auto-proceed = Continuing in { $seconds }s
@@ -4,10 +4,34 @@ adjust-offset = Adjust offset
 offset-cancel = Cancel
 offset-reset = Reset
 offset-save = Save
+detect-offset = Detect
+detecting-offset = Detecting offset…
+offset-detected = Suggested offset: { $offset }ms
+offset-position = { $bpm } BPM · bar { $bar }, beat { $beat }
 speed = Speed
+speed-bpm = Target BPM
+hit-early = EARLY
+hit-late = LATE
+accuracy-below-target = Accuracy below target ({ $target })
 
 ex-time-out-of-range = Time is out of range
 ex-invalid-format = Invalid format
 ex-time-set = Time changed
 ex-time-start = Time started
 ex-time-stop = Time stopped
+
+practice-from-start = Start from beginning
+
+chart-preset-active = Preset
+chart-preset-save = Save as preset
+chart-preset-clear = Clear preset
+chart-preset-saved = Speed, offset and mods saved for this chart
+chart-preset-cleared = Preset cleared
+
+preset-preview = Previewing preset: { $name }
+preset-preview-off = Preset preview off
+preset-preview-failed = Preset { $name } can't be previewed
+speed-clamped = Speed can't go below { $min }x while playing
+
+autoplay-on = Autoplay on
+autoplay-off = Autoplay off
@@ -38,8 +38,25 @@ expected-01 = Expected 0 / 1 (real note / fake note)
 unexpected-extra = Unexpected extra content: { $next }
 line-location = On line #{ $lid }
 
+# osu
+osu-unsupported-mode = Only osu!mania beatmaps are supported (got mode { $mode })
+osu-no-timing-points = No uninherited timing point found
+
 # extra
 shader-load-failed = Cannot load shader from { $path }
 shader-not-found = Cannot find preset shader { $shader }
 effect-location = In effect #{ $id }
 video-load-failed = Failed to read video from { $path }
+texture-load-failed = Failed to read texture from { $path }
+
+# expressions
+expr-unexpected-char = Unexpected `{ $char }` at position { $pos }
+expr-unexpected-end = Unexpected end of expression
+expr-invalid-number = Invalid number: { $number }
+expr-unknown-name = Unknown variable or function: { $name }
+expr-wrong-arity = `{ $name }` takes { $expected } arguments, got { $got }
+expr-too-deep = Expression is nested too deeply
+expr-too-long = Expression is too long
+expr-not-finite = Expression is not a finite number at t = { $time }
+expr-parse-failed = Failed to parse { $property } expression `{ $expr }`
+line-motion-location = In line motion #{ $id }
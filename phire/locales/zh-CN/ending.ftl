@@ -1,8 +1,10 @@
 
 uploading = 成绩上传中
+uploading-attempt = 成绩上传中（第 { $attempt }/{ $total } 次尝试）
 uploaded = 成绩上传成功
 upload-failed = 成绩上传失败
 upload-cancel = 取消上传
 upload-retry = 重试
 
 still-uploading = 尚在上传成绩
+auto-proceed = { $seconds } 秒后继续
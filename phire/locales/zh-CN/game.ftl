@@ -4,10 +4,34 @@ adjust-offset = 调整延迟
 offset-cancel = 取消
 offset-reset = 重置
 offset-save = 保存
+detect-offset = 检测
+detecting-offset = 正在检测延迟…
+offset-detected = 建议延迟：{ $offset }ms
+offset-position = { $bpm } BPM · 第 { $bar } 小节，第 { $beat } 拍
 speed = 速度
+speed-bpm = 目标 BPM
+hit-early = 过早
+hit-late = 过晚
+accuracy-below-target = 准确率低于目标（{ $target }）
 
 ex-time-out-of-range = 时间不在范围内
 ex-invalid-format = 格式有误
 ex-time-set = 设置成功
 ex-time-start = 开始时间
 ex-time-end = 结束时间
+
+practice-from-start = 从头开始
+
+chart-preset-active = 预设
+chart-preset-save = 保存为预设
+chart-preset-clear = 清除预设
+chart-preset-saved = 已为此谱面保存速度、偏移与模组
+chart-preset-cleared = 已清除预设
+
+preset-preview = 正在预览预设：{ $name }
+preset-preview-off = 已关闭预设预览
+preset-preview-failed = 无法预览预设 { $name }
+speed-clamped = 游玩中速度不能低于 { $min }x
+
+autoplay-on = 自动游玩已开启
+autoplay-off = 自动游玩已关闭
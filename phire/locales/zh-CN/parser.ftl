@@ -38,8 +38,25 @@ expected-01 = 应当为 0 / 1 (真 note / 假 note)
 unexpected-extra = 未知的附加内容: { $next }
 line-location = #{ $lid } 行中
 
+# osu
+osu-unsupported-mode = 仅支持 osu!mania 谱面（当前模式为 { $mode }）
+osu-no-timing-points = 未找到非继承时间点
+
 # extra
 shader-load-failed = 无法从 { $path } 中加载 shader
 shader-not-found = 未找到预置 shader { $shader }
 effect-location = #{ $id } 号 effect 中
 video-load-failed = 从 { $path } 中加载视频失败
+texture-load-failed = 从 { $path } 中加载纹理失败
+
+# expressions
+expr-unexpected-char = 位置 { $pos } 处有意外的 `{ $char }`
+expr-unexpected-end = 表达式意外结束
+expr-invalid-number = 无效的数字：{ $number }
+expr-unknown-name = 未知的变量或函数：{ $name }
+expr-wrong-arity = `{ $name }` 需要 { $expected } 个参数，实际为 { $got } 个
+expr-too-deep = 表达式嵌套过深
+expr-too-long = 表达式过长
+expr-not-finite = 表达式在 t = { $time } 处不是有限数
+expr-parse-failed = 解析 { $property } 表达式 `{ $expr }` 失败
+line-motion-location = #{ $id } 号线条运动中
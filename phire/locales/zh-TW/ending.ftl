@@ -1,5 +1,6 @@
 
 uploading = 上傳成績中...
+uploading-attempt = 上傳成績中...（第 { $attempt }/{ $total } 次嘗試）
 uploaded = 成績上傳成功
 upload-failed = 成績上傳失敗
 upload-cancel = 取消上傳
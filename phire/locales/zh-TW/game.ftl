@@ -4,8 +4,13 @@ adjust-offset = 調整延遲
 offset-cancel = 取消
 offset-reset = 重置
 offset-save = 保存
+detect-offset = 檢測
+detecting-offset = 正在檢測延遲…
+offset-detected = 建議延遲：{ $offset }ms
 speed = 速度
 
 ex-time-out-of-range = 時間不在範圍內
 ex-invalid-format = 格式有誤
 ex-time-set = 設定成功
+
+practice-from-start = 從頭開始
@@ -35,8 +35,13 @@ expected-01 = 應當為 0 / 1 (真 note / 假 note)
 unexpected-extra = 未知的附加內容: { $next }
 line-location = #{ $lid } 行中
 
+# osu
+osu-unsupported-mode = 僅支援 osu!mania 譜面（目前模式為 { $mode }）
+osu-no-timing-points = 未找到非繼承時間點
+
 # extra
 shader-load-failed = 無法從 { $path } 中載入 shader
 shader-not-found = 未找到預置 shader { $shader }
 effect-location = #{ $id } 號 effect 中
 video-load-failed = 從 { $path } 中載入影片失敗
+texture-load-failed = 從 { $path } 中載入紋理失敗
@@ -1,6 +1,10 @@
+use crate::{core::UIElement, judge::MAX_SIMULATED_LATENCY_MS};
 use bitflags::bitflags;
+use macroquad::color::Color;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
 
 pub static TIPS: Lazy<Vec<String>> = Lazy::new(|| 
     include_str!("tips.txt").split('\n')
@@ -30,6 +34,255 @@ pub enum ChallengeModeColor {
     Rainbow,
 }
 
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorFilter {
+    #[default]
+    None,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorFilter {
+    pub const ALL: [ColorFilter; 4] = [Self::None, Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// Name of the built-in effect preset implementing this filter
+    pub fn preset(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Protanopia => Some("protanopia"),
+            Self::Deuteranopia => Some("deuteranopia"),
+            Self::Tritanopia => Some("tritanopia"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BadNoteStyle {
+    #[default]
+    Default,
+    /// Keep the note's own color and only fade it out
+    FadeOnly,
+    /// Don't show bad notes at all
+    Hidden,
+}
+
+impl BadNoteStyle {
+    pub const ALL: [BadNoteStyle; 3] = [Self::Default, Self::FadeOnly, Self::Hidden];
+}
+
+/// How score and combo numbers wider than their slot are drawn.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LongNumberMode {
+    #[default]
+    Shrink,
+    /// Cut off the end with an ellipsis, keeping the most significant part
+    Truncate,
+    /// Scroll back and forth through the whole text
+    Scroll,
+}
+
+impl LongNumberMode {
+    pub const ALL: [LongNumberMode; 3] = [Self::Shrink, Self::Truncate, Self::Scroll];
+}
+
+/// When the combo number is drawn above the combo label.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ComboDisplay {
+    #[default]
+    Always,
+    /// Only at multiples of `combo_display_step`
+    Milestones,
+    /// Only the label is drawn
+    Hidden,
+}
+
+impl ComboDisplay {
+    pub const ALL: [ComboDisplay; 3] = [Self::Always, Self::Milestones, Self::Hidden];
+
+    pub fn shows(&self, combo: u32, step: u32) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Milestones => step != 0 && combo % step == 0,
+            Self::Hidden => false,
+        }
+    }
+}
+
+/// Filter applied to the chart music only, with the cutoff frequency in Hz.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioFilter {
+    LowPass(f32),
+    HighPass(f32),
+}
+
+impl AudioFilter {
+    pub fn cutoff(&self) -> f32 {
+        match self {
+            Self::LowPass(cutoff) | Self::HighPass(cutoff) => *cutoff,
+        }
+    }
+
+    pub fn with_cutoff(self, cutoff: f32) -> Self {
+        match self {
+            Self::LowPass(_) => Self::LowPass(cutoff),
+            Self::HighPass(_) => Self::HighPass(cutoff),
+        }
+    }
+}
+
+/// Amount of hit particles, for scaling down on weak devices.
+///
+/// | Quality  | Particle cap           | Debris per hit |
+/// |----------|------------------------|----------------|
+/// | `Off`    | none, nothing emitted  | 0              |
+/// | `Low`    | `max_particles / 4`    | 1              |
+/// | `Medium` | `max_particles / 2`    | 2              |
+/// | `High`   | `max_particles`        | 4              |
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ParticleQuality {
+    Off,
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl ParticleQuality {
+    pub const ALL: [ParticleQuality; 4] = [Self::Off, Self::Low, Self::Medium, Self::High];
+
+    /// Particle cap derived from `config.max_particles`
+    pub fn max_particles(self, max_particles: usize) -> usize {
+        self.divisor().map_or(0, |it| max_particles / it)
+    }
+
+    /// Divisor of the emission rate, `None` if nothing should be emitted
+    pub fn divisor(self) -> Option<usize> {
+        match self {
+            Self::Off => None,
+            Self::Low => Some(4),
+            Self::Medium => Some(2),
+            Self::High => Some(1),
+        }
+    }
+}
+
+/// How the score is computed from the judgements.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScoreMode {
+    /// 90% accuracy and 10% max combo, out of 1,000,000
+    #[default]
+    Phigros,
+    /// Accuracy only, out of 1,000,000
+    Millions,
+    /// Accuracy in hundredths of a percent, out of 10,000
+    Percentage,
+}
+
+impl ScoreMode {
+    pub const ALL: [ScoreMode; 3] = [Self::Phigros, Self::Millions, Self::Percentage];
+
+    /// Score of a flawless play
+    pub fn total(self) -> u32 {
+        match self {
+            Self::Phigros | Self::Millions => 1_000_000,
+            Self::Percentage => 10_000,
+        }
+    }
+
+    /// Converts the Phigros score and accuracy of a play into a score of this mode, for display only.
+    pub fn apply(self, score: u32, accuracy: f64) -> u32 {
+        match self {
+            Self::Phigros => score,
+            mode => (accuracy * mode.total() as f64).round() as u32,
+        }
+    }
+
+    /// Scales a score of this mode to the 1,000,000 range used by grade thresholds.
+    pub fn normalize(self, score: u32) -> u32 {
+        (score as u64 * 1_000_000 / self.total() as u64) as u32
+    }
+
+    pub fn format(self, score: u32) -> String {
+        match self {
+            Self::Phigros => format!("{score:07}"),
+            Self::Millions => {
+                let digits = score.to_string();
+                let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+                for (i, c) in digits.chars().enumerate() {
+                    if i != 0 && (digits.len() - i) % 3 == 0 {
+                        result.push(',');
+                    }
+                    result.push(c);
+                }
+                result
+            }
+            Self::Percentage => format!("{:.2}%", score as f32 / 100.),
+        }
+    }
+}
+
+/// Adjustment of a HUD element on top of its default layout.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct UiLayout {
+    pub offset: (f32, f32),
+    /// Scale around the center of the element
+    pub scale: f32,
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        Self { offset: (0., 0.), scale: 1. }
+    }
+}
+
+/// What is drawn behind the playfield. Colors are in `0xRRGGBB` form.
+#[derive(Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BackgroundMode {
+    /// The blurred illustration of the chart
+    #[default]
+    Illustration,
+    Solid(u32),
+    /// A vertical gradient from the top color to the bottom color
+    Gradient(u32, u32),
+}
+
+/// Speed, offset and mods remembered for a single chart.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartPreset {
+    pub speed: f32,
+    pub offset: f32,
+    pub mods: Mods,
+}
+
+impl ChartPreset {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            speed: config.speed,
+            offset: config.offset,
+            mods: config.mods,
+        }
+    }
+
+    /// Applies the preset on top of `config` and marks it as active.
+    pub fn apply(&self, config: &mut Config) {
+        config.speed = self.speed;
+        config.offset = self.offset;
+        config.mods = self.mods;
+        config.chart_preset = Some(*self);
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 #[serde(default)]
 #[serde(rename_all = "camelCase")]
@@ -39,52 +292,163 @@ pub struct Config {
     pub aggressive: bool,
     pub aspect_ratio: Option<f32>,
     pub audio_buffer_size: Option<u32>,
+    pub bad_note_style: BadNoteStyle,
+    pub long_number_mode: LongNumberMode,
     pub audio_compatibility: bool,
     pub challenge_color: ChallengeModeColor,
     pub challenge_rank: u32,
     pub chart_debug_line: f32,
     pub chart_debug_note: f32,
     pub chart_ratio: f32,
+    /// Height in pixels of the offscreen canvas the HUD and pause menu are drawn on before being scaled to the screen
+    pub ui_virtual_height: Option<f32>,
+    pub color_filter: ColorFilter,
     pub all_good: bool,
     pub all_bad: bool,
     pub disable_effect: bool,
     pub double_click_to_pause: bool,
+    /// Pause the game when the window loses focus
+    pub pause_on_blur: bool,
     pub double_hint: bool,
     pub fix_aspect_ratio: bool,
     pub fxaa: bool,
+    pub hold_tick_sound: bool,
+    /// When disabled, no touch or key ever changes the state of scenes and notes are judged as in autoplay
     pub interactive: bool,
+    /// Touches judge the closest note in time regardless of where they are. Such plays are not recorded nor uploaded
+    pub lenient_position: bool,
+    /// Holds released within `forgiving_hold_window` seconds before their end count as completed. Such plays are not recorded nor uploaded
+    pub forgiving_holds: bool,
+    pub forgiving_hold_window: f32,
     pub note_scale: f32,
+    /// Extra scales for hold, drag, click and flick notes on top of `note_scale`, indexed by `NoteKind::order`
+    pub note_scale_per_kind: [f32; 4],
+    pub note_speed_mult: f32,
+    /// Draws fading afterimages behind moving notes
+    pub note_trail: bool,
+    /// ARGB tints for hold, drag, click and flick notes, indexed by `NoteKind::order`
+    pub note_tints: [Option<u32>; 4],
+    pub tint_bad_notes: bool,
+    /// ARGB color of perfect hit effects and the judge line, overriding the resource pack
+    pub color_perfect: Option<u32>,
+    /// ARGB color of good hit effects and the judge line, overriding the resource pack
+    pub color_good: Option<u32>,
+    /// Overrides of the resource pack's hold flags, `None` keeps the pack's value
+    pub hold_keep_head: Option<bool>,
+    pub hold_repeat: Option<bool>,
+    pub hold_compact: Option<bool>,
+    pub beat_grid: bool,
+    pub metronome: bool,
+    pub metronome_subdivision: u32,
+    /// Combo values that trigger a burst when reached, empty to disable
+    pub combo_milestones: Vec<u32>,
+    pub combo_display_mode: ComboDisplay,
+    /// Step of the combo numbers shown in `ComboDisplay::Milestones` mode
+    pub combo_display_step: u32,
+    /// Shake the combo when it breaks
+    pub combo_break_feedback: bool,
+    /// Sound played on a combo break, only with `combo_break_feedback`
+    pub combo_break_sound: Option<String>,
     pub mods: Mods,
     pub mp_enabled: bool,
     pub mp_address: String,
+    pub normalize_audio: bool,
+    /// Silences the music while it keeps driving the timing, sound effects and the metronome still play
+    pub mute_music: bool,
+    pub music_filter: Option<AudioFilter>,
+    /// Keep the music playing quietly while paused instead of stopping it
+    pub pause_keep_music: bool,
     pub offline_mode: bool,
+    /// Times to try uploading a result before asking the player, retries are delayed exponentially
+    pub upload_max_attempts: u32,
+    /// Delay before the first retry, in seconds
+    pub upload_retry_delay: f32,
+    /// Leave the result screen by itself this many seconds after uploading finishes
+    pub ending_auto_proceed: Option<f32>,
     pub offset: f32,
     pub particle: bool,
+    pub particle_quality: ParticleQuality,
+    pub score_mode: ScoreMode,
     pub player_name: String,
     pub player_rks: f32,
     pub res_pack_path: Option<String>,
+    /// MSAA sample count of the chart, one of 1, 2, 4 and 8. Lowered to what the GPU supports when loading
     pub sample_count: u32,
     pub show_acc: bool,
+    /// Show the number of judged notes out of the total under the progress bar
+    pub show_note_progress: bool,
     pub speed: f32,
+    /// Set the practice speed in Exercise mode as a target BPM instead of a multiplier
+    pub speed_as_bpm: bool,
     pub touch_debug: bool,
+    /// Glow around the screen edges while every judged note is perfect
+    pub ap_aura: bool,
+    /// Tint the judge line with the perfect/good colors while the play is still AP/FC, otherwise it stays white
+    pub judge_line_feedback: bool,
+    /// Multiplier of the thickness of plain judge lines
+    pub judge_line_thickness: f32,
+    /// Strength of the soft glow around plain judge lines, 0 to disable
+    pub judge_line_glow: f32,
+    /// Mirror the chart horizontally without touching judgement, for watching. Ignored in scored play, where the flip mod is used
+    pub visual_flip_x: bool,
+    /// Show "EARLY" / "LATE" next to notes judged as good
+    pub hit_error_popup: bool,
+    /// Show the signed timing error in milliseconds below every tapped note
+    pub show_hit_ms: bool,
+    /// Plot the timing errors of recent taps on a horizontal meter spanning the good window
+    pub timing_meter: bool,
+    /// Warn when the live accuracy (0 to 1) drops below this
+    pub accuracy_target: Option<f32>,
+    /// Pause the game when `accuracy_target` is missed
+    pub accuracy_target_pause: bool,
+    /// Touches that start and end within this window in the same frame are ignored
+    pub touch_debounce_ms: f32,
+    /// Distance a touch must move from where it started before it can flick
+    pub touch_dead_zone: f32,
+    /// Debug only: delays all judged input by this many milliseconds (at most 500). Scores made with it are invalid
+    pub simulated_input_latency_ms: f32,
+    /// Names of keys acting as taps on equal-width lanes from left to right, e.g. `["D", "F", "J", "K"]`
+    pub key_bindings: Vec<String>,
+    /// Name of the key toggling autoplay while watching in View mode, empty to disable
+    pub autoplay_toggle_key: String,
+    pub show_fps: bool,
     pub volume_music: f32,
     pub volume_sfx: f32,
     pub volume_bgm: f32,
     pub watermark: String,
+    /// Path to an image drawn instead of the text watermark
+    pub watermark_image: Option<String>,
+    /// ARGB color of the bars beside the chart, replacing the default dim
+    pub letterbox_color: Option<u32>,
+    /// Path to an image stretched over the bars beside the chart, takes precedence over `letterbox_color`
+    pub letterbox_image: Option<String>,
     pub roman: bool,
     pub chinese: bool,
     pub combo: String,
     pub difficulty: String,
+    /// Overrides of difficulty tier colors, e.g. `{"IN": 0xbe2d23}`
+    pub level_colors: HashMap<String, u32>,
     pub disable_loading: bool,
 
     // for compatibility
     pub autoplay: Option<bool>,
+    /// Target accuracy (0 to 1) for autoplay; hit times are perturbed to approach it instead of playing perfectly
+    pub autoplay_accuracy: Option<f32>,
 
     pub disable_audio: bool,
     pub judge_offset: f32,
+    /// Per-chart preset applied to this config, only set on the copy passed to a single game
+    pub chart_preset: Option<ChartPreset>,
 
     pub render_line: bool,
+    /// Path to an image drawn instead of the plain judge line, textured lines of the chart are kept
+    pub judge_line_texture: Option<String>,
     pub render_line_extra: bool,
+    /// Pulse the brightness of plain judge lines with the loudness of the music
+    pub reactive_lines: bool,
+    /// Keep drawing notes after they pass the judge line, for the whole chart. Purely cosmetic: judged notes still
+    /// disappear. Notes are no longer culled once passed, so dense charts draw noticeably more
+    pub always_draw_below: bool,
     pub render_note: bool,
     pub render_ui_pause: bool,
     pub render_ui_name: bool,
@@ -92,13 +456,30 @@ pub struct Config {
     pub render_ui_score: bool,
     pub render_ui_combo: bool,
     pub render_ui_bar: bool,
+    /// Overrides of HUD element positions, ignored for elements attached to lines
+    pub ui_layout: HashMap<UIElement, UiLayout>,
     pub render_bg: bool,
     pub render_bg_dim: bool,
+    /// Darkness of the overlay above the background, overriding the chart's `background_dim`
+    pub background_dim: Option<f32>,
+    /// Draw only judge lines and notes: no background, particles, videos or effects. Judgement and audio are unaffected
+    pub minimal_render: bool,
+    pub background_mode: BackgroundMode,
+    /// Blur the illustration used as background, with a radius of `bg_blurriness`
+    pub blur_background: bool,
     pub bg_blurriness: f32,
+    /// How much (0 to 1) the background illustration follows the movement of the lines, 0 keeps it still
+    pub bg_parallax: f32,
 
     pub max_particles: usize,
 
     pub fade: f32,
+    /// Seconds to fade the whole scene in from the start, replacing the built-in intro fade. 0 keeps the default
+    pub intro_fade: f32,
+    /// Seconds to fade the whole scene out before the music ends. 0 keeps the default
+    pub outro_fade: f32,
+    /// Speed factor slowly reached during the last seconds of the music. Only used in View mode or non-interactive rendering
+    pub finale_slowmo: Option<f32>,
     pub alpha_tint: bool, // note.alpha <=0.5 blue, note.alpha >0.5 red
 }
 
@@ -109,51 +490,115 @@ impl Default for Config {
             aggressive: false,
             aspect_ratio: None,
             audio_buffer_size: None,
+            bad_note_style: BadNoteStyle::Default,
+            long_number_mode: LongNumberMode::Shrink,
             audio_compatibility: false,
             challenge_color: ChallengeModeColor::Rainbow,
             challenge_rank: 45,
             chart_debug_line: 0.0,
             chart_debug_note: 0.0,
             chart_ratio: 1.0,
+            ui_virtual_height: None,
+            color_filter: ColorFilter::None,
             all_good: false,
             all_bad: false,
             disable_effect: false,
             double_click_to_pause: true,
+            // browsers report focus changes for reasons unrelated to the player leaving
+            pause_on_blur: cfg!(not(target_arch = "wasm32")),
             double_hint: true,
             fix_aspect_ratio: false,
             fxaa: false,
+            hold_tick_sound: false,
             interactive: true,
+            lenient_position: false,
+            forgiving_holds: false,
+            forgiving_hold_window: 0.3,
+            beat_grid: false,
+            metronome: false,
+            metronome_subdivision: 1,
+            combo_milestones: Vec::new(),
+            combo_display_mode: ComboDisplay::Always,
+            combo_display_step: 10,
+            combo_break_feedback: false,
+            combo_break_sound: None,
             mods: Mods::default(),
             mp_address: "mp2.phira.cn:12345".to_owned(),
             mp_enabled: false,
+            normalize_audio: false,
+            mute_music: false,
+            music_filter: None,
+            pause_keep_music: false,
             note_scale: 1.0,
+            note_scale_per_kind: [1.0; 4],
+            note_speed_mult: 1.0,
+            note_trail: false,
+            note_tints: [None; 4],
+            tint_bad_notes: false,
+            color_perfect: None,
+            color_good: None,
+            hold_keep_head: None,
+            hold_repeat: None,
+            hold_compact: None,
             offline_mode: false,
+            upload_max_attempts: 3,
+            ending_auto_proceed: None,
+            upload_retry_delay: 1.,
             offset: 0.0,
             particle: true,
+            particle_quality: ParticleQuality::High,
+            score_mode: ScoreMode::Phigros,
             player_name: "Guest".to_string(),
             player_rks: 15.,
             res_pack_path: None,
             sample_count: 1,
             show_acc: false,
+            show_note_progress: false,
             speed: 1.0,
+            speed_as_bpm: false,
             touch_debug: false,
+            ap_aura: false,
+            judge_line_feedback: true,
+            judge_line_thickness: 1.,
+            judge_line_glow: 0.,
+            visual_flip_x: false,
+            hit_error_popup: false,
+            show_hit_ms: false,
+            timing_meter: false,
+            accuracy_target: None,
+            accuracy_target_pause: false,
+            touch_debounce_ms: 0.,
+            touch_dead_zone: 0.,
+            simulated_input_latency_ms: 0.,
+            key_bindings: Vec::new(),
+            autoplay_toggle_key: "A".to_owned(),
+            show_fps: false,
             volume_music: 1.0,
             volume_sfx: 0.0,
             volume_bgm: 1.0,
             watermark: "".to_string(),
+            watermark_image: None,
+            letterbox_color: None,
+            letterbox_image: None,
             roman: false,
             chinese: false,
             combo: "COMBO".to_string(),
             difficulty: "".to_string(),
+            level_colors: HashMap::new(),
             disable_loading: false,
 
             autoplay: None,
+            autoplay_accuracy: None,
 
             disable_audio: false,
+            chart_preset: None,
             judge_offset: 0.,
 
             render_line: true,
+            judge_line_texture: None,
             render_line_extra: true,
+            reactive_lines: false,
+            always_draw_below: false,
             render_note: true,
             render_ui_pause: true,
             render_ui_name: true,
@@ -161,14 +606,23 @@ impl Default for Config {
             render_ui_score: true,
             render_ui_combo: true,
             render_ui_bar: true,
+            ui_layout: HashMap::new(),
             render_bg: true,
             render_bg_dim: true,
+            background_dim: None,
+            minimal_render: false,
+            background_mode: BackgroundMode::Illustration,
+            blur_background: true,
             bg_blurriness: 80.,
+            bg_parallax: 0.,
         
 
             max_particles: 20000,
 
             fade: 0.,
+            intro_fade: 0.,
+            outro_fade: 0.,
+            finale_slowmo: None,
             alpha_tint: false,
         }
     }
@@ -176,9 +630,82 @@ impl Default for Config {
 
 impl Config {
     pub fn init(&mut self) {
-        if let Some(flag) = self.autoplay {
+        self.validate_and_migrate();
+    }
+
+    /// Brings a deserialized config into a consistent state: deprecated fields are moved into their replacements and
+    /// numbers are clamped into ranges the game can handle. Every change is logged.
+    pub fn validate_and_migrate(&mut self) {
+        if let Some(flag) = self.autoplay.take() {
+            info!("config: moved `autoplay: {flag}` into mods");
             self.mods.set(Mods::AUTOPLAY, flag);
         }
+
+        let default = Config::default();
+        macro_rules! clamp {
+            ($($field:ident $(.$index:tt)?: $range:expr),* $(,)?) => {$(
+                let (value, range) = (self.$field$([$index])?, $range);
+                let clamped = if value.is_nan() { default.$field$([$index])? } else { value.clamp(*range.start(), *range.end()) };
+                if clamped != value {
+                    warn!("config: `{}` is {value}, changed to {clamped}", stringify!($field$([$index])?));
+                    self.$field$([$index])? = clamped;
+                }
+            )*};
+        }
+        macro_rules! clamp_opt {
+            ($($field:ident: $range:expr),* $(,)?) => {$(
+                if let Some(value) = self.$field {
+                    let range = $range;
+                    let clamped = if value.is_nan() { None } else { Some(value.clamp(*range.start(), *range.end())) };
+                    if clamped != Some(value) {
+                        warn!("config: `{}` is {value}, changed to {clamped:?}", stringify!($field));
+                        self.$field = clamped;
+                    }
+                }
+            )*};
+        }
+        macro_rules! at_least {
+            ($($field:ident: $min:expr),* $(,)?) => {$(
+                if self.$field < $min {
+                    warn!("config: `{}` is {}, changed to {}", stringify!($field), self.$field, $min);
+                    self.$field = $min;
+                }
+            )*};
+        }
+        clamp! {
+            speed: 0.25..=4.,
+            offset: -5.0..=5.,
+            note_scale: 0.1..=5.,
+            note_scale_per_kind.0: 0.1..=5.,
+            note_scale_per_kind.1: 0.1..=5.,
+            note_scale_per_kind.2: 0.1..=5.,
+            note_scale_per_kind.3: 0.1..=5.,
+            note_speed_mult: 0.1..=10.,
+            chart_ratio: 0.05..=1.,
+            volume_music: 0.0..=2.,
+            volume_sfx: 0.0..=2.,
+            volume_bgm: 0.0..=2.,
+            judge_line_thickness: 0.1..=10.,
+            judge_line_glow: 0.0..=5.,
+            bg_parallax: 0.0..=1.,
+            touch_debounce_ms: 0.0..=1000.,
+            touch_dead_zone: 0.0..=1.,
+            simulated_input_latency_ms: 0.0..=MAX_SIMULATED_LATENCY_MS,
+            forgiving_hold_window: 0.0..=1.,
+            upload_retry_delay: 0.0..=600.,
+        }
+        clamp_opt! {
+            aspect_ratio: 0.1..=10.,
+            accuracy_target: 0.0..=1.,
+            autoplay_accuracy: 0.0..=1.,
+            ending_auto_proceed: 0.0..=3600.,
+        }
+        // zero would divide by zero or never do anything
+        at_least! {
+            combo_display_step: 1,
+            metronome_subdivision: 1,
+            upload_max_attempts: 1,
+        }
     }
 
     #[inline]
@@ -191,8 +718,84 @@ impl Config {
         self.has_mod(Mods::AUTOPLAY)
     }
 
+    #[inline]
+    pub fn particle_enabled(&self) -> bool {
+        self.particle && self.particle_quality != ParticleQuality::Off && !self.minimal_render
+    }
+
     #[inline]
     pub fn flip_x(&self) -> bool {
         self.has_mod(Mods::FLIP_X)
     }
+
+    /// Whether the chart is drawn mirrored, either by the flip mod or by `visual_flip_x`.
+    #[inline]
+    pub fn render_flip_x(&self) -> bool {
+        self.flip_x() || self.visual_flip_x
+    }
+
+    #[inline]
+    pub fn note_kind_scale(&self, order: i8) -> f32 {
+        self.note_scale_per_kind.get(order as usize).copied().unwrap_or(1.)
+    }
+
+    #[inline]
+    pub fn note_tint(&self, order: i8) -> Option<Color> {
+        self.note_tints.get(order as usize).copied().flatten().map(Color::from_hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> Config {
+        let mut config: Config = serde_json::from_str(json).unwrap();
+        config.validate_and_migrate();
+        config
+    }
+
+    #[test]
+    fn clamps_out_of_range() {
+        let config = load(r#"{ "speed": 100, "volumeMusic": -1, "volumeSfx": 3.5, "volumeBgm": 0.5, "noteScale": 0, "comboDisplayStep": 0 }"#);
+        assert_eq!(config.speed, 4.);
+        assert_eq!(config.volume_music, 0.);
+        assert_eq!(config.volume_sfx, 2.);
+        assert_eq!(config.volume_bgm, 0.5);
+        assert_eq!(config.note_scale, 0.1);
+        assert_eq!(config.combo_display_step, 1);
+    }
+
+    #[test]
+    fn replaces_nan() {
+        // JSON has no NaN, YAML is a superset of it that does
+        let mut config: Config = serde_yaml::from_str("{ speed: .nan, volumeMusic: .nan, noteScale: .nan, accuracyTarget: .nan }").unwrap();
+        config.validate_and_migrate();
+        let default = Config::default();
+        assert_eq!(config.speed, default.speed);
+        assert_eq!(config.volume_music, default.volume_music);
+        assert_eq!(config.note_scale, default.note_scale);
+        assert_eq!(config.accuracy_target, None);
+    }
+
+    #[test]
+    fn migrates_legacy_autoplay() {
+        let config = load(r#"{ "autoplay": true }"#);
+        assert!(config.autoplay());
+        assert_eq!(config.autoplay, None);
+
+        let config = load(r#"{ "autoplay": false }"#);
+        assert!(!config.autoplay());
+        assert_eq!(config.autoplay, None);
+    }
+
+    #[test]
+    fn keeps_valid_config() {
+        let config = load(r#"{ "speed": 1.5, "volumeMusic": 0.8, "noteScale": 1.2, "comboDisplayStep": 25 }"#);
+        assert_eq!(config.speed, 1.5);
+        assert_eq!(config.volume_music, 0.8);
+        assert_eq!(config.note_scale, 1.2);
+        assert_eq!(config.combo_display_step, 25);
+        assert!(!config.autoplay());
+    }
 }
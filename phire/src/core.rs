@@ -13,10 +13,10 @@ mod anim;
 pub use anim::{Anim, AnimFloat, AnimVector, Keyframe};
 
 mod chart;
-pub use chart::{Chart, ChartExtra, ChartSettings, HitSoundMap};
+pub use chart::{Chart, ChartExtra, ChartSettings, ChartStats, ChartWarning, HitSoundMap, LineMotion, NoteRef};
 
 mod effect;
-pub use effect::{Effect, Uniform};
+pub use effect::{Effect, Uniform, UniformDefault};
 
 mod line;
 pub use line::{GifFrames, JudgeLine, JudgeLineCache, JudgeLineKind, UIElement};
@@ -29,7 +29,7 @@ mod object;
 pub use object::{CtrlObject, Object};
 
 mod render;
-pub use render::{copy_fbo, internal_id, MSRenderTarget};
+pub use render::{copy_fbo, internal_id, supported_sample_count, MSRenderTarget};
 
 mod resource;
 pub use resource::{NoteStyle, ParticleEmitter, ResPackInfo, Resource, ResourcePack, SfxMap, BUFFER_SIZE, DPI_VALUE};
@@ -69,6 +69,10 @@ impl Triple {
     }
 }
 
+/// Tempo map of a chart, converting between time (in seconds) and beats.
+///
+/// Lookups cache the last visited segment, which is why they take `&mut self`. Queries that move
+/// forward monotonically (as during playback) are amortized O(1).
 #[derive(Default, Clone)] // the default is a dummy
 pub struct BpmList {
     elements: Vec<(f32, f32, f32)>, // (beats, time, bpm)
@@ -76,6 +80,7 @@ pub struct BpmList {
 }
 
 impl BpmList {
+    /// Creates a tempo map from `(beat, bpm)` pairs sorted by beat. The first pair is assumed to start at time 0.
     pub fn new(ranges: Vec<(f32, f32)> /*(beat, bpm)*/) -> Self {
         let mut elements = Vec::new();
         let mut time = 0.0;
@@ -92,6 +97,21 @@ impl BpmList {
         BpmList { elements, cursor: 0 }
     }
 
+    /// Creates a tempo map from `(beat, bpm)` events in any order.
+    ///
+    /// Events with a non-positive or non-finite BPM are ignored. The tempo map always starts at beat 0: the last event at
+    /// or before it is moved there and the ones before are dropped, or the first one is extended backwards if all of
+    /// them come later. Returns `None` if there is no valid event at all.
+    pub fn from_events(events: impl IntoIterator<Item = (f32, f32)>) -> Option<Self> {
+        let mut events: Vec<_> = events.into_iter().filter(|(beat, bpm)| beat.is_finite() && bpm.is_finite() && *bpm > 0.).collect();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let start = events.iter().rposition(|(beat, _)| *beat <= 0.).unwrap_or(0);
+        events.drain(..start);
+        events.first_mut()?.0 = 0.;
+        Some(Self::new(events))
+    }
+
+    /// Creates a tempo map measured in seconds, where a "beat" is simply a second.
     pub fn new_time(ranges: Vec<(f32, f32)> /*(time, bpm)*/) -> Self {
         let mut elements = Vec::new();
         for (time, bpm) in ranges {
@@ -100,6 +120,7 @@ impl BpmList {
         BpmList { elements, cursor: 0 }
     }
 
+    /// Returns the time (in seconds) at the given beat. This is the inverse of [`BpmList::beat`].
     pub fn time_beats(&mut self, beats: f32) -> f32 {
         while let Some(kf) = self.elements.get(self.cursor + 1) {
             if kf.0 > beats {
@@ -114,10 +135,12 @@ impl BpmList {
         time + (beats - start_beats) * (60. / bpm)
     }
 
+    /// Returns the time (in seconds) at the given [`Triple`].
     pub fn time(&mut self, triple: &Triple) -> f32 {
         self.time_beats(triple.beats())
     }
 
+    /// Returns the (fractional) beat at the given time in seconds. This is the inverse of [`BpmList::time_beats`].
     pub fn beat(&mut self, time: f32) -> f32 {
         while let Some(kf) = self.elements.get(self.cursor + 1) {
             if kf.1 > time {
@@ -132,6 +155,15 @@ impl BpmList {
         beats + (time - start_time) / (60. / bpm)
     }
 
+    /// Returns the lowest and highest BPM, or `(0, 0)` for an empty tempo map.
+    pub fn bpm_range(&self) -> (f32, f32) {
+        self.elements
+            .iter()
+            .fold(None, |range: Option<(f32, f32)>, (_, _, bpm)| Some(range.map_or((*bpm, *bpm), |(min, max)| (min.min(*bpm), max.max(*bpm)))))
+            .unwrap_or_default()
+    }
+
+    /// Returns the BPM in effect at the given time in seconds.
     pub fn now_bpm(&mut self, time: f32) -> f32 {
         while let Some(kf) = self.elements.get(self.cursor + 1) {
             if kf.1 > time {
@@ -145,4 +177,59 @@ impl BpmList {
         let (_, _, bpm) = &self.elements[self.cursor];
         *bpm
     }
+
+    /// The BPM lasting the longest before `end`, which is what a chart's tempo is usually given as.
+    pub fn dominant_bpm(&self, end: f32) -> f32 {
+        let mut totals: Vec<(f32, f32)> = Vec::new(); // (bpm, duration)
+        for (i, (_, time, bpm)) in self.elements.iter().enumerate() {
+            let next = self.elements.get(i + 1).map_or(end, |it| it.1);
+            let duration = (next.min(end) - time).max(0.);
+            match totals.iter_mut().find(|it| (it.0 - bpm).abs() < 1e-3) {
+                Some(total) => total.1 += duration,
+                None => totals.push((*bpm, duration)),
+            }
+        }
+        totals.into_iter().reduce(|a, b| if b.1 > a.1 { b } else { a }).map_or(0., |it| it.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn bpm_list_round_trip() {
+        let mut list = BpmList::from_events([(16., 180.), (0., 120.), (8., 60.)]).unwrap();
+        for (beat, time) in [(0., 0.), (4., 2.), (8., 4.), (12., 8.), (16., 12.), (19., 13.)] {
+            assert_close(list.time_beats(beat), time);
+            assert_close(list.beat(time), beat);
+        }
+        for beat in [17.5, 3., 10.25, 0.5] {
+            let time = list.time_beats(beat);
+            assert_close(list.beat(time), beat);
+        }
+    }
+
+    #[test]
+    fn bpm_list_starts_at_zero() {
+        // the first event is extended backwards
+        let mut list = BpmList::from_events([(4., 120.)]).unwrap();
+        assert_close(list.time_beats(4.), 2.);
+
+        // events before beat 0 are superseded by the last of them
+        let mut list = BpmList::from_events([(-8., 60.), (-2., 120.), (4., 240.)]).unwrap();
+        assert_close(list.time_beats(4.), 2.);
+        assert_close(list.time_beats(8.), 3.);
+    }
+
+    #[test]
+    fn bpm_list_ignores_invalid_events() {
+        assert!(BpmList::from_events([(0., 0.), (4., f32::NAN), (f32::INFINITY, 120.)]).is_none());
+        let mut list = BpmList::from_events([(0., -120.), (0., 60.)]).unwrap();
+        assert_close(list.time_beats(1.), 1.);
+    }
 }
@@ -125,6 +125,27 @@ impl<T: Tweenable> Anim<T> {
         })
     }
 
+    /// Evaluates the animation at `time` without moving the cursor.
+    pub fn at_opt(&self, time: f32) -> Option<T> {
+        if self.keyframes.is_empty() {
+            return None;
+        }
+        let index = self.keyframes.partition_point(|it| it.time <= time).saturating_sub(1);
+        let value = if index == self.keyframes.len() - 1 {
+            self.keyframes[index].value.clone()
+        } else {
+            let kf1 = &self.keyframes[index];
+            let kf2 = &self.keyframes[index + 1];
+            let t = (time - kf1.time) / (kf2.time - kf1.time);
+            T::tween(&kf1.value, &kf2.value, kf1.tween.y(t))
+        };
+        Some(if let Some(next) = &self.next {
+            T::add(&value, &next.at_opt(time).unwrap())
+        } else {
+            value
+        })
+    }
+
     pub fn map_value(&mut self, mut f: impl FnMut(T) -> T) {
         self.keyframes.iter_mut().for_each(|it| it.value = f(it.value.clone()));
         if let Some(next) = &mut self.next {
@@ -137,6 +158,10 @@ impl<T: Tweenable + Default> Anim<T> {
     pub fn now(&self) -> T {
         self.now_opt().unwrap_or_default()
     }
+
+    pub fn at(&self, time: f32) -> T {
+        self.at_opt(time).unwrap_or_default()
+    }
 }
 
 pub type AnimFloat = Anim<f32>;
@@ -1,15 +1,37 @@
-use super::{BpmList, Effect, JudgeLine, JudgeLineKind, Matrix, Resource, UIElement, Vector, Video};
-use crate::{fs::FileSystem, judge::JudgeStatus, ui::Ui};
+use super::{AnimFloat, BpmList, Effect, JudgeLine, JudgeLineKind, Matrix, NoteKind, RenderConfig, Resource, UIElement, Vector, Video};
+use crate::{
+    config::Mods,
+    ext::NotNanExt,
+    fs::FileSystem,
+    judge::{JudgeStatus, LIMIT_BAD},
+    ui::Ui,
+};
 use anyhow::{Context, Result};
 use macroquad::prelude::*;
 use sasa::AudioClip;
-use std::{cell::RefCell, collections::HashMap};
+use serde::Serialize;
+use std::{cell::RefCell, collections::HashMap, fmt};
+use tracing::warn;
+
+const UNFOCUSED_ALPHA: f32 = 0.15;
+const DUPLICATE_EPS: f32 = 1e-4;
 
 #[derive(Default)]
 pub struct ChartExtra {
     pub effects: Vec<Effect>,
     pub global_effects: Vec<Effect>,
     pub videos: Vec<Video>,
+    /// Applied to the lines when the chart is created
+    pub line_motions: Vec<LineMotion>,
+}
+
+/// Motion added on top of a line's own, in the units of its [`Object`](super::Object).
+pub struct LineMotion {
+    pub line: usize,
+    pub x: Option<AnimFloat>,
+    pub y: Option<AnimFloat>,
+    pub rotation: Option<AnimFloat>,
+    pub alpha: Option<AnimFloat>,
 }
 
 #[derive(Default)]
@@ -20,6 +42,55 @@ pub struct ChartSettings {
 
 pub type HitSoundMap = HashMap<String, AudioClip>;
 
+/// Position of a note in [`Chart::lines`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NoteRef {
+    pub line: usize,
+    pub note: usize,
+}
+
+/// Metadata of a chart which can be computed without rendering it. Fake notes are not counted.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartStats {
+    pub click: u32,
+    pub hold: u32,
+    pub flick: u32,
+    pub drag: u32,
+    pub total: u32,
+    /// Time of the end of the last note, in seconds
+    pub length: f32,
+    /// Maximum number of notes within one second
+    pub peak_nps: u32,
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+}
+
+/// A likely mistake in a chart found by [`Chart::validate`]. Times are in seconds of the chart.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ChartWarning {
+    /// Two notes on the same line at the same time and position
+    DuplicateNote { line: usize, time: f32 },
+    /// A hold that ends before it starts
+    InvalidHold { line: usize, time: f32, end_time: f32 },
+    /// A note before the music starts, which can't be hit
+    NoteBeforeStart { line: usize, time: f32 },
+    /// A plain line that has no notes and isn't used as a parent or by the UI
+    EmptyLine { line: usize },
+}
+
+impl fmt::Display for ChartWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateNote { line, time } => write!(f, "line {line}: duplicate note at {time:.3}s"),
+            Self::InvalidHold { line, time, end_time } => write!(f, "line {line}: hold at {time:.3}s ends at {end_time:.3}s"),
+            Self::NoteBeforeStart { line, time } => write!(f, "line {line}: note at {time:.3}s is before the music starts"),
+            Self::EmptyLine { line } => write!(f, "line {line}: no notes"),
+        }
+    }
+}
+
 pub struct Chart {
     pub offset: f32,
     pub lines: Vec<JudgeLine>,
@@ -28,12 +99,48 @@ pub struct Chart {
     pub extra: ChartExtra,
 
     pub order: Vec<usize>,
+    /// Effects with a `z_index`, sorted by it
+    pub layered_effects: Vec<usize>,
     pub attach_ui: [Option<usize>; 7],
     pub hitsounds: HitSoundMap,
+    /// Line the beat grid is drawn along when no line is focused, the one with the most notes
+    pub grid_line: Option<usize>,
+}
+
+/// Adds `layer` to the end of the chain of `anim`, whose layers are summed up. An empty `anim` is seeded with `default`
+/// first, so the layer is applied on top of the value the property has without animation.
+fn add_anim_layer(anim: &mut AnimFloat, layer: AnimFloat, default: f32) {
+    // an empty head makes the whole chain evaluate to nothing
+    if anim.keyframes.is_empty() {
+        anim.keyframes = AnimFloat::fixed(default).keyframes;
+        anim.cursor = 0;
+    }
+    let mut last = anim;
+    while last.next.is_some() {
+        last = last.next.as_mut().unwrap();
+    }
+    last.next = Some(Box::new(layer));
 }
 
 impl Chart {
-    pub fn new(offset: f32, lines: Vec<JudgeLine>, bpm_list: BpmList, settings: ChartSettings, extra: ChartExtra, hitsounds: HitSoundMap) -> Self {
+    pub fn new(offset: f32, mut lines: Vec<JudgeLine>, bpm_list: BpmList, settings: ChartSettings, mut extra: ChartExtra, hitsounds: HitSoundMap) -> Self {
+        for motion in std::mem::take(&mut extra.line_motions) {
+            let Some(line) = lines.get_mut(motion.line) else {
+                warn!("line motion refers to missing line #{}", motion.line);
+                continue;
+            };
+            let object = &mut line.object;
+            for (anim, layer, default) in [
+                (&mut object.translation.0, motion.x, 0.),
+                (&mut object.translation.1, motion.y, 0.),
+                (&mut object.rotation, motion.rotation, 0.),
+                (&mut object.alpha, motion.alpha, 1.),
+            ] {
+                if let Some(layer) = layer {
+                    add_anim_layer(anim, layer, default);
+                }
+            }
+        }
         let mut attach_ui = [None; 7];
         let mut order = (0..lines.len())
             .filter(|it| {
@@ -46,6 +153,9 @@ impl Chart {
             })
             .collect::<Vec<_>>();
         order.sort_by_key(|it| (lines[*it].z_index, *it));
+        let mut layered_effects: Vec<usize> = (0..extra.effects.len()).filter(|it| extra.effects[*it].z_index.is_some()).collect();
+        layered_effects.sort_by_key(|it| (extra.effects[*it].z_index, *it));
+        let grid_line = order.iter().rev().copied().max_by_key(|it| lines[*it].notes.len());
         Self {
             offset,
             lines,
@@ -54,9 +164,128 @@ impl Chart {
             extra,
 
             order,
+            layered_effects,
             attach_ui,
             hitsounds,
+            grid_line,
+        }
+    }
+
+    pub fn statistics(&self) -> ChartStats {
+        let mut stats = ChartStats::default();
+        let mut times = Vec::new();
+        for note in self.lines.iter().flat_map(|it| it.notes.iter()).filter(|it| !it.fake) {
+            let end_time = match note.kind {
+                NoteKind::Click => {
+                    stats.click += 1;
+                    note.time
+                }
+                NoteKind::Hold { end_time, .. } => {
+                    stats.hold += 1;
+                    end_time
+                }
+                NoteKind::Flick => {
+                    stats.flick += 1;
+                    note.time
+                }
+                NoteKind::Drag => {
+                    stats.drag += 1;
+                    note.time
+                }
+            };
+            stats.length = stats.length.max(end_time);
+            times.push(note.time.not_nan());
+        }
+        stats.total = times.len() as u32;
+        times.sort_unstable();
+        let mut start = 0;
+        for (end, time) in times.iter().enumerate() {
+            while **time - *times[start] >= 1. {
+                start += 1;
+            }
+            stats.peak_nps = stats.peak_nps.max((end - start + 1) as u32);
         }
+        (stats.min_bpm, stats.max_bpm) = self.bpm_list.borrow().bpm_range();
+        stats
+    }
+
+    /// Note density over the chart length split into `buckets` time slices, normalized so the densest bucket is `1`.
+    pub fn density_map(&self, buckets: usize) -> Vec<f32> {
+        self.density_map_with(buckets, false)
+    }
+
+    /// Like [`Chart::density_map`], but holds also count for the time they are held, proportional to how much of each bucket they cover.
+    pub fn density_map_weighted(&self, buckets: usize) -> Vec<f32> {
+        self.density_map_with(buckets, true)
+    }
+
+    fn density_map_with(&self, buckets: usize, weight_holds: bool) -> Vec<f32> {
+        let mut map = vec![0.; buckets];
+        let notes: Vec<_> = self.lines.iter().flat_map(|it| it.notes.iter()).filter(|it| !it.fake).collect();
+        let length = notes
+            .iter()
+            .map(|it| match it.kind {
+                NoteKind::Hold { end_time, .. } => end_time.max(it.time),
+                _ => it.time,
+            })
+            .fold(0., f32::max);
+        if buckets == 0 || length <= 0. {
+            return map;
+        }
+        let bucket_len = length / buckets as f32;
+        let bucket_of = |time: f32| ((time.max(0.) / bucket_len) as usize).min(buckets - 1);
+        for note in notes {
+            map[bucket_of(note.time)] += 1.;
+            if let (true, NoteKind::Hold { end_time, .. }) = (weight_holds, &note.kind) {
+                let (start, end) = (note.time.max(0.), end_time.max(0.));
+                if end <= start {
+                    continue;
+                }
+                for (id, value) in map.iter_mut().enumerate().take(bucket_of(end) + 1).skip(bucket_of(start)) {
+                    let (lo, hi) = (id as f32 * bucket_len, (id + 1) as f32 * bucket_len);
+                    *value += (hi.min(end) - lo.max(start)).max(0.) / bucket_len;
+                }
+            }
+        }
+        let max = map.iter().copied().fold(0., f32::max);
+        if max > 0. {
+            map.iter_mut().for_each(|it| *it /= max);
+        }
+        map
+    }
+
+    /// Finds likely mistakes in the chart. Fake notes are ignored.
+    pub fn validate(&self) -> Vec<ChartWarning> {
+        let mut warnings = Vec::new();
+        let parents: Vec<usize> = self.lines.iter().filter_map(|it| it.parent).collect();
+        for (id, line) in self.lines.iter().enumerate() {
+            if line.notes.is_empty() {
+                if matches!(line.kind, JudgeLineKind::Normal) && line.attach_ui.is_none() && !parents.contains(&id) {
+                    warnings.push(ChartWarning::EmptyLine { line: id });
+                }
+                continue;
+            }
+            let mut positions = Vec::new();
+            for note in line.notes.iter().filter(|it| !it.fake) {
+                if let NoteKind::Hold { end_time, .. } = note.kind {
+                    if end_time <= note.time {
+                        warnings.push(ChartWarning::InvalidHold { line: id, time: note.time, end_time });
+                    }
+                }
+                if note.time + self.offset < 0. {
+                    warnings.push(ChartWarning::NoteBeforeStart { line: id, time: note.time });
+                }
+                positions.push((note.time.not_nan(), note.object.translation.0.at(note.time).not_nan(), note.above));
+            }
+            positions.sort_unstable();
+            for pair in positions.windows(2) {
+                let ((t1, x1, a1), (t2, x2, a2)) = (pair[0], pair[1]);
+                if a1 == a2 && *t2 - *t1 < DUPLICATE_EPS && (*x2 - *x1).abs() < DUPLICATE_EPS {
+                    warnings.push(ChartWarning::DuplicateNote { line: id, time: *t1 });
+                }
+            }
+        }
+        warnings
     }
 
     #[inline]
@@ -74,7 +303,7 @@ impl Chart {
             let ro = obj.new_rotation_wrt_point(-obj.rotation.now().to_radians(), rotation_point.map_or_else(|| Vector::default(), |(x, y)| Vector::new(x, y)));
             ui.with(Matrix::new_translation(&tr) * ro * scale, |ui| f(ui, color))
         } else {
-            f(ui, WHITE)
+            Self::with_layout(ui, res, element, scale_point, f)
         }
     }
 
@@ -89,14 +318,31 @@ impl Chart {
             scale.m11 = 1.0;
             ui.with(obj.now_rotation().append_translation(&tr) * scale, |ui| f(ui, color))
         } else {
-            f(ui, WHITE)
+            Self::with_layout(ui, res, element, ct, f)
         }
     }
 
-    pub async fn load_textures(&mut self, fs: &mut dyn FileSystem) -> Result<()> {
+    /// Applies the user's layout override of `element`, scaling around `center`.
+    fn with_layout<R>(ui: &mut Ui, res: &Resource, element: UIElement, center: Option<(f32, f32)>, f: impl FnOnce(&mut Ui, Color) -> R) -> R {
+        let Some(layout) = res.config.ui_layout.get(&element) else {
+            return f(ui, WHITE);
+        };
+        let center = center.map_or_else(Vector::default, |(x, y)| Vector::new(x, y));
+        let tr = Matrix::new_translation(&(center + Vector::new(layout.offset.0, layout.offset.1)))
+            * Matrix::new_nonuniform_scaling(&Vector::new(layout.scale, layout.scale))
+            * Matrix::new_translation(&-center);
+        ui.with(tr, |ui| f(ui, WHITE))
+    }
+
+    /// Loads the textures of texture lines, reporting `(loaded, total)` after each one.
+    pub async fn load_textures(&mut self, fs: &mut dyn FileSystem, mut progress: impl FnMut(usize, usize)) -> Result<()> {
+        let total = self.lines.iter().filter(|it| matches!(it.kind, JudgeLineKind::Texture(..))).count();
+        let mut loaded = 0;
         for line in &mut self.lines {
             if let JudgeLineKind::Texture(tex, path) = &mut line.kind {
                 *tex = image::load_from_memory(&fs.load_file(path).await.with_context(|| format!("failed to load illustration {path}"))?)?.into();
+                loaded += 1;
+                progress(loaded, total);
             }
         }
         Ok(())
@@ -114,8 +360,9 @@ impl Chart {
             line.cache.reset(&mut line.notes);
         }
         for video in &mut self.extra.videos {
-            video.next_frame = 0;
-            video.ended = false;
+            if let Err(err) = video.seek(0.) {
+                warn!("failed to seek video: {err:?}");
+            }
         }
     }
 
@@ -135,25 +382,310 @@ impl Chart {
         }
     }
 
+    /// Moves every animation of the chart to `time` without judging notes, emitting particles or running effect
+    /// callbacks, e.g. to render another moment of the chart.
+    pub fn set_time(&mut self, time: f32) {
+        for line in &mut self.lines {
+            line.set_time(time);
+        }
+        for effect in &mut self.extra.effects {
+            effect.set_time(time);
+        }
+    }
+
+    /// Notes that would be drawn right now with their bounding rects, in the same coordinates as hit effects.
+    ///
+    /// Only reads the chart and the resource, so it can be called at any point of a frame outside of line rendering.
+    /// Notes hidden by `appear_before` are only filtered out when the BPM list isn't borrowed elsewhere.
+    pub fn visible_notes(&self, res: &Resource) -> Vec<(NoteRef, Rect)> {
+        let mut result = Vec::new();
+        if res.config.note_scale <= 0. || !res.config.render_note {
+            return result;
+        }
+        let mut bpm_list = self.bpm_list.try_borrow_mut().ok();
+        let flip_x = if res.config.render_flip_x() { -1. } else { 1. };
+        let below = Matrix::identity().append_nonuniform_scaling(&Vector::new(1., -1.));
+        for (line_id, line) in self.lines.iter().enumerate() {
+            let alpha = line.object.alpha.now_opt().unwrap_or(1.0);
+            let mut ctrl_obj = line.ctrl_obj.borrow().clone();
+            let mut config = RenderConfig {
+                settings: &self.settings,
+                ctrl_obj: &mut ctrl_obj,
+                line_height: line.height.now(),
+                appear_before: f32::INFINITY,
+                invisible_time: if res.config.has_mod(Mods::FADE_OUT) { LIMIT_BAD } else { f32::INFINITY },
+                draw_below: line.show_below || res.config.always_draw_below,
+                incline_sin: line.incline.now_opt().map(|it| it.to_radians().sin()).unwrap_or_default(),
+            };
+            if alpha < 0. {
+                if !self.settings.pe_alpha_extension {
+                    continue;
+                }
+                match (-alpha).floor() as u32 {
+                    1 => continue,
+                    2 => config.draw_below = res.config.always_draw_below,
+                    w if (100..1000).contains(&w) => config.appear_before = (w as f32 - 100.) / 10.,
+                    _ => {}
+                }
+            }
+            let model = line.now_transform(res, &self.lines);
+            for (id, note) in line.notes.iter().enumerate() {
+                let model = if note.above { model } else { model * below };
+                if let Some(rect) = note.screen_rect(res, &model, &mut config, bpm_list.as_deref_mut()) {
+                    // the chart is drawn mirrored vertically, see `render`
+                    let rect = Rect::new(if flip_x < 0. { -rect.right() } else { rect.x }, -rect.bottom(), rect.w, rect.h);
+                    result.push((NoteRef { line: line_id, note: id }, rect));
+                }
+            }
+        }
+        result
+    }
+
     pub fn render(&self, ui: &mut Ui, res: &mut Resource) {
-        res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.flip_x() { -1. } else { 1. }, 1.)), |res| {
+        res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.render_flip_x() { -1. } else { 1. }, 1.)), |res| {
+            if res.config.minimal_render {
+                return;
+            }
             for video in &self.extra.videos {
                 video.render(res);
             }
         });
-        res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.flip_x() { -1. } else { 1. }, -1.)), |res| {
-            let mut guard = self.bpm_list.borrow_mut();
-            for id in &self.order {
-                self.lines[*id].render(ui, res, &self.lines, &mut guard, &self.settings, *id);
-            }
-            drop(guard);
-            res.note_buffer.borrow_mut().draw_all();
-            if res.config.sample_count > 1 {
-                unsafe { get_internal_gl() }.flush();
-                if let Some(target) = &res.chart_target {
-                    target.blit();
+        let layered = res.chart_target.is_some() && !res.no_effect && !res.config.minimal_render;
+        let splits = if layered {
+            self.layer_splits(self.layered_effects.iter().filter_map(|it| self.extra.effects[*it].z_index))
+        } else {
+            Vec::new()
+        };
+        let mut start = 0;
+        // lines are drawn in segments, each followed by the effects layered right above them
+        for segment in 0..=splits.len() {
+            let end = splits.get(segment).copied().unwrap_or(self.order.len());
+            res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.render_flip_x() { -1. } else { 1. }, -1.)), |res| {
+                let mut guard = self.bpm_list.borrow_mut();
+                // the grid is drawn along a single line, many lines would each add their own copy
+                let grid_line = if res.config.beat_grid { res.focus_line.or(self.grid_line) } else { None };
+                for id in &self.order[start..end] {
+                    let alpha = res.alpha;
+                    if res.focus_line.map_or(false, |it| it != *id) {
+                        res.alpha *= UNFOCUSED_ALPHA;
+                    }
+                    self.lines[*id].render(ui, res, &self.lines, &mut guard, &self.settings, *id, grid_line == Some(*id));
+                    res.alpha = alpha;
+                }
+                drop(guard);
+                res.note_buffer.borrow_mut().draw_all();
+                if res.config.sample_count > 1 {
+                    unsafe { get_internal_gl() }.flush();
+                    if let Some(target) = &res.chart_target {
+                        target.blit();
+                    }
                 }
+            });
+            start = end;
+            if segment < splits.len() {
+                Self::render_layered_effect(&self.extra.effects[self.layered_effects[segment]], res);
             }
+        }
+    }
+
+    /// Number of lines in [`Chart::order`] drawn before each of the effects with the given `z_index`es, which must be
+    /// sorted. An effect is drawn right above the lines with a `z_index` not above its own.
+    fn layer_splits(&self, effect_z: impl IntoIterator<Item = i32>) -> Vec<usize> {
+        effect_z.into_iter().map(|z| self.order.partition_point(|it| self.lines[*it].z_index <= z)).collect()
+    }
+
+    fn render_layered_effect(effect: &Effect, res: &mut Resource) {
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let projection = gl.quad_gl.get_projection_matrix();
+        let pass = gl.quad_gl.get_active_render_pass();
+        let viewport = gl.quad_gl.get_viewport();
+        set_camera(&Camera2D {
+            zoom: vec2(1., res.aspect_ratio),
+            ..Default::default()
         });
+        effect.render(res);
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let target = res.chart_target.as_ref().unwrap();
+        let pass = if res.config.sample_count > 1 {
+            // the following lines are drawn into the multisampled buffer, which hasn't got the effect applied yet
+            gl.quad_gl.render_pass(Some(target.input().render_pass));
+            let top = 1. / res.aspect_ratio;
+            draw_texture_ex(
+                target.output().texture,
+                -1.,
+                -top,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(2., top * 2.)),
+                    ..Default::default()
+                },
+            );
+            gl.flush();
+            pass
+        } else {
+            // the effect swaps the output texture
+            Some(target.output().render_pass)
+        };
+        gl.quad_gl.set_projection_matrix(projection);
+        gl.quad_gl.render_pass(pass);
+        gl.quad_gl.viewport(viewport);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Anim, AnimVector, CtrlObject, HitSound, JudgeLineCache, Note, Object};
+
+    fn note(kind: NoteKind, time: f32, x: f32, fake: bool) -> Note {
+        Note {
+            object: Object {
+                translation: AnimVector(AnimFloat::fixed(x), AnimFloat::default()),
+                ..Default::default()
+            },
+            kind,
+            hitsound: HitSound::None,
+            time,
+            height: time,
+            speed: 1.,
+            above: true,
+            multiple_hint: false,
+            fake,
+            judge: JudgeStatus::NotJudged,
+            attr: false,
+        }
+    }
+
+    fn hold(end_time: f32) -> NoteKind {
+        NoteKind::Hold {
+            end_time,
+            end_height: end_time,
+            end_speed: 1.,
+        }
+    }
+
+    fn line(mut notes: Vec<Note>) -> JudgeLine {
+        JudgeLine {
+            object: Object::default(),
+            ctrl_obj: RefCell::new(CtrlObject::default()),
+            kind: JudgeLineKind::Normal,
+            height: AnimFloat::default(),
+            incline: AnimFloat::default(),
+            cache: JudgeLineCache::new(&mut notes),
+            notes,
+            color: Anim::default(),
+            parent: None,
+            z_index: 0,
+            show_below: false,
+            attach_ui: None,
+            anchor: [0.5, 0.5],
+        }
+    }
+
+    fn chart(lines: Vec<JudgeLine>) -> Chart {
+        Chart::new(0., lines, BpmList::new(vec![(0., 120.), (16., 180.)]), ChartSettings::default(), ChartExtra::default(), HitSoundMap::new())
+    }
+
+    #[test]
+    fn statistics_skip_fake_notes() {
+        let chart = chart(vec![
+            line(vec![
+                note(NoteKind::Click, 1., 0., false),
+                note(NoteKind::Click, 1.5, 0.2, false),
+                note(hold(4.), 2., 0., false),
+                note(NoteKind::Flick, 9., 0., true),
+            ]),
+            line(vec![
+                note(NoteKind::Drag, 1.2, 0., false),
+                note(NoteKind::Drag, 1.4, 0., false),
+                note(NoteKind::Flick, 3., 0., false),
+                note(NoteKind::Click, 1.1, 0., true),
+                note(hold(20.), 1., 0., true),
+            ]),
+        ]);
+        let stats = chart.statistics();
+        assert_eq!((stats.click, stats.hold, stats.flick, stats.drag), (2, 1, 1, 2));
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.length, 4.);
+        // the notes at 1.0 and 2.0 are a second apart, so at most four fall within one second
+        assert_eq!(stats.peak_nps, 4);
+        assert_eq!((stats.min_bpm, stats.max_bpm), (120., 180.));
+
+        let empty = chart(vec![line(Vec::new())]).statistics();
+        assert_eq!((empty.total, empty.length, empty.peak_nps), (0, 0., 0));
+    }
+
+    #[test]
+    fn validate_finds_each_warning() {
+        let valid = || vec![note(NoteKind::Click, 1., 0., false)];
+        let warnings = |lines: Vec<JudgeLine>| chart(lines).validate();
+
+        assert!(warnings(vec![line(valid())]).is_empty());
+
+        let duplicate = vec![note(NoteKind::Click, 1., 0.3, false), note(NoteKind::Drag, 1., 0.3, false), note(NoteKind::Click, 1., 0.5, false)];
+        assert_eq!(warnings(vec![line(duplicate)]), [ChartWarning::DuplicateNote { line: 0, time: 1. }]);
+
+        let invalid_hold = vec![note(hold(1.), 2., 0., false)];
+        assert_eq!(warnings(vec![line(valid()), line(invalid_hold)]), [ChartWarning::InvalidHold { line: 1, time: 2., end_time: 1. }]);
+
+        let before_start = vec![note(NoteKind::Click, -0.5, 0., false)];
+        assert_eq!(warnings(vec![line(before_start)]), [ChartWarning::NoteBeforeStart { line: 0, time: -0.5 }]);
+
+        assert_eq!(warnings(vec![line(valid()), line(Vec::new())]), [ChartWarning::EmptyLine { line: 1 }]);
+
+        // fake notes, parents and lines attached to the UI are fine
+        let fake = vec![note(hold(1.), 2., 0., true), note(NoteKind::Click, -1., 0., true), note(NoteKind::Click, -1., 0., true)];
+        let mut child = line(valid());
+        child.parent = Some(1);
+        let mut attached = line(Vec::new());
+        attached.attach_ui = Some(UIElement::Pause);
+        assert!(warnings(vec![line(fake), line(Vec::new()), child, attached]).is_empty());
+    }
+
+    #[test]
+    fn density_map_of_even_notes_is_flat() {
+        // one note in the middle of every tenth of the chart, fake notes don't count
+        let mut notes: Vec<_> = (0..10).map(|i| note(NoteKind::Click, i as f32 + 0.5, 0., false)).collect();
+        notes.push(note(NoteKind::Click, 2.5, 0.5, true));
+        notes.push(note(NoteKind::Click, 30., 0., true));
+        let even = chart(vec![line(notes)]);
+        assert_eq!(even.density_map(10), [1.; 10]);
+        assert_eq!(even.density_map(5), [1.; 5]);
+        assert!(even.density_map(0).is_empty());
+
+        assert_eq!(chart(vec![line(Vec::new())]).density_map(4), [0.; 4]);
+    }
+
+    #[test]
+    fn effects_layer_between_lines() {
+        let lines = [3, 0, 2, 5, 1, 2]
+            .into_iter()
+            .map(|z| {
+                let mut line = line(Vec::new());
+                line.z_index = z;
+                line
+            })
+            .collect();
+        let chart = chart(lines);
+        // lines with the same z_index keep their order
+        assert_eq!(chart.order, [1, 4, 2, 5, 0, 3]);
+        assert_eq!(chart.layer_splits([-1, 0, 2, 2, 4, 10]), [0, 1, 4, 4, 5, 6]);
+        assert!(chart.layer_splits([]).is_empty());
+    }
+
+    #[test]
+    fn anim_layer_on_empty_head() {
+        let mut alpha = AnimFloat::default();
+        add_anim_layer(&mut alpha, AnimFloat::fixed(-0.25), 1.);
+        alpha.set_time(0.);
+        assert_eq!(alpha.now_opt(), Some(0.75));
+
+        let mut x = AnimFloat::fixed(0.5);
+        add_anim_layer(&mut x, AnimFloat::fixed(0.25), 0.);
+        add_anim_layer(&mut x, AnimFloat::fixed(0.125), 0.);
+        x.set_time(0.);
+        assert_eq!(x.now_opt(), Some(0.875));
     }
 }
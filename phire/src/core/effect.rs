@@ -1,5 +1,5 @@
 use super::{Anim, Resource, Tweenable};
-use crate::ext::{get_viewport, screen_aspect};
+use crate::ext::{get_viewport, screen_aspect, SafeTexture};
 use anyhow::{anyhow, bail, Result};
 use macroquad::prelude::*;
 use miniquad::UniformType;
@@ -11,13 +11,16 @@ use std::{collections::HashSet, ops::Range};
 static SHADERS: phf::Map<&'static str, &'static str> = phf_map! {
     "chromatic" => include_str!("shaders/chromatic.glsl"),
     "circleBlur" => include_str!("shaders/circle_blur.glsl"),
+    "deuteranopia" => include_str!("shaders/deuteranopia.glsl"),
     "fisheye" => include_str!("shaders/fisheye.glsl"),
     "glitch" => include_str!("shaders/glitch.glsl"),
     "grayscale" => include_str!("shaders/grayscale.glsl"),
     "noise" => include_str!("shaders/noise.glsl"),
     "pixel" => include_str!("shaders/pixel.glsl"),
+    "protanopia" => include_str!("shaders/protanopia.glsl"),
     "radialBlur" => include_str!("shaders/radial_blur.glsl"),
     "shockwave" => include_str!("shaders/shockwave.glsl"),
+    "tritanopia" => include_str!("shaders/tritanopia.glsl"),
     "vignette" => include_str!("shaders/vignette.glsl"),
 };
 
@@ -102,13 +105,40 @@ impl<T: UniformValue + Tweenable> Uniform for (String, Anim<T>) {
     }
 }
 
+/// Default value of a shader uniform, declared as `uniform <type> <name>; // %<default>%`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformDefault {
+    Float(f32),
+    Vec2(Vec2),
+    Color(Color),
+}
+
+impl UniformDefault {
+    /// GLSL type of the uniform
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Float(_) => "float",
+            Self::Vec2(_) => "vec2",
+            Self::Color(_) => "vec4",
+        }
+    }
+}
+
 pub struct Effect {
     time_range: Range<f32>,
     t: f32,
     material: Material,
     defaults: Vec<Box<dyn Uniform>>,
     uniforms: Vec<Box<dyn Uniform>>,
+    textures: Vec<(String, SafeTexture)>,
+    active: bool,
     pub global: bool,
+    /// Renders the effect right after the lines with a `z_index` not above this, instead of after the whole chart
+    pub z_index: Option<i32>,
+    /// Called with the current time on the first frame the effect becomes active
+    pub on_enter: Option<Box<dyn FnMut(f32)>>,
+    /// Called with the current time on the first frame the effect becomes inactive
+    pub on_exit: Option<Box<dyn FnMut(f32)>>,
 }
 
 impl Effect {
@@ -120,31 +150,69 @@ impl Effect {
         RPE_SHADERS.get(name).copied()
     }
 
-    pub fn new(time_range: Range<f32>, shader: &str, uniforms: Vec<Box<dyn Uniform>>, global: bool) -> Result<Self> {
+    /// Names of the built-in presets, sorted
+    pub fn preset_names() -> Vec<&'static str> {
+        let mut names: Vec<_> = SHADERS.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Names of the built-in RPE presets, sorted
+    pub fn rpe_preset_names() -> Vec<&'static str> {
+        let mut names: Vec<_> = RPE_SHADERS.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Parses the uniforms declaring a default value in the shader source.
+    pub fn uniform_defaults(shader: &str) -> Result<Vec<(String, UniformDefault)>> {
         static DEF_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"uniform\s+(\w+)\s+(\w+);\s+//\s+%([^%]+)%").unwrap());
-        let defaults = DEF_REGEX
+        DEF_REGEX
             .captures_iter(shader)
-            .map(|caps| -> Result<Box<dyn Uniform>> {
+            .map(|caps| {
                 let type_name = caps.get(1).unwrap().as_str();
                 let name = caps.get(2).unwrap().as_str().to_owned();
                 let value = caps.get(3).unwrap().as_str();
-                Ok(match type_name {
-                    "float" => Box::new((name, value.parse::<f32>()?)),
-                    "vec2" => Box::new((name, {
-                        let (x, y) = value.split_once(',').ok_or_else(|| anyhow!("Expected x,y"))?;
-                        vec2(x.trim().parse()?, y.trim().parse()?)
-                    })),
-                    "vec4" => Box::new((name, {
-                        let values: Vec<_> = value.split(',').map(|it| it.trim()).collect();
-                        if values.len() != 4 {
-                            bail!("Expected r,g,b,a");
-                        }
-                        Color::new(values[0].parse()?, values[1].parse()?, values[2].parse()?, values[3].parse()?)
-                    })),
-                    _ => bail!("Unknown type: {type_name}"),
-                })
+                Ok((
+                    name,
+                    match type_name {
+                        "float" => UniformDefault::Float(value.parse::<f32>()?),
+                        "vec2" => UniformDefault::Vec2({
+                            let (x, y) = value.split_once(',').ok_or_else(|| anyhow!("Expected x,y"))?;
+                            vec2(x.trim().parse()?, y.trim().parse()?)
+                        }),
+                        "vec4" => UniformDefault::Color({
+                            let values: Vec<_> = value.split(',').map(|it| it.trim()).collect();
+                            if values.len() != 4 {
+                                bail!("Expected r,g,b,a");
+                            }
+                            Color::new(values[0].parse()?, values[1].parse()?, values[2].parse()?, values[3].parse()?)
+                        }),
+                        _ => bail!("Unknown type: {type_name}"),
+                    },
+                ))
             })
-            .collect::<Result<Vec<Box<dyn Uniform>>>>()?;
+            .collect()
+    }
+
+    pub fn new(
+        time_range: Range<f32>,
+        shader: &str,
+        uniforms: Vec<Box<dyn Uniform>>,
+        textures: Vec<(String, SafeTexture)>,
+        global: bool,
+    ) -> Result<Self> {
+        static SAMPLER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"uniform\s+sampler2D\s+(\w+);\s+//\s+sampler\b").unwrap());
+        let defaults = Self::uniform_defaults(shader)?
+            .into_iter()
+            .map(|(name, value)| -> Box<dyn Uniform> {
+                match value {
+                    UniformDefault::Float(value) => Box::new((name, value)),
+                    UniformDefault::Vec2(value) => Box::new((name, value)),
+                    UniformDefault::Color(value) => Box::new((name, value)),
+                }
+            })
+            .collect::<Vec<_>>();
         let mut ocurred_uniforms = HashSet::new();
         let mut new_uniforms = Vec::new();
         let mut add_uniform = |(name, its_type): (String, UniformType)| {
@@ -161,6 +229,18 @@ impl Effect {
         for u in &uniforms {
             add_uniform(u.uniform_pair());
         }
+        let mut sampler_names = vec!["screenTexture".to_owned()];
+        for caps in SAMPLER_REGEX.captures_iter(shader) {
+            let name = caps.get(1).unwrap().as_str();
+            if !textures.iter().any(|(it, _)| it == name) {
+                bail!("Missing texture for sampler: {name}");
+            }
+        }
+        for (name, _) in &textures {
+            if !sampler_names.contains(name) {
+                sampler_names.push(name.clone());
+            }
+        }
         Ok(Self {
             time_range,
             t: f32::NEG_INFINITY,
@@ -170,17 +250,34 @@ impl Effect {
                 shader,
                 MaterialParams {
                     uniforms: new_uniforms,
-                    textures: vec!["screenTexture".to_owned()],
+                    textures: sampler_names,
                     ..Default::default()
                 },
             )?,
             uniforms,
+            textures,
+            active: false,
             global,
+            z_index: None,
+            on_enter: None,
+            on_exit: None,
         })
     }
 
     pub fn update(&mut self, res: &Resource) {
         let t = res.time;
+        self.t = t;
+        let active = self.time_range.contains(&t);
+        if active != std::mem::replace(&mut self.active, active) {
+            if let Some(callback) = if active { &mut self.on_enter } else { &mut self.on_exit } {
+                callback(t);
+            }
+        }
+        self.set_time(t);
+    }
+
+    /// Moves the effect to `t` without running `on_enter` or `on_exit`.
+    pub fn set_time(&mut self, t: f32) {
         self.t = t;
         if self.time_range.contains(&t) {
             for uniform in &mut self.uniforms {
@@ -207,6 +304,9 @@ impl Effect {
         target.swap();
         let tex = target.old().texture;
         self.material.set_texture("screenTexture", tex);
+        for (name, texture) in &self.textures {
+            self.material.set_texture(name, **texture);
+        }
         let screen_dim = vec2(tex.width(), tex.height());
         self.material.set_uniform("screenSize", screen_dim);
         gl.quad_gl.render_pass(Some(target.output().render_pass));
@@ -9,10 +9,17 @@ use crate::{
 use macroquad::prelude::*;
 use miniquad::{RenderPass, Texture, TextureParams, TextureWrap};
 use nalgebra::Rotation2;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 
-#[derive(Clone, Copy, Deserialize)]
+const BEAT_GRID_WIDTH: f32 = 1.;
+const BEAT_GRID_MAX_BEATS: u32 = 64;
+const REACTIVE_MIN_BRIGHTNESS: f32 = 0.35;
+const LINE_THICKNESS: f32 = 0.0075;
+const LINE_GLOW_WIDTH: f32 = 0.04; // at full glow, on each side of the line
+const LINE_GLOW_ALPHA: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum UIElement {
@@ -155,6 +162,32 @@ unsafe impl Sync for JudgeLine {}
 unsafe impl Send for JudgeLine {}
 
 impl JudgeLine {
+    fn set_own_time(&mut self, time: f32) {
+        match &mut self.kind {
+            JudgeLineKind::Text(anim) => {
+                anim.set_time(time);
+            }
+            JudgeLineKind::Paint(anim, ..) => {
+                anim.set_time(time);
+            }
+            JudgeLineKind::TextureGif(anim, ..) => {
+                anim.set_time(time);
+            }
+            _ => {}
+        }
+        self.color.set_time(time);
+    }
+
+    /// Moves the animations of the line and its notes to `time`, leaving the judgement state alone.
+    pub fn set_time(&mut self, time: f32) {
+        self.object.set_time(time);
+        self.height.set_time(time);
+        self.set_own_time(time);
+        for note in &mut self.notes {
+            note.object.set_time(time);
+        }
+    }
+
     pub fn update(&mut self, res: &mut Resource, tr: Matrix, bpm_list: &mut BpmList, index: usize) {
         // self.object.set_time(res.time); // this is done by chart, chart has to calculate transform for us
         let rot = self.object.rotation.now();
@@ -167,19 +200,7 @@ impl JudgeLine {
             !note.dead()
         });
         drop(ctrl_obj);
-        match &mut self.kind {
-            JudgeLineKind::Text(anim) => {
-                anim.set_time(res.time);
-            }
-            JudgeLineKind::Paint(anim, ..) => {
-                anim.set_time(res.time);
-            }
-            JudgeLineKind::TextureGif(anim, ..) => {
-                anim.set_time(res.time);
-            }
-            _ => {}
-        }
-        self.color.set_time(res.time);
+        self.set_own_time(res.time);
         self.cache.above_indices.retain_mut(|index| {
             while matches!(self.notes[*index].judge, JudgeStatus::Judged) {
                 if self
@@ -228,7 +249,37 @@ impl JudgeLine {
             self.object.now_rotation().append_translation(&Self::fetch_pos(self, res, lines))
     }
 
-    pub fn render(&self, ui: &mut Ui, res: &mut Resource, lines: &[JudgeLine], bpm_list: &mut BpmList, settings: &ChartSettings, id: usize) {
+    /// Draws a line at every upcoming beat, at the height a note on that beat would have.
+    fn render_beat_grid(&self, ui: &mut Ui, res: &mut Resource, bpm_list: &mut BpmList, line_height: f32, height_above: f32) {
+        let speed = res.config.note_speed_mult;
+        let mut height = self.height.clone();
+        let first = bpm_list.beat(res.time).ceil() as u32;
+        res.with_model(Matrix::identity().append_nonuniform_scaling(&Vector::new(1.0, -1.0)), |res| {
+            res.apply_model(|res| {
+                for beat in first..first + BEAT_GRID_MAX_BEATS {
+                    height.set_time(bpm_list.time_beats(beat as f32));
+                    let h = (height.now() - line_height) * speed;
+                    if h > height_above {
+                        break;
+                    }
+                    let y = -h / res.aspect_ratio;
+                    let downbeat = beat % 4 == 0;
+                    let color = Color::new(1., 1., 1., res.alpha * if downbeat { 0.5 } else { 0.15 });
+                    ui.fill_rect(Rect::new(-BEAT_GRID_WIDTH, y - 0.002, BEAT_GRID_WIDTH * 2., 0.004), color);
+                    if downbeat {
+                        ui.text(format!("#{}", beat / 4 + 1))
+                            .pos(-BEAT_GRID_WIDTH, y - 0.01)
+                            .anchor(0., 1.)
+                            .size(0.35)
+                            .color(color)
+                            .draw();
+                    }
+                }
+            });
+        });
+    }
+
+    pub fn render(&self, ui: &mut Ui, res: &mut Resource, lines: &[JudgeLine], bpm_list: &mut BpmList, settings: &ChartSettings, id: usize, beat_grid: bool) {
         let alpha = self.object.alpha.now_opt().unwrap_or(1.0);
         let color = self.color.now_opt();
         res.with_model(self.now_transform(res, lines), |res| {
@@ -241,8 +292,37 @@ impl JudgeLine {
                             if color.a == 0.0 {
                                 return;
                             }
+                            if res.config.reactive_lines {
+                                let k = REACTIVE_MIN_BRIGHTNESS + (1. - REACTIVE_MIN_BRIGHTNESS) * res.audio_level;
+                                color.r *= k;
+                                color.g *= k;
+                                color.b *= k;
+                            }
                             let len = res.info.line_length;
-                            draw_line(-len, 0., len, 0., 0.0075, color);
+                            if let Some(texture) = &res.judge_line_texture {
+                                let h = len * 2. * texture.height() / texture.width();
+                                draw_texture_ex(
+                                    **texture,
+                                    -len,
+                                    -h / 2.,
+                                    color,
+                                    DrawTextureParams {
+                                        dest_size: Some(vec2(len * 2., h)),
+                                        flip_y: true,
+                                        ..Default::default()
+                                    },
+                                );
+                            } else {
+                                let glow = res.config.judge_line_glow;
+                                if glow > 0. {
+                                    let w = LINE_GLOW_WIDTH * glow;
+                                    let edge = Color { a: color.a * LINE_GLOW_ALPHA * glow.min(1.), ..color };
+                                    let clear = Color { a: 0., ..color };
+                                    ui.fill_rect(Rect::new(-len, 0., len * 2., w), (edge, (0., 0.), clear, (0., w)));
+                                    ui.fill_rect(Rect::new(-len, -w, len * 2., w), (edge, (0., 0.), clear, (0., -w)));
+                                }
+                                draw_line(-len, 0., len, 0., LINE_THICKNESS * res.config.judge_line_thickness, color);
+                            }
                         }
                     }
                     JudgeLineKind::Texture(texture, _) => {
@@ -377,7 +457,7 @@ impl JudgeLine {
                 line_height: self.height.now(),
                 appear_before: f32::INFINITY,
                 invisible_time: f32::INFINITY,
-                draw_below: self.show_below,
+                draw_below: self.show_below || res.config.always_draw_below,
                 incline_sin: self.incline.now_opt().map(|it| it.to_radians().sin()).unwrap_or_default(),
             };
             if res.config.has_mod(Mods::FADE_OUT) {
@@ -402,7 +482,7 @@ impl JudgeLine {
                         }
                     }
                     2 => {
-                        config.draw_below = false;
+                        config.draw_below = res.config.always_draw_below;
                     }
                     w if (100..1000).contains(&w) => {
                         config.appear_before = (w as f32 - 100.) / 10.;
@@ -422,7 +502,10 @@ impl JudgeLine {
             ];
             let height_above = p[0].y.max(p[1].y.max(p[2].y.max(p[3].y))) * res.aspect_ratio;
             let height_below = p[0].y.min(p[1].y.min(p[2].y.min(p[3].y))) * res.aspect_ratio;
-            let agg = res.config.aggressive;
+            let speed_mult = res.config.note_speed_mult;
+            // culling assumes notes scroll towards the line, which doesn't hold for negative speeds
+            let aggressive = res.config.aggressive;
+            let agg = |speed: f32| aggressive && speed * speed_mult > 0.;
             let mut height = self.height.clone();
             if res.config.note_scale > 0. && res.config.render_note {
                 for note in self.notes.iter().take(self.cache.not_plain_count).filter(|it| it.above) {
@@ -431,10 +514,10 @@ impl JudgeLine {
                         height.now()
                     };
                     let note_height = note.height - line_height + note.object.translation.1.now();
-                    if agg && note_height < height_below / note.speed && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
+                    if agg(note.speed) && note_height < height_below / (note.speed * speed_mult) && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
                         continue;
                     }
-                    if agg && note_height > height_above / note.speed && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
+                    if agg(note.speed) && note_height > height_above / (note.speed * speed_mult) && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
                         break;
                     }
                     note.render(ui, res, &mut config, bpm_list, line_set_debug_alpha, id);
@@ -446,10 +529,10 @@ impl JudgeLine {
                             break;
                         }
                         let note_height = note.height - config.line_height + note.object.translation.1.now();
-                        if agg && note_height < height_below / speed {
+                        if agg(speed) && note_height < height_below / (speed * speed_mult) {
                             continue;
                         }
-                        if agg && note_height > height_above / speed {
+                        if agg(speed) && note_height > height_above / (speed * speed_mult) {
                             break;
                         }
                         note.render(ui, res, &mut config, bpm_list, line_set_debug_alpha, id);
@@ -463,10 +546,10 @@ impl JudgeLine {
                             height.now()
                         };
                         let note_height = note.height - line_height + note.object.translation.1.now();
-                        if agg && note_height < -height_above / note.speed && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
+                        if agg(note.speed) && note_height < -height_above / (note.speed * speed_mult) && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
                             continue;
                         }
-                        if agg && note_height > -height_below / note.speed && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
+                        if agg(note.speed) && note_height > -height_below / (note.speed * speed_mult) && matches!(res.chart_format, ChartFormat::Pgr | ChartFormat::Rpe) {
                             break;
                         }
                         note.render(ui, res, &mut config, bpm_list, line_set_debug_alpha, id);
@@ -478,10 +561,10 @@ impl JudgeLine {
                                 break;
                             }
                             let note_height = note.height - config.line_height + note.object.translation.1.now();
-                            if agg && note_height < -height_above / speed {
+                            if agg(speed) && note_height < -height_above / (speed * speed_mult) {
                                 continue;
                             }
-                            if agg && note_height > -height_below / speed {
+                            if agg(speed) && note_height > -height_below / (speed * speed_mult) {
                                 break;
                             }
                             note.render(ui, res, &mut config, bpm_list, line_set_debug_alpha, id);
@@ -489,6 +572,9 @@ impl JudgeLine {
                     }
                 });
             }
+            if beat_grid {
+                self.render_beat_grid(ui, res, bpm_list, config.line_height, height_above);
+            }
             if res.config.chart_debug_line > 0. {
                 res.with_model(Matrix::identity().append_nonuniform_scaling(&Vector::new(1.0, -1.0)), |res| {
                     res.apply_model(|res| {
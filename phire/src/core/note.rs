@@ -2,7 +2,7 @@ use super::{
     chart::ChartSettings, BpmList, CtrlObject, JudgeLine, Matrix, Object, Point, Resource, Vector
 };
 use crate::{
-    core::HEIGHT_RATIO, ext::parse_alpha, info::ChartFormat, judge::JudgeStatus, parse::RPE_HEIGHT, ui::Ui
+    config::BadNoteStyle, core::HEIGHT_RATIO, ext::parse_alpha, info::ChartFormat, judge::JudgeStatus, parse::RPE_HEIGHT, ui::Ui
 };
 
 
@@ -10,11 +10,19 @@ use macroquad::prelude::*;
 pub use crate::{
     judge::HitSound,
 };
+use crate::judge::play_sfx;
 
 //const HOLD_PARTICLE_INTERVAL: f32 = 0.15;
 const FADEOUT_TIME: f32 = 0.16;
 const BAD_TIME: f32 = 0.5;
 
+const NOTE_TRAIL_COUNT: usize = 3;
+const NOTE_TRAIL_INTERVAL: f32 = 0.02;
+const NOTE_TRAIL_ALPHA: f32 = 0.6;
+/// Batch order of repeated hold bodies, below every other note so that the bodies of all holds are drawn together
+/// and always under the heads and tails, whatever the texture ids are.
+const HOLD_BODY_ORDER: i8 = -1;
+
 #[derive(Clone, Debug)]
 pub enum NoteKind {
     Click,
@@ -84,13 +92,20 @@ fn draw_tex(res: &Resource, texture: Texture2D, order: i8, x: f32, y: f32, color
     params.flip_y = true;
     draw_tex_pts(res, texture, order, p, color, params);
 }
+/// Bounds of screen space points, `None` if they are entirely outside of the visible area.
+fn screen_bounds(p: &[Point; 4], chart_ratio: f32) -> Option<Rect> {
+    let (min_x, max_x) = p.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), it| (lo.min(it.x), hi.max(it.x)));
+    let (min_y, max_y) = p.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), it| (lo.min(it.y), hi.max(it.y)));
+    let bound = 1. / chart_ratio;
+    if min_x > bound || max_x < -bound || min_y > bound || max_y < -bound {
+        return None;
+    }
+    Some(Rect::new(min_x, min_y, max_x - min_x, max_y - min_y))
+}
+
 fn draw_tex_pts(res: &Resource, texture: Texture2D, order: i8, p: [Point; 4], color: Color, params: DrawTextureParams) {
     let mut p = p.map(|it| res.world_to_screen(it));
-    if p[0].x.min(p[1].x.min(p[2].x.min(p[3].x))) > 1. / res.config.chart_ratio
-        || p[0].x.max(p[1].x.max(p[2].x.max(p[3].x))) < -1. / res.config.chart_ratio
-        || p[0].y.min(p[1].y.min(p[2].y.min(p[3].y))) > 1. / res.config.chart_ratio
-        || p[0].y.max(p[1].y.max(p[2].y.max(p[3].y))) < -1. / res.config.chart_ratio
-    {
+    if screen_bounds(&p, res.config.chart_ratio).is_none() {
         return;
     }
     let Rect { x: sx, y: sy, w: sw, h: sh } = params.source.unwrap_or(Rect { x: 0., y: 0., w: 1., h: 1. });
@@ -116,6 +131,13 @@ fn draw_tex_pts(res: &Resource, texture: Texture2D, order: i8, p: [Point; 4], co
         .push((order, texture.raw_miniquad_texture_handle().gl_internal_id()), vertices);
 }
 
+fn tinted(color: Color, tint: Option<Color>) -> Color {
+    match tint {
+        Some(tint) => Color::new(color.r * tint.r, color.g * tint.g, color.b * tint.b, color.a * tint.a),
+        None => color,
+    }
+}
+
 fn draw_center(res: &Resource, tex: Texture2D, order: i8, scale: f32, color: Color) {
     let hf = vec2(scale, tex.height() * scale / tex.width());
     draw_tex(
@@ -133,6 +155,55 @@ fn draw_center(res: &Resource, tex: Texture2D, order: i8, scale: f32, color: Col
     );
 }
 
+/// Vertical placement of a note relative to its line, shared by [`Note::render`] and [`Note::screen_rect`].
+struct Placement {
+    spd: f32,
+    /// Speed at the end of a hold, `spd` for other notes
+    end_spd: f32,
+    /// Notes with negative speed approach from the other side of the line
+    dir: f32,
+    line_height: f32,
+    height: f32,
+    base: f32,
+    /// Height the note is covered by the line below
+    cover_base: f32,
+    aspect_ratio: f32,
+}
+
+impl Placement {
+    /// Whether the note has passed the line, or is hidden beneath it
+    fn below_line(&self, note: &Note, time: f32) -> bool {
+        (time - FADEOUT_TIME >= note.time && !matches!(note.kind, NoteKind::Hold { .. })) || (note.time > time && self.cover_base * self.dir <= -0.001)
+    }
+
+    /// Alpha multiplier of [`crate::config::Config::fade`], `None` if the note is faded out entirely.
+    fn fade_alpha(&self, fade: f32) -> Option<f32> {
+        let dist = self.base * self.dir;
+        if fade > 0. {
+            let over = fade * 0.8;
+            if dist > fade {
+                None
+            } else if dist > over {
+                Some((fade - dist) / (fade - over))
+            } else {
+                Some(1.)
+            }
+        } else if fade < 0. {
+            let fade_out = fade.abs();
+            let over = fade_out * 0.8;
+            if dist < over {
+                None
+            } else if dist < fade_out {
+                Some((dist - over) / (fade_out - over))
+            } else {
+                Some(1.)
+            }
+        } else {
+            Some(1.)
+        }
+    }
+}
+
 impl Note {
     pub fn rotation(&self, line: &JudgeLine) -> f32 {
         line.object.rotation.now() + if self.above { 0. } else { 180. }
@@ -154,6 +225,9 @@ impl Note {
                 );
                 //println!("{} {} {}", index, bpm_list.now_bpm(index as f32), beat);
                 *at = res.time + beat / res.config.speed; //HOLD_PARTICLE_INTERVAL
+                if res.config.hold_tick_sound && !res.config.disable_audio {
+                    play_sfx(&mut res.sfx_drag, &res.config);
+                }
                 Some(if perfect && !res.config.all_good && !res.config.all_bad {
                     res.res_pack.info.fx_perfect()
                 } else {
@@ -185,6 +259,43 @@ impl Note {
         ctrl_obj.set_height((self.height - line_height + self.object.translation.1.now() / self.speed) * RPE_HEIGHT / 2.);
     }
 
+    fn placement(&self, ctrl_obj: &CtrlObject, line_height: f32, aspect_ratio: f32, speed_mult: f32, hold_partial_cover: bool) -> Placement {
+        let ctrl_y = ctrl_obj.y.now_opt().unwrap_or(1.);
+        let spd = self.speed * ctrl_y * speed_mult;
+        let line_height = line_height / aspect_ratio * spd;
+        let height = self.height / aspect_ratio * spd;
+        let (end_spd, cover_height) = match self.kind {
+            NoteKind::Hold { end_height, end_speed, .. } => {
+                let end_spd = end_speed * ctrl_y * speed_mult;
+                (end_spd, if hold_partial_cover { end_height / aspect_ratio * end_spd } else { height })
+            }
+            _ => (spd, height),
+        };
+        Placement {
+            spd,
+            end_spd,
+            dir: spd.signum(),
+            line_height,
+            height,
+            base: height - line_height,
+            cover_base: cover_height + self.object.translation.1.now() - line_height,
+            aspect_ratio,
+        }
+    }
+
+    /// Bottom and top of a hold body in the note's space at `time`, mirrored for negative speeds.
+    fn hold_extent(&self, p: &Placement, end_height: f32, time: f32, pgr: bool) -> (f32, f32) {
+        let end_height = end_height / p.aspect_ratio * p.spd;
+        let bottom = if self.time <= time { p.line_height } else { p.height } - p.line_height;
+        let top = if pgr {
+            let hold_line_height = (time.max(self.time) - self.time) * p.end_spd / p.aspect_ratio / HEIGHT_RATIO;
+            bottom + end_height - p.height - hold_line_height
+        } else {
+            end_height - p.line_height
+        };
+        (bottom * p.dir, top * p.dir)
+    }
+
     pub fn now_transform(&self, res: &Resource, ctrl_obj: &CtrlObject, base: f32, incline_sin: f32) -> Matrix {
         let incline_val = 1. - incline_sin * (base * res.aspect_ratio + self.object.translation.1.now()) * RPE_HEIGHT / 2. / 360.;
         let mut tr = self.object.now_translation(res);
@@ -195,6 +306,64 @@ impl Note {
         self.object.now_rotation().append_nonuniform_scaling(&scale).append_translation(&tr)
     }
 
+    /// Bounding rect in the space of [`Resource::world_to_screen`] of the note placed by `model`, following the same
+    /// visibility rules as [`Note::render`] without drawing anything. `None` if the note wouldn't be drawn.
+    pub fn screen_rect(&self, res: &Resource, model: &Matrix, config: &mut RenderConfig, bpm_list: Option<&mut BpmList>) -> Option<Rect> {
+        if matches!(self.judge, JudgeStatus::Judged) && !matches!(self.kind, NoteKind::Hold { .. }) {
+            return None;
+        }
+        if let (true, Some(bpm_list)) = (config.appear_before.is_finite(), bpm_list) {
+            let beat = bpm_list.beat(self.time);
+            if bpm_list.time_beats(beat - config.appear_before) > res.time {
+                return None;
+            }
+        }
+        if config.invisible_time.is_finite() && self.time - config.invisible_time < res.time {
+            return None;
+        }
+
+        let ctrl_obj = &mut config.ctrl_obj;
+        self.init_ctrl_obj(ctrl_obj, config.line_height);
+        let placement = self.placement(ctrl_obj, config.line_height, res.aspect_ratio, res.config.note_speed_mult, config.settings.hold_partial_cover);
+        if !config.draw_below && placement.below_line(self, res.time) {
+            return None;
+        }
+        placement.fade_alpha(res.config.fade)?;
+
+        let order = self.kind.order();
+        let style = if res.config.double_hint && self.multiple_hint {
+            &res.res_pack.note_style_mh
+        } else {
+            &res.res_pack.note_style
+        };
+        let scale = style.click.width() / res.res_pack.note_style.click.width() * res.note_width * res.config.note_kind_scale(order);
+        let (tr, bottom, top) = match self.kind {
+            NoteKind::Hold { end_time, end_height, .. } => {
+                if res.time >= end_time {
+                    return None;
+                }
+                let (bottom, top) = self.hold_extent(&placement, end_height, res.time, matches!(res.chart_format, ChartFormat::Pgr));
+                let tr = self.now_transform(res, ctrl_obj, 0., 0.).prepend_nonuniform_scaling(&Vector::new(1., placement.dir));
+                (tr, bottom, top)
+            }
+            _ => {
+                if self.fake && res.time >= self.time {
+                    return None;
+                }
+                let tex = match self.kind {
+                    NoteKind::Click => &style.click,
+                    NoteKind::Drag => &style.drag,
+                    _ => &style.flick,
+                };
+                let h = tex.height() * scale / tex.width();
+                (self.now_transform(res, ctrl_obj, placement.base, config.incline_sin), -h, h)
+            }
+        };
+        let mat = model * tr;
+        let p = [(-scale, bottom), (scale, bottom), (scale, top), (-scale, top)].map(|(x, y)| res.world_to_screen(mat.transform_point(&Point::new(x, y))));
+        screen_bounds(&p, res.config.chart_ratio)
+    }
+
     pub fn render(&self, ui: &mut Ui, res: &mut Resource, config: &mut RenderConfig, bpm_list: &mut BpmList, line_set_debug_alpha: bool, line_id: usize) {
         if matches!(self.judge, JudgeStatus::Judged) && !matches!(self.kind, NoteKind::Hold { .. }) {
             return;
@@ -222,10 +391,8 @@ impl Note {
             }
         }
 
-        let spd = self.speed * ctrl_obj.y.now_opt().unwrap_or(1.);
-        let line_height = config.line_height / res.aspect_ratio * spd;
-        let height = self.height / res.aspect_ratio * spd;
-        let base = height - line_height;
+        let placement = self.placement(ctrl_obj, config.line_height, res.aspect_ratio, res.config.note_speed_mult, config.settings.hold_partial_cover);
+        let Placement { dir, line_height, height, base, .. } = placement;
 
         if res.config.aggressive && matches!(res.chart_format, ChartFormat::Pec) && matches!(self.kind, NoteKind::Hold { .. }) {
             let h = if self.time <= res.time { line_height } else { height };
@@ -235,21 +402,6 @@ impl Note {
             }
         }
 
-        let cover_base = if !config.settings.hold_partial_cover {
-            height + self.object.translation.1.now() - line_height
-        } else {
-            match self.kind {
-                NoteKind::Hold { end_time: _,  end_height, end_speed } => {
-                    let end_spd = end_speed * ctrl_obj.y.now_opt().unwrap_or(1.);
-                    let end_height = end_height / res.aspect_ratio * end_spd;
-                    end_height + self.object.translation.1.now() - line_height
-                }
-                _ => {
-                    height + self.object.translation.1.now() - line_height
-                }
-            }
-        };
-
         if res.config.alpha_tint {
             if color.a <= 0.5 {
                 color.r *= 0.6;
@@ -266,10 +418,7 @@ impl Note {
         }
 
         // && ((res.time - FADEOUT_TIME >= self.time) || (self.fake && res.time >= self.time) || (self.time > res.time && base <= -1e-5))
-        if !config.draw_below
-            && ((res.time - FADEOUT_TIME >= self.time && !matches!(self.kind, NoteKind::Hold { .. })) || (self.time > res.time && cover_base <= -0.001))
-            // && self.speed != 0.
-        {
+        if !config.draw_below && placement.below_line(self, res.time) {
             if res.config.chart_debug_note > 0. {
                 color.a *= 0.2;
             } else {
@@ -279,30 +428,19 @@ impl Note {
         if line_set_debug_alpha {
             color.a *= 0.4;
         }
-        if res.config.fade > 0. {
-            let over = res.config.fade * 0.8;
-            if base > res.config.fade {
-                return;
-            } else if base > over {
-                color.a *= (res.config.fade - base) / (res.config.fade - over);
-            }
-        } else if res.config.fade < 0. {
-            let fade_out = res.config.fade.abs();
-            let over = fade_out * 0.8;
-            if base < over {
-                return;
-            } else if base < fade_out {
-                //color.a *= (fade_out - base) / (fade_out - over);
-                color.a *= (base - over) / (fade_out - over);
-            }
-        }
+        let Some(fade) = placement.fade_alpha(res.config.fade) else {
+            return;
+        };
+        color.a *= fade;
 
+        let order = self.kind.order();
         let scale = (if res.config.double_hint && self.multiple_hint {
             res.res_pack.note_style_mh.click.width() / res.res_pack.note_style.click.width()
         } else {
             1.0
-        }) * res.note_width;
-        let order = self.kind.order();
+        }) * res.note_width
+            * res.config.note_kind_scale(order);
+        let mut color = tinted(color, res.config.note_tint(order));
         let style = if res.config.double_hint && self.multiple_hint {
             &res.res_pack.note_style_mh
         } else {
@@ -313,7 +451,22 @@ impl Note {
             if !config.draw_below {
                 color.a *= (self.time - res.time).min(0.) / FADEOUT_TIME + 1.;
             }
-            res.with_model(self.now_transform(res, ctrl_obj, base, config.incline_sin), |res| {
+            let tr = self.now_transform(res, ctrl_obj, base, config.incline_sin);
+            if res.config.note_trail && self.object.translation.1.keyframes.len() > 1 {
+                let now = self.object.translation.now();
+                for i in (1..=NOTE_TRAIL_COUNT).rev() {
+                    let time = res.time - i as f32 * NOTE_TRAIL_INTERVAL;
+                    let translation = &self.object.translation;
+                    let mut delta = Vector::new(translation.0.at(time), translation.1.at(time)) - now;
+                    delta.y /= res.aspect_ratio;
+                    let mut color = color;
+                    color.a *= NOTE_TRAIL_ALPHA * (1. - i as f32 / (NOTE_TRAIL_COUNT + 1) as f32);
+                    res.with_model(tr.append_translation(&delta), |res| {
+                        draw_center(res, tex, order, scale, color);
+                    });
+                }
+            }
+            res.with_model(tr, |res| {
                 draw_center(res, tex, order, scale, color);
             });
         };
@@ -322,9 +475,10 @@ impl Note {
                 if self.fake && res.time >= self.time { return };
                 draw(res, *style.click);
             }
-            NoteKind::Hold { end_time, end_height, end_speed } => {
+            NoteKind::Hold { end_time, end_height, .. } => {
                 if self.fake && res.time >= end_time { return };
-                res.with_model(self.now_transform(res, ctrl_obj, 0., 0.), |res| {
+                // reversed holds are drawn mirrored so that the head still faces the line
+                res.with_model(self.now_transform(res, ctrl_obj, 0., 0.).prepend_nonuniform_scaling(&Vector::new(1., dir)), |res| {
                     if matches!(self.judge, JudgeStatus::Judged) {
                         // miss
                         color.a *= 0.5;
@@ -332,8 +486,7 @@ impl Note {
                     if res.time >= end_time {
                         return;
                     }
-                    let end_spd = end_speed * ctrl_obj.y.now_opt().unwrap_or(1.);
-                    if matches!(res.chart_format, ChartFormat::Pgr) && end_spd == 0. {
+                    if matches!(res.chart_format, ChartFormat::Pgr) && placement.end_spd == 0. {
                         if res.config.chart_debug_note > 0. {
                             color.a *= 0.2;
                         } else {
@@ -341,21 +494,10 @@ impl Note {
                         }
                     }
 
-                    let end_height = end_height / res.aspect_ratio * spd;
-                    let time = if res.time >= self.time {res.time} else {self.time};
-
                     //let clip = !config.draw_below && config.settings.hold_partial_cover;
                     let clip = false;
 
-                    let h = if self.time <= res.time { line_height } else { height };
-                    let bottom = h - line_height; //StartY
-                    let top = if matches!(res.chart_format, ChartFormat::Pgr) {
-                        let hold_height = end_height - height;
-                        let hold_line_height = (time - self.time) * end_spd / res.aspect_ratio / HEIGHT_RATIO;
-                        bottom + hold_height - hold_line_height
-                    } else {
-                        end_height - line_height
-                    };
+                    let (bottom, top) = self.hold_extent(&placement, end_height, res.time, matches!(res.chart_format, ChartFormat::Pgr));
 
                     //let max_hold_height = 3. / res.config.chart_ratio / res.aspect_ratio;
                     //let top = if res.config.aggressive && hold_height - hold_line_height >= max_hold_height { bottom + max_hold_height } else { top };
@@ -376,6 +518,8 @@ impl Note {
                     let ratio = style.hold_ratio();
                     // body
                     // TODO (end_height - height) is not always total height
+                    // a repeated body is still a single quad, its source rect spans several tiles of the wrapping texture;
+                    // otherwise all three pieces share the hold texture and go into the same batch
                     draw_tex(
                         res,
                         **(if res.res_pack.info.hold_repeat {
@@ -383,7 +527,7 @@ impl Note {
                         } else {
                             tex
                         }),
-                        order,
+                        if res.res_pack.info.hold_repeat { HOLD_BODY_ORDER } else { order },
                         -scale,
                         bottom,
                         color,
@@ -528,6 +672,8 @@ impl BadNote {
         if res.time > self.time + BAD_TIME {
             return false;
         }
+        let order = self.kind.order();
+        let tint = if res.config.tint_bad_notes { res.config.note_tint(order) } else { None };
         res.with_model(self.matrix, |res| {
             let style = &res.res_pack.note_style;
             draw_center(
@@ -538,11 +684,87 @@ impl BadNote {
                     NoteKind::Flick => *style.flick,
                     _ => unreachable!(),
                 },
-                self.kind.order(),
-                res.note_width,
-                Color::new(0.423529, 0.262745, 0.262745, (self.time - res.time).max(-1.) / BAD_TIME + 1.),
+                order,
+                res.note_width * res.config.note_kind_scale(order),
+                {
+                    let alpha = (self.time - res.time).max(-1.) / BAD_TIME + 1.;
+                    match res.config.bad_note_style {
+                        BadNoteStyle::FadeOnly => tinted(Color::new(1., 1., 1., alpha), tint),
+                        _ => tinted(Color::new(0.423529, 0.262745, 0.262745, alpha), tint),
+                    }
+                },
             );
         });
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(kind: NoteKind, time: f32, height: f32, speed: f32) -> Note {
+        Note {
+            object: Object::default(),
+            kind,
+            hitsound: HitSound::None,
+            time,
+            height,
+            speed,
+            above: true,
+            multiple_hint: false,
+            fake: false,
+            judge: JudgeStatus::NotJudged,
+            attr: false,
+        }
+    }
+
+    fn placement(note: &Note, line_height: f32) -> Placement {
+        note.placement(&CtrlObject::default(), line_height, 1., 1., false)
+    }
+
+    // a line scrolling from height 0 to 4 over 4 seconds, with a click at 2s and a hold from 2s to 3s
+    #[test]
+    fn reverse_scroll_approaches_from_below() {
+        for speed in [1., -1.] {
+            let click = note(NoteKind::Click, 2., 2., speed);
+            let p = placement(&click, 1.);
+            assert_eq!(p.dir, speed);
+            assert_eq!(p.base * p.dir, 1., "distance to the line doesn't depend on the direction");
+            assert!(!p.below_line(&click, 1.));
+            assert_eq!(p.fade_alpha(0.), Some(1.));
+            assert_eq!(p.fade_alpha(0.5), None);
+            assert_eq!(p.fade_alpha(-0.5), Some(1.));
+
+            // the line has scrolled past a note that is still due, so it is covered
+            let p = placement(&click, 2.5);
+            assert!(p.below_line(&click, 1.9));
+
+            let p = placement(&click, 2.);
+            assert!(!p.below_line(&click, 2.));
+            assert!(p.below_line(&click, 2. + FADEOUT_TIME * 2.));
+        }
+    }
+
+    #[test]
+    fn reverse_scroll_hold_extent() {
+        let kind = NoteKind::Hold {
+            end_time: 3.,
+            end_height: 3.,
+            end_speed: 1.,
+        };
+        let forward = note(kind.clone(), 2., 2., 1.);
+        let reverse = note(kind, 2., 2., -1.);
+        for pgr in [false, true] {
+            let (bottom, top) = forward.hold_extent(&placement(&forward, 1.), 3., 1., pgr);
+            assert_eq!((bottom, top), (1., 2.));
+            // mirrored by the model, so the extent is the same as scrolling forward
+            let (bottom, top) = reverse.hold_extent(&placement(&reverse, 1.), 3., 1., pgr);
+            assert_eq!((bottom, top), (1., 2.));
+        }
+        // while being held the body starts at the line
+        let (bottom, top) = reverse.hold_extent(&placement(&reverse, 2.5), 3., 2.5, false);
+        assert_eq!((bottom, top), (0., 0.5));
+        assert!(!placement(&reverse, 2.5).below_line(&reverse, 2.5));
+    }
+}
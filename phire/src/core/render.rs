@@ -23,6 +23,40 @@ pub fn copy_fbo(src: GLuint, dst: GLuint, dim: (u32, u32)) -> bool {
     }
 }
 
+// not exposed by miniquad's GL bindings
+const GL_MAX_SAMPLES: u32 = 0x8D57;
+
+/// Whether the context is OpenGL ES 2 (or WebGL 1), where `GL_MAX_SAMPLES` and multisampled renderbuffers don't exist.
+fn is_gles2() -> bool {
+    unsafe {
+        use miniquad::gl::*;
+        let version = glGetString(GL_VERSION);
+        if version.is_null() {
+            return true;
+        }
+        let version = std::ffi::CStr::from_ptr(version as *const _).to_string_lossy();
+        version.starts_with("OpenGL ES 2") || version.starts_with("WebGL 1")
+    }
+}
+
+/// Largest power-of-two MSAA sample count not above `requested` that the GL backend supports, 1 on OpenGL ES 2.
+pub fn supported_sample_count(requested: u32) -> u32 {
+    if is_gles2() {
+        return 1;
+    }
+    let max = unsafe {
+        use miniquad::gl::*;
+        let mut max = 0;
+        glGetIntegerv(GL_MAX_SAMPLES, &mut max);
+        (max as u32).max(1)
+    };
+    let mut count = 1;
+    while count * 2 <= requested.min(max) {
+        count *= 2;
+    }
+    count
+}
+
 pub fn internal_id(target: RenderTarget) -> GLuint {
     target.render_pass.gl_internal_id(unsafe { get_internal_gl() }.quad_context)
 }
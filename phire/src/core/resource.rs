@@ -1,10 +1,11 @@
-use super::{MSRenderTarget, Matrix, Point, NOTE_WIDTH_RATIO_BASE};
+use super::{supported_sample_count, MSRenderTarget, Matrix, Point, NOTE_WIDTH_RATIO_BASE};
 use crate::{
-    config::Config,
+    config::{Config, ParticleQuality},
     ext::{create_audio_manger, nalgebra_to_glm, SafeTexture},
     fs::FileSystem,
     info::{ChartFormat, ChartInfo},
     particle::{AtlasConfig, ColorCurve, Emitter, EmitterConfig},
+    ui::{FontArc, TextPainter},
 };
 use anyhow::{bail, Context, Result};
 use macroquad::prelude::*;
@@ -12,8 +13,9 @@ use miniquad::{gl::{GLuint, GL_LINEAR}, Texture, TextureWrap};
 use sasa::{AudioClip, AudioManager, Sfx};
 use serde::Deserialize;
 use std::{cell::RefCell, collections::{BTreeMap, HashMap, VecDeque}, ops::DerefMut, path::Path, sync::atomic::AtomicU32};
+use tracing::warn;
 
-pub const MAX_SIZE: usize = 64; // needs tweaking
+pub const MAX_SIZE: usize = 256; // quads per draw call
 pub static DPI_VALUE: AtomicU32 = AtomicU32::new(250);
 pub const BUFFER_SIZE: usize = 1024;
 
@@ -171,6 +173,22 @@ impl NoteStyle {
         let ey = self.to_uv(self.hold_atlas.0);
         Rect::new(0., 0., 1., ey)
     }
+
+    /// Cuts the body out of the hold texture as a repeating texture, used with `hold_repeat`.
+    fn build_hold_body(&mut self) {
+        let pixels = self.hold.get_texture_data();
+        let width = self.hold.width() as u16;
+        let height = self.hold.height() as u16;
+        let atlas = self.hold_atlas;
+        let res = Texture2D::from_rgba8(
+            width,
+            height - atlas.0 as u16 - atlas.1 as u16,
+            &pixels.bytes[(atlas.0 as usize * width as usize * 4)..(pixels.bytes.len() - atlas.1 as usize * width as usize * 4)],
+        );
+        let context = unsafe { get_internal_gl() }.quad_context;
+        res.raw_miniquad_texture_handle().set_wrap(context, TextureWrap::Repeat);
+        self.hold_body = Some(res.into());
+    }
 }
 
 pub struct ResourcePack {
@@ -180,6 +198,8 @@ pub struct ResourcePack {
     pub sfx_click: AudioClip,
     pub sfx_drag: AudioClip,
     pub sfx_flick: AudioClip,
+    /// Optional `{kind}_{perfect|good}` sounds, indexed by note kind order and judgement
+    pub sfx_judge: [[Option<AudioClip>; 2]; 4],
     pub ending: AudioClip,
     pub hit_fx: SafeTexture,
 }
@@ -197,6 +217,78 @@ impl ResourcePack {
         .await
     }
 
+    /// Downloads a resource pack archive (cached by URL hash and revalidated on every call, by the browser on the web) and
+    /// loads it. Falls back to the bundled pack on network errors.
+    pub async fn from_url(url: &str, accept_invalid_cert: bool) -> Result<Self> {
+        match Self::fetch_archive(url, accept_invalid_cert).await {
+            Ok(mut fs) => Self::load(fs.deref_mut()).await,
+            Err(err) => {
+                warn!("failed to download resource pack from {url}, falling back to default: {err:?}");
+                Self::from_path(None::<&str>).await
+            }
+        }
+    }
+
+    /// Downloads the archive into the cache, revalidating a cached copy with the `ETag`/`Last-Modified` it came with.
+    /// The cached copy is kept if the server says it's unchanged or can't be reached.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn fetch_archive(url: &str, accept_invalid_cert: bool) -> Result<Box<dyn FileSystem + Send + Sync>> {
+        use reqwest::{header, StatusCode};
+        use sha2::{Digest, Sha256};
+
+        let dir = std::env::temp_dir().join("phire-respack");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.zip", hex::encode(Sha256::digest(url.as_bytes()))));
+        // validators of the cached copy, the `ETag` and `Last-Modified` headers on one line each
+        let meta_path = path.with_extension("meta");
+        let cached = path.exists();
+        let validators = if cached { std::fs::read_to_string(&meta_path).unwrap_or_default() } else { String::new() };
+        let mut validators = validators.lines();
+        let (etag, last_modified) = (validators.next().unwrap_or_default(), validators.next().unwrap_or_default());
+
+        let client = reqwest::ClientBuilder::new().danger_accept_invalid_certs(accept_invalid_cert).build()?;
+        let mut request = client.get(url);
+        if !etag.is_empty() {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if !last_modified.is_empty() {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let resp = match request.send().await.and_then(|resp| resp.error_for_status()) {
+            Ok(resp) => resp,
+            Err(err) if cached => {
+                warn!("failed to revalidate resource pack from {url}, using the cached copy: {err:?}");
+                return crate::fs::fs_from_file(&path);
+            }
+            Err(err) => return Err(err).with_context(|| format!("failed to fetch resource pack from {url}")),
+        };
+        if cached && resp.status() == StatusCode::NOT_MODIFIED {
+            return crate::fs::fs_from_file(&path);
+        }
+        let value = |name: header::HeaderName| resp.headers().get(name).and_then(|it| it.to_str().ok()).unwrap_or_default().to_owned();
+        let meta = format!("{}\n{}\n", value(header::ETAG), value(header::LAST_MODIFIED));
+        let bytes = resp.bytes().await.context("failed to receive resource pack")?;
+        let tmp = path.with_extension("part");
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(&tmp, &path)?;
+        std::fs::write(&meta_path, meta)?;
+        crate::fs::fs_from_file(&path)
+    }
+
+    /// Downloads the archive into memory. The browser caches and revalidates the request, and decides on certificates
+    /// itself.
+    #[cfg(target_arch = "wasm32")]
+    async fn fetch_archive(url: &str, _accept_invalid_cert: bool) -> Result<Box<dyn FileSystem + Send + Sync>> {
+        let bytes = reqwest::get(url)
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .with_context(|| format!("failed to fetch resource pack from {url}"))?
+            .bytes()
+            .await
+            .context("failed to receive resource pack")?;
+        Ok(Box::new(crate::fs::ZipFileSystem::new(bytes.to_vec())?))
+    }
+
     pub async fn load(fs: &mut dyn FileSystem) -> Result<Self> {
         macro_rules! load_tex {
             ($path:literal) => {
@@ -223,22 +315,8 @@ impl ResourcePack {
         };
         note_style_mh.verify()?;
         if info.hold_repeat {
-            fn get_body(style: &mut NoteStyle) {
-                let pixels = style.hold.get_texture_data();
-                let width = style.hold.width() as u16;
-                let height = style.hold.height() as u16;
-                let atlas = style.hold_atlas;
-                let res = Texture2D::from_rgba8(
-                    width,
-                    height - atlas.0 as u16 - atlas.1 as u16,
-                    &pixels.bytes[(atlas.0 as usize * width as usize * 4)..(pixels.bytes.len() - atlas.1 as usize * width as usize * 4)],
-                );
-                let context = unsafe { get_internal_gl() }.quad_context;
-                res.raw_miniquad_texture_handle().set_wrap(context, TextureWrap::Repeat);
-                style.hold_body = Some(res.into());
-            }
-            get_body(&mut note_style);
-            get_body(&mut note_style_mh);
+            note_style.build_hold_body();
+            note_style_mh.build_hold_body();
         }
         let hit_fx = image::load_from_memory(&fs.load_file("hit_fx.png").await.context("Missing hit_fx.png")?)?.into();
 
@@ -255,6 +333,21 @@ impl ResourcePack {
                 }
             };
         }
+        let mut sfx_judge: [[Option<AudioClip>; 2]; 4] = Default::default();
+        for (kind, clips) in ["hold", "drag", "click", "flick"].into_iter().zip(sfx_judge.iter_mut()) {
+            for (judgement, clip) in ["perfect", "good"].into_iter().zip(clips.iter_mut()) {
+                for ext in ["ogg", "wav", "mp3"] {
+                    if let Ok(bytes) = fs.load_file(&format!("{kind}_{judgement}.{ext}")).await {
+                        // these are optional, a broken one just falls back to the plain sound
+                        match AudioClip::new(bytes) {
+                            Ok(it) => *clip = Some(it),
+                            Err(err) => warn!("failed to decode {kind}_{judgement}.{ext}, using the default sound: {err:?}"),
+                        }
+                        break;
+                    }
+                }
+            }
+        }
         Ok(Self {
             info,
             note_style,
@@ -275,6 +368,7 @@ pub struct ParticleEmitter {
     emitter_square: Emitter,
     emitter_square_config: EmitterConfig,
     hide_particles: bool,
+    square_count: usize,
 }
 
 impl ParticleEmitter {
@@ -289,8 +383,10 @@ impl ParticleEmitter {
         };
         let config_default = Config::default();
         let config = config.unwrap_or(config_default);
+        // keep at least one slot, the GPU buffers can't be empty
+        let max_particles = config.particle_quality.max_particles(config.max_particles).max(4);
         let emitter_config = EmitterConfig {
-            max_particles: config.max_particles / 4,
+            max_particles: max_particles / 4,
             local_coords: false,
             texture: Some(*res_pack.hit_fx),
             lifetime: res_pack.info.hit_fx_duration,
@@ -304,7 +400,7 @@ impl ParticleEmitter {
             ..Default::default()
         };
         let emitter_square_config = EmitterConfig {
-            max_particles: config.max_particles,
+            max_particles,
             local_coords: false,
             lifetime: res_pack.info.hit_fx_duration,
             lifetime_randomness: 0.0,
@@ -324,6 +420,7 @@ impl ParticleEmitter {
             emitter_square: Emitter::new(emitter_square_config.clone()),
             emitter_square_config,
             hide_particles,
+            square_count: config.particle_quality.divisor().map_or(0, |it| 4 / it),
         };
         res.set_scale(scale);
         Ok(res)
@@ -333,9 +430,9 @@ impl ParticleEmitter {
         self.emitter.config.initial_rotation = rotation;
         self.emitter.config.base_color = color;
         self.emitter.emit(&self.emitter_config, pt, 1);
-        if !self.hide_particles {
+        if !self.hide_particles && self.square_count > 0 {
             self.emitter_square.config.base_color = color;
-            self.emitter_square.emit(&self.emitter_square_config, pt, 4);
+            self.emitter_square.emit(&self.emitter_square_config, pt, self.square_count);
         }
     }
 
@@ -350,17 +447,24 @@ impl ParticleEmitter {
     }
 }
 
+/// Note quads grouped by `(order, texture id)`, with one mesh per draw call.
+///
+/// Meshes are kept between frames (only their contents are cleared) so that
+/// charts with lots of notes don't reallocate every frame.
 #[derive(Default)]
-pub struct NoteBuffer(BTreeMap<(i8, GLuint), Vec<(Vec<Vertex>, Vec<u16>)>>);
+pub struct NoteBuffer(BTreeMap<(i8, GLuint), (usize, Vec<(Vec<Vertex>, Vec<u16>)>)>, (usize, usize));
 pub type SfxMap = HashMap<String, Sfx>;
 
 impl NoteBuffer {
     pub fn push(&mut self, key: (i8, GLuint), vertices: [Vertex; 4]) {
-        let meshes = self.0.entry(key).or_default();
-        if meshes.last().map_or(true, |it| it.0.len() + 4 > MAX_SIZE * 4) {
-            meshes.push(Default::default());
+        let (used, meshes) = self.0.entry(key).or_default();
+        if *used == 0 || meshes[*used - 1].0.len() + 4 > MAX_SIZE * 4 {
+            if *used == meshes.len() {
+                meshes.push(Default::default());
+            }
+            *used += 1;
         }
-        let last = meshes.last_mut().unwrap();
+        let last = &mut meshes[*used - 1];
         let i = last.0.len() as u16;
         last.0.extend_from_slice(&vertices);
         last.1.extend_from_slice(&[i, i + 1, i + 2, i, i + 2, i + 3]);
@@ -371,13 +475,26 @@ impl NoteBuffer {
         gl.flush();
         let gl = gl.quad_gl;
         gl.draw_mode(DrawMode::Triangles);
-        for ((_, tex_id), meshes) in std::mem::take(&mut self.0).into_iter() {
-            gl.texture(Some(Texture2D::from_miniquad_texture(unsafe { Texture::from_raw_id(tex_id, miniquad::TextureFormat::RGBA8) })));
-            for mesh in meshes {
+        for ((_, tex_id), (used, meshes)) in self.0.iter_mut() {
+            if *used == 0 {
+                continue;
+            }
+            gl.texture(Some(Texture2D::from_miniquad_texture(unsafe { Texture::from_raw_id(*tex_id, miniquad::TextureFormat::RGBA8) })));
+            for mesh in &mut meshes[..*used] {
                 gl.geometry(&mesh.0, &mesh.1);
+                self.1 .0 += 1;
+                self.1 .1 += mesh.0.len();
+                mesh.0.clear();
+                mesh.1.clear();
             }
+            *used = 0;
         }
     }
+
+    /// Returns the number of draw calls and vertices submitted since the last call.
+    pub fn take_stats(&mut self) -> (usize, usize) {
+        std::mem::take(&mut self.1)
+    }
 }
 
 pub struct Resource {
@@ -393,6 +510,10 @@ pub struct Resource {
 
     pub alpha: f32,
     pub judge_line_color: Color,
+    /// Smoothed loudness of the music at the current position in `[0, 1]`, only updated with `config.reactive_lines`
+    pub audio_level: f32,
+    /// Replaces the plain judge line, tinted by `judge_line_color`
+    pub judge_line_texture: Option<SafeTexture>,
 
     pub camera: Camera2D,
 
@@ -406,8 +527,11 @@ pub struct Resource {
     pub icon_retry: SafeTexture,
     pub icon_resume: SafeTexture,
     pub icon_proceed: SafeTexture,
+    /// Font from the chart's `font.ttf`, used for the in-game HUD instead of the default one
+    pub chart_font: Option<RefCell<TextPainter>>,
 
-    pub emitter: ParticleEmitter,
+    /// `None` if particles are off in the quality settings
+    pub emitter: Option<ParticleEmitter>,
 
     pub audio: AudioManager,
     pub music: AudioClip,
@@ -415,11 +539,15 @@ pub struct Resource {
     pub sfx_click: Sfx,
     pub sfx_drag: Sfx,
     pub sfx_flick: Sfx,
+    pub sfx_judge: [[Option<Sfx>; 2]; 4],
     pub extra_sfxs: SfxMap,
+    pub sfx_combo_break: Option<Sfx>,
     pub frame_times: VecDeque<f64>, // frame interval time
 
     pub chart_target: Option<MSRenderTarget>,
     pub no_effect: bool,
+    /// Line kept fully opaque while the others are dimmed (View mode)
+    pub focus_line: Option<usize>,
 
     pub note_buffer: RefCell<NoteBuffer>,
 
@@ -470,7 +598,7 @@ impl Resource {
     }
 
     pub async fn new(
-        config: Config,
+        mut config: Config,
         chart_format: ChartFormat,
         info: ChartInfo,
         mut fs: Box<dyn FileSystem>,
@@ -484,9 +612,35 @@ impl Resource {
                 SafeTexture::from(Texture2D::from_image(&load_image($path).await?))
             };
         }
-        let res_pack = ResourcePack::from_path(config.res_pack_path.as_ref())
+        let sample_count = supported_sample_count(config.sample_count);
+        if sample_count != config.sample_count {
+            warn!("MSAA {}x is not supported, falling back to {sample_count}x", config.sample_count);
+            config.sample_count = sample_count;
+        }
+        let mut res_pack = ResourcePack::from_path(config.res_pack_path.as_ref())
             .await
             .context("Failed to load resource pack")?;
+        if let Some(color) = config.color_perfect {
+            res_pack.info.color_perfect = color;
+            res_pack.info.color_perfect_line = color;
+        }
+        if let Some(color) = config.color_good {
+            res_pack.info.color_good = color;
+            res_pack.info.color_good_line = color;
+        }
+        if let Some(keep_head) = config.hold_keep_head {
+            res_pack.info.hold_keep_head = keep_head;
+        }
+        if let Some(compact) = config.hold_compact {
+            res_pack.info.hold_compact = compact;
+        }
+        if let Some(repeat) = config.hold_repeat {
+            if repeat && res_pack.note_style.hold_body.is_none() {
+                res_pack.note_style.build_hold_body();
+                res_pack.note_style_mh.build_hold_body();
+            }
+            res_pack.info.hold_repeat = repeat;
+        }
         let vec2_ratio = vec2(1.,-config.aspect_ratio.unwrap_or(info.aspect_ratio));
         let camera = Camera2D {
             target: vec2(0., 0.),
@@ -501,6 +655,33 @@ impl Resource {
         let sfx_click = audio.create_sfx(res_pack.sfx_click.clone(), buffer_size)?;
         let sfx_drag = audio.create_sfx(res_pack.sfx_drag.clone(), buffer_size)?;
         let sfx_flick = audio.create_sfx(res_pack.sfx_flick.clone(), buffer_size)?;
+        let mut sfx_judge: [[Option<Sfx>; 2]; 4] = Default::default();
+        for (clips, sfxs) in res_pack.sfx_judge.iter().zip(sfx_judge.iter_mut()) {
+            for (clip, sfx) in clips.iter().zip(sfxs.iter_mut()) {
+                if let Some(clip) = clip {
+                    *sfx = Some(audio.create_sfx(clip.clone(), buffer_size)?);
+                }
+            }
+        }
+        let sfx_combo_break = config.combo_break_sound.as_ref().and_then(|path| {
+            match std::fs::read(path).map_err(anyhow::Error::from).and_then(|it| Ok(audio.create_sfx(AudioClip::new(it)?, buffer_size)?)) {
+                Ok(sfx) => Some(sfx),
+                Err(err) => {
+                    warn!("failed to load combo break sound {path}: {err:?}");
+                    None
+                }
+            }
+        });
+        let chart_font = match fs.load_file("font.ttf").await {
+            Ok(bytes) => match FontArc::try_from_vec(bytes) {
+                Ok(font) => Some(RefCell::new(TextPainter::new(font))),
+                Err(err) => {
+                    warn!("invalid chart font, using the default one: {err:?}");
+                    None
+                }
+            },
+            Err(_) => None,
+        };
         let frame_times: VecDeque<f64> = VecDeque::new();
 
         let aspect_ratio = config.aspect_ratio.unwrap_or(info.aspect_ratio);
@@ -509,7 +690,19 @@ impl Resource {
 
         let no_effect = config.disable_effect || has_no_effect;
 
-        let emitter = ParticleEmitter::new(&res_pack, note_scale, res_pack.info.hide_particles, Some(config.clone()))?;
+        let emitter = if config.particle_quality == ParticleQuality::Off {
+            None
+        } else {
+            Some(ParticleEmitter::new(&res_pack, note_scale, res_pack.info.hide_particles, Some(config.clone()))?)
+        };
+
+        let judge_line_texture = config.judge_line_texture.as_ref().and_then(|path| match image::open(path) {
+            Ok(image) => Some(SafeTexture::from(image).with_filter(GL_LINEAR)),
+            Err(err) => {
+                warn!("failed to load judge line texture {path}: {err:?}");
+                None
+            }
+        });
 
         macroquad::window::gl_set_drawcall_buffer_capacity(MAX_SIZE * 4, MAX_SIZE * 6);
         Ok(Self {
@@ -524,7 +717,9 @@ impl Resource {
             time: 0.,
 
             alpha: 1.,
-            judge_line_color: res_pack.info.fx_perfect_line(),
+            judge_line_color: if config.judge_line_feedback { res_pack.info.fx_perfect_line() } else { WHITE },
+            audio_level: 0.,
+            judge_line_texture,
 
             camera,
 
@@ -538,6 +733,7 @@ impl Resource {
             icon_retry: load_tex!("retry.png"),
             icon_resume: load_tex!("resume.png"),
             icon_proceed: load_tex!("proceed.png"),
+            chart_font,
 
             emitter,
 
@@ -547,11 +743,14 @@ impl Resource {
             sfx_click,
             sfx_drag,
             sfx_flick,
+            sfx_judge,
             extra_sfxs: SfxMap::new(),
+            sfx_combo_break,
             frame_times,
 
             chart_target: None,
             no_effect,
+            focus_line: None,
 
             note_buffer: RefCell::new(NoteBuffer::default()),
 
@@ -560,25 +759,36 @@ impl Resource {
     }
 
     pub fn emit_at_origin(&mut self, rotation: f32, color: Color) {
-        if !self.config.particle {
+        if !self.config.particle_enabled() {
             return;
         }
         let pt = self.world_to_screen(Point::default());
-        self.emitter.emit_at(
-            vec2(if self.config.flip_x() { -pt.x } else { pt.x }, -pt.y),
+        let Some(emitter) = &mut self.emitter else {
+            return;
+        };
+        emitter.emit_at(
+            vec2(if self.config.render_flip_x() { -pt.x } else { pt.x }, -pt.y),
             if self.res_pack.info.hit_fx_rotate { rotation.to_radians() } else { 0. },
             color,
         );
     }
 
+    /// Darkness of the overlay above the background, from the config if set or else from the chart.
+    pub fn background_dim(&self) -> f32 {
+        self.config.background_dim.unwrap_or(self.info.background_dim)
+    }
+
     pub fn update_size(&mut self, vp: (i32, i32, i32, i32)) -> bool {
         if self.last_vp == vp {
             return false;
         }
         self.last_vp = vp;
-        if !self.no_effect || self.config.sample_count != 1 {
-            self.chart_target = Some(MSRenderTarget::new((vp.2 as u32, vp.3 as u32), self.config.sample_count));
-        }
+        // the color filter is applied to the chart target even without any effect
+        self.chart_target = if !self.no_effect || self.config.sample_count != 1 || self.config.color_filter.preset().is_some() {
+            Some(MSRenderTarget::new((vp.2 as u32, vp.3 as u32), self.config.sample_count))
+        } else {
+            None
+        };
         fn viewport(aspect_ratio: f32, (x, y, w, h): (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
             let w = w as f32;
             let h = h as f32;
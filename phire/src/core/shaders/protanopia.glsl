@@ -0,0 +1,35 @@
+# version 100
+// Daltonization, adapted from http://www.daltonize.org/
+precision mediump float;
+
+varying lowp vec2 uv;
+uniform sampler2D screenTexture;
+
+uniform float factor; // %1.0% 0..1
+
+const mat3 RGB2LMS = mat3(
+  17.8824, 3.45565, 0.0299566,
+  43.5161, 27.1554, 0.184309,
+  4.11935, 3.86714, 1.46709
+);
+const mat3 LMS2RGB = mat3(
+  0.0809444479, -0.0102485335, -0.000365296938,
+  -0.130504409, 0.0540193266, -0.00412161469,
+  0.116721066, -0.113614708, 0.693511405
+);
+// written row by row, hence applied as `lms * SIMULATE`
+const mat3 SIMULATE = mat3(
+  0.0, 2.02344, -2.52581,
+  0.0, 1.0, 0.0,
+  0.0, 0.0, 1.0
+);
+
+void main() {
+  vec4 color = texture2D(screenTexture, uv);
+  vec3 lms = RGB2LMS * color.rgb;
+  vec3 sim = LMS2RGB * (lms * SIMULATE);
+  vec3 err = color.rgb - sim;
+  vec3 shift = vec3(0.0, err.r * 0.7 + err.g, err.r * 0.7 + err.b);
+  vec3 corrected = clamp(color.rgb + shift, 0.0, 1.0);
+  gl_FragColor = vec4(mix(color.rgb, corrected, factor), color.a);
+}
@@ -7,13 +7,17 @@ use prpr_avc::AVPixelFormat;
 use std::{cell::RefCell, io::Write};
 use tempfile::NamedTempFile;
 
+/// Frames decoded in one [`Video::update`] at most, a seek far ahead catches up over several game frames instead of
+/// stalling one.
+const MAX_DECODED_FRAMES: usize = 30;
+
 thread_local! {
     static VIDEO_BUFFERS: RefCell<[Vec<u8>; 3]> = RefCell::default();
 }
 
 pub struct Video {
     video: prpr_avc::Video,
-    _video_file: NamedTempFile,
+    video_file: NamedTempFile,
 
     material: Material,
     tex_y: Texture2D,
@@ -71,7 +75,7 @@ impl Video {
 
         Ok(Self {
             video,
-            _video_file: video_file,
+            video_file,
 
             material,
             tex_y,
@@ -88,6 +92,20 @@ impl Video {
         })
     }
 
+    /// Moves the decoder towards the frame at `t`.
+    ///
+    /// The decoder can only go forward, so seeking backwards reopens the video. Frames up to `t` are decoded by
+    /// the following updates rather than here, which keeps scrubbing from blocking on the decoder.
+    pub fn seek(&mut self, t: f32) -> Result<()> {
+        let target = ((t - self.start_time).max(0.) as f64 / self.frame_delta) as usize;
+        if target < self.next_frame || self.ended {
+            self.video = prpr_avc::Video::open(self.video_file.path().as_os_str().to_str().unwrap(), AVPixelFormat::YUV420P)?;
+            self.next_frame = 0;
+            self.ended = false;
+        }
+        Ok(())
+    }
+
     pub fn update(&mut self, t: f32) -> Result<()> {
         if t < self.start_time || self.ended {
             return Ok(());
@@ -98,16 +116,21 @@ impl Video {
         if self.next_frame <= that_frame {
             VIDEO_BUFFERS.with(|it| {
                 let mut buf = it.borrow_mut();
-                while self.next_frame <= that_frame {
-                    buf[0].clear();
-                    buf[1].clear();
-                    buf[2].clear();
+                let last = that_frame.min(self.next_frame + MAX_DECODED_FRAMES - 1);
+                while self.next_frame <= last {
+                    // frames skipped after a seek or a slow frame are decoded but never uploaded
+                    let shown = self.next_frame == last;
                     if self
                         .video
                         .with_frame(|frame| {
-                            buf[0].extend_from_slice(frame.data(0));
-                            buf[1].extend_from_slice(frame.data_half(1));
-                            buf[2].extend_from_slice(frame.data_half(2));
+                            if shown {
+                                buf[0].clear();
+                                buf[1].clear();
+                                buf[2].clear();
+                                buf[0].extend_from_slice(frame.data(0));
+                                buf[1].extend_from_slice(frame.data_half(1));
+                                buf[2].extend_from_slice(frame.data_half(2));
+                            }
                         })
                         .is_none()
                     {
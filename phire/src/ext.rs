@@ -1,7 +1,7 @@
 use crate::{
-    config::Config,
+    config::{Config, LongNumberMode},
     core::{Matrix, Point, Vector},
-    ui::Ui,
+    ui::{TextPainter, Ui},
 };
 use anyhow::{anyhow, Result};
 use image::DynamicImage;
@@ -17,7 +17,7 @@ use regex::Regex;
 use sasa::AudioManager;
 use serde::Deserialize;
 use std::{
-    collections::VecDeque, future::Future, ops::Deref, pin::Pin, sync::{Arc, Mutex}, task::{Poll, RawWaker, RawWakerVTable, Waker}
+    collections::{HashMap, VecDeque}, future::Future, ops::Deref, pin::Pin, sync::{Arc, Mutex}, task::{Poll, RawWaker, RawWakerVTable, Waker}
 };
 use tracing::{debug, info_span};
 use lazy_static::lazy_static;
@@ -193,6 +193,60 @@ pub fn draw_text_aligned_fix(ui: &mut Ui, text: &str, x: f32, y: f32, anchor: (f
     ui.text(text).pos(x, y).anchor(anchor.0, anchor.1).size(scale).color(color).draw()
 }
 
+const LONG_NUMBER_SCROLL_SPEED: f32 = 0.15;
+
+/// Draws a number that may be wider than `max_width`, fitting it according to `mode`. `time` drives the scrolling.
+pub fn draw_long_number(
+    ui: &mut Ui,
+    mut painter: Option<&mut TextPainter>,
+    text: &str,
+    (x, y): (f32, f32),
+    anchor: (f32, f32),
+    size: f32,
+    color: Color,
+    max_width: f32,
+    mode: LongNumberMode,
+    time: f32,
+) -> Rect {
+    let rect = ui.text(text).size(size).measure_with_font(painter.as_deref_mut());
+    if rect.w <= max_width {
+        return ui.text(text).pos(x, y).anchor(anchor.0, anchor.1).size(size).color(color).draw_with_font(painter);
+    }
+    match mode {
+        LongNumberMode::Shrink => ui
+            .text(text)
+            .pos(x, y)
+            .anchor(anchor.0, anchor.1)
+            .size(size * max_width / rect.w)
+            .color(color)
+            .draw_with_font(painter),
+        LongNumberMode::Truncate => {
+            let chars: Vec<char> = text.chars().collect();
+            let truncated = |len: usize| chars[..len].iter().chain(std::iter::once(&'…')).collect::<String>();
+            // longest prefix that fits with the ellipsis
+            let (mut lo, mut hi) = (0, chars.len());
+            while lo < hi {
+                let mid = (lo + hi + 1) / 2;
+                if ui.text(truncated(mid)).size(size).measure_with_font(painter.as_deref_mut()).w <= max_width {
+                    lo = mid;
+                } else {
+                    hi = mid - 1;
+                }
+            }
+            ui.text(truncated(lo)).pos(x, y).anchor(anchor.0, anchor.1).size(size).color(color).draw_with_font(painter)
+        }
+        LongNumberMode::Scroll => {
+            let overflow = rect.w - max_width;
+            let shift = overflow * (1. - (time * LONG_NUMBER_SCROLL_SPEED / overflow * std::f32::consts::PI).cos()) / 2.;
+            let clip = Rect::new(x - max_width * anchor.0, y - rect.h * anchor.1, max_width, rect.h);
+            ui.scissor(Some(clip));
+            ui.text(text).pos(clip.x - shift, clip.y).size(size).color(color).draw_with_font(painter);
+            ui.scissor(None);
+            clip
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ScaleType {
@@ -495,6 +549,27 @@ pub fn semi_white(alpha: f32) -> Color {
     Color::new(1., 1., 1., alpha)
 }
 
+/// Built-in colors of difficulty tiers, in `0xRRGGBB` form.
+pub const LEVEL_COLORS: [(&str, u32); 5] = [("EZ", 0x51af44), ("HD", 0x3173b3), ("IN", 0xbe2d23), ("AT", 0x383838), ("SP", 0x9b59b6)];
+
+/// Color of the difficulty tier named by the first word of `level`, e.g. `IN` in `IN Lv.15`.
+///
+/// Unknown tiers use white.
+pub fn level_color(level: &str) -> Color {
+    level_color_with(level, &HashMap::new())
+}
+
+/// Like [`level_color`], but entries in `overrides` take precedence over [`LEVEL_COLORS`].
+pub fn level_color_with(level: &str, overrides: &HashMap<String, u32>) -> Color {
+    let tier = level.split_whitespace().next().unwrap_or_default();
+    overrides
+        .iter()
+        .map(|(name, color)| (name.as_str(), *color))
+        .chain(LEVEL_COLORS)
+        .find(|(name, _)| name.eq_ignore_ascii_case(tier))
+        .map_or(WHITE, |(_, color)| Color::from_hex(color))
+}
+
 pub fn open_url(url: &str) -> Result<()> {
     cfg_if::cfg_if! {
         if #[cfg(target_os = "android")] {
@@ -595,6 +670,76 @@ pub fn parse_time(s: &str) -> Option<f32> {
     Some(res)
 }
 
+const BINDABLE_KEYS: [KeyCode; 58] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+];
+
+/// Parses a key name as written in `config.key_bindings`, case-insensitively. Digits can be given without the `Key` prefix.
+pub fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let name = name.trim();
+    BINDABLE_KEYS.into_iter().find(|key| {
+        let debug = format!("{key:?}");
+        debug.eq_ignore_ascii_case(name) || debug.strip_prefix("Key").map_or(false, |it| it == name)
+    })
+}
+
 pub fn parse_alpha(alpha: f32, res_alpha: f32, min_alpha: f32, chart_debug: bool) -> f32 {
     if chart_debug {
         (min_alpha + (1. - min_alpha) * alpha) * res_alpha
@@ -656,6 +801,82 @@ pub fn push_frame_time(frame_times: &mut VecDeque<f64>, real_time: f64) {
     }
 }
 
+/// Rolling record of frame intervals, stored in a fixed ring buffer.
+pub struct FrameStats {
+    intervals: [f32; Self::SIZE],
+    sorted: [f32; Self::SIZE],
+    head: usize,
+    len: usize,
+    last_time: Option<f64>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            intervals: [0.; Self::SIZE],
+            sorted: [0.; Self::SIZE],
+            head: 0,
+            len: 0,
+            last_time: None,
+        }
+    }
+}
+
+impl FrameStats {
+    pub const SIZE: usize = 240;
+
+    pub fn push(&mut self, real_time: f64) {
+        if let Some(last) = self.last_time.replace(real_time) {
+            let dt = (real_time - last) as f32;
+            if dt <= 0. {
+                return;
+            }
+            self.intervals[self.head] = dt;
+            self.head = (self.head + 1) % Self::SIZE;
+            self.len = (self.len + 1).min(Self::SIZE);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.last_time = None;
+    }
+
+    /// Frame intervals in seconds, from the oldest to the newest.
+    pub fn intervals(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.len).map(move |i| self.intervals[(self.head + Self::SIZE - self.len + i) % Self::SIZE])
+    }
+
+    pub fn current_fps(&self) -> f32 {
+        if self.len == 0 {
+            return 0.;
+        }
+        1. / self.intervals[(self.head + Self::SIZE - 1) % Self::SIZE]
+    }
+
+    pub fn average_fps(&self) -> f32 {
+        if self.len == 0 {
+            return 0.;
+        }
+        self.len as f32 / self.intervals().sum::<f32>()
+    }
+
+    /// Average FPS of the slowest 1% of frames.
+    pub fn low_fps(&mut self) -> f32 {
+        if self.len == 0 {
+            return 0.;
+        }
+        let len = self.len;
+        for (i, dt) in (0..len).map(|i| self.intervals[(self.head + Self::SIZE - len + i) % Self::SIZE]).enumerate() {
+            self.sorted[i] = dt;
+        }
+        let sorted = &mut self.sorted[..len];
+        sorted.sort_unstable_by(|a, b| b.total_cmp(a));
+        let count = (len / 100).max(1);
+        count as f32 / sorted[..count].iter().sum::<f32>()
+    }
+}
+
 pub fn round_to_step(value: f32, step: f32) -> f32 {
     let aligned = (value / step).round() * step;
     let digits = (-step.log10()).ceil() as i32;
@@ -124,9 +124,24 @@ impl FileSystem for ExternalFileSystem {
 #[derive(Clone)]
 pub struct ZipFileSystem(pub Arc<Mutex<ZipArchive<Cursor<Vec<u8>>>>>, String);
 
+const INFO_FILES: [&str; 3] = ["info.yml", "info.txt", "info.csv"];
+
 impl ZipFileSystem {
     pub fn new(bytes: Vec<u8>) -> Result<Self> {
         let zip = ZipArchive::new(Cursor::new(bytes))?;
+        // Packages are often zipped together with their enclosing folder (or several of them), so we
+        // treat the shallowest folder containing an info file as the root of the chart
+        let info_root = zip
+            .file_names()
+            .filter_map(|name| {
+                let (dir, file) = name.rsplit_once('/').unwrap_or(("", name));
+                INFO_FILES.contains(&file).then_some(dir)
+            })
+            .min_by_key(|dir| (dir.matches('/').count(), dir.len()))
+            .map(|dir| if dir.is_empty() { String::new() } else { concat_string!(dir, "/") });
+        if let Some(root) = info_root {
+            return Ok(Self(Arc::new(Mutex::new(zip)), root));
+        }
         let root_dirs = zip
             .file_names()
             .filter(|it| it.ends_with('/') && it.find('/') == Some(it.len() - 1))
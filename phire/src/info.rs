@@ -9,6 +9,7 @@ pub enum ChartFormat {
     Pec = 1,
     Pgr = 2,
     Pbc = 3,
+    Osu = 4,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -1,5 +1,5 @@
 use crate::{
-    config::Config,
+    config::{BadNoteStyle, Config, ScoreMode},
     core::{BadNote, Chart, Note, NoteKind, Point, Resource, Vector, NOTE_WIDTH_RATIO_BASE},
     ext::{get_viewport, NotNanExt},
 };
@@ -10,8 +10,12 @@ use macroquad::prelude::{
 use miniquad::{EventHandler, MouseButton};
 use once_cell::sync::Lazy;
 use sasa::{PlaySfxParams, Sfx};
-use serde::Serialize;
-use std::{cell::RefCell, collections::HashMap, num::FpCategory};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    num::FpCategory,
+};
 
 pub const FLICK_SPEED_THRESHOLD: f32 = 0.8;
 pub const LIMIT_PERFECT: f32 = 0.08;
@@ -19,6 +23,9 @@ pub const LIMIT_GOOD: f32 = 0.16;
 pub const LIMIT_BAD: f32 = 0.18;
 pub const UP_TOLERANCE: f32 = 0.05;
 pub const DIST_FACTOR: f32 = 0.2;
+pub const MAX_SIMULATED_LATENCY_MS: f32 = 500.;
+/// Capacity of `Judge::hit_errors`, the oldest entries are dropped beyond it
+pub const HIT_ERROR_CAPACITY: usize = 32;
 
 pub fn play_sfx(sfx: &mut Sfx, config: &Config) {
     if config.volume_sfx <= 1e-2 {
@@ -70,6 +77,23 @@ impl HitSound {
         }
     }
 
+    /// Plays the resource pack's sound for this note kind and judgement if there is one, otherwise the hitsound itself.
+    /// Custom hitsounds of the chart always take precedence.
+    pub fn play_judged(&self, res: &mut Resource, kind: &NoteKind, judgement: Judgement) {
+        let index = match judgement {
+            Judgement::Perfect => Some(0),
+            Judgement::Good => Some(1),
+            _ => None,
+        };
+        if let (Some(index), HitSound::Click | HitSound::Flick | HitSound::Drag) = (index, self) {
+            if let Some(sfx) = &mut res.sfx_judge[kind.order() as usize][index] {
+                play_sfx(sfx, &res.config);
+                return;
+            }
+        }
+        self.play(res);
+    }
+
     pub fn default_from_kind(kind: &NoteKind) -> Self {
         match kind {
             NoteKind::Click => HitSound::Click,
@@ -82,6 +106,8 @@ impl HitSound {
 
 pub struct FlickTracker {
     threshold: f32,
+    dead_zone: f32,
+    origin: Point,
     last_point: Point,
     last_delta: Option<Vector>,
     last_time: f32,
@@ -95,6 +121,8 @@ impl FlickTracker {
         let dpi = 275;
         Self {
             threshold: FLICK_SPEED_THRESHOLD * dpi as f32 / 386.,
+            dead_zone: 0.,
+            origin: point,
             last_point: point,
             last_delta: None,
             last_time: time,
@@ -103,6 +131,12 @@ impl FlickTracker {
         }
     }
 
+    /// Movements within `dead_zone` of the starting point are never treated as flicks.
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+
     pub fn push(&mut self, time: f32, position: Point) {
         let delta = position - self.last_point;
         self.last_point = position;
@@ -112,7 +146,7 @@ impl FlickTracker {
             if speed < self.threshold {
                 self.stopped = true;
             }
-            if self.stopped && !self.flicked {
+            if self.stopped && !self.flicked && (position - self.origin).magnitude() >= self.dead_zone {
                 self.flicked = delta.magnitude() / dt >= self.threshold * 2.;
             }
             // if speed < self.threshold || self.stopped {
@@ -228,6 +262,7 @@ impl JudgeInner {
             early,
             late: self.diffs.len() as u32 - early,
             std: 0.,
+            ..Default::default()
         }
     }
 
@@ -245,6 +280,64 @@ mod inner;
 #[cfg(feature = "closed")]
 use inner::*;
 
+/// How long before the end of a hold a release no longer breaks it.
+fn hold_tail(config: &Config) -> f32 {
+    if config.forgiving_holds {
+        LIMIT_BAD.max(config.forgiving_hold_window)
+    } else {
+        LIMIT_BAD
+    }
+}
+
+/// Advances a hold that is being held at `t`, `touching` tells whether it is still pressed. Returns whether the hold
+/// breaks and should be judged as a miss.
+fn update_hold(status: &mut JudgeStatus, end_time: f32, t: f32, spd: f32, hold_tail: f32, touching: impl FnOnce() -> bool) -> bool {
+    let JudgeStatus::Hold(.., pre_judge, up_time) = status else {
+        return false;
+    };
+    if (end_time - t) / spd <= hold_tail {
+        *pre_judge = true;
+        return false;
+    }
+    if touching() {
+        *up_time = f32::INFINITY;
+    } else if t > *up_time + UP_TOLERANCE {
+        *status = JudgeStatus::Judged;
+        return true;
+    } else if up_time.is_infinite() {
+        *up_time = t;
+    }
+    false
+}
+
+/// Picks the judgement and hit error of a note under imperfect autoplay.
+///
+/// The chance of an imperfect hit is derived from `target` and nudged by how far `current` accuracy has drifted from it.
+/// Bad hits are only planned for clicks, and only when the target is unreachable with goods alone.
+fn plan_autoplay_hit(kind: &NoteKind, target: f32, current: f32) -> (Judgement, f32) {
+    const GOOD_WEIGHT: f32 = 0.65;
+    const FEEDBACK: f32 = 4.;
+    if matches!(kind, NoteKind::Drag | NoteKind::Flick) {
+        return (Judgement::Perfect, 0.);
+    }
+    let target = target.clamp(0., 1.);
+    let bad = if matches!(kind, NoteKind::Click) {
+        (1. - target / GOOD_WEIGHT).max(0.)
+    } else {
+        0.
+    };
+    let imperfect = ((1. - target) / (1. - GOOD_WEIGHT) + (current - target) * FEEDBACK).clamp(0., 1.);
+    let sign = if rand::gen_range(0., 1.) < 0.5 { -1. } else { 1. };
+    let r = rand::gen_range(0., 1.);
+    if r < bad.min(imperfect) {
+        (Judgement::Bad, sign * rand::gen_range(LIMIT_GOOD, LIMIT_BAD))
+    } else if r < imperfect {
+        (Judgement::Good, sign * rand::gen_range(LIMIT_PERFECT, LIMIT_GOOD))
+    } else {
+        (Judgement::Perfect, sign * rand::gen_range(0., LIMIT_PERFECT * 0.75))
+    }
+}
+
 #[repr(C)]
 pub struct Judge {
     // notes of each line in order
@@ -257,6 +350,46 @@ pub struct Judge {
 
     pub(crate) inner: JudgeInner,
     pub judgements: RefCell<Vec<(f32, u32, u32, Result<Judgement, bool>)>>,
+
+    // the fields above are shared with the closed build and must keep their layout, new ones go below
+
+    key_touches: Vec<Touch>,
+
+    active_touches: HashSet<u64>,
+    max_touches: u32,
+    total_touches: u32,
+
+    // planned (judgement, hit error) of each note under imperfect autoplay
+    autoplay_plan: HashMap<(usize, u32), (Judgement, f32)>,
+
+    pub score_mode: ScoreMode,
+    // kept here rather than read from `inner`, which doesn't expose them
+    max_combo: u32,
+    num_of_notes: u32,
+    /// Signed timing errors (in seconds) of every Perfect and Good judgement, for [`PlayResult::hit_errors`]
+    timing_errors: Vec<f32>,
+    /// Good judgements with `config.hit_error_popup`: time, position in chart coordinates and whether it's late
+    pub hit_popups: Vec<(f32, Point, bool)>,
+    /// Timed hits with `config.show_hit_ms` or `config.timing_meter`: time, position in chart coordinates and signed error
+    /// in seconds (late is positive)
+    pub hit_errors: VecDeque<(f32, Point, f32)>,
+
+    // input held back by `config.simulated_input_latency_ms`
+    delayed_input: VecDeque<FrameInput>,
+    last_polled: Vec<Touch>,
+
+    // start times of the touches held down, for `config.touch_debounce_ms`
+    touch_starts: HashMap<u64, f64>,
+}
+
+/// Input gathered for one judge update.
+struct FrameInput {
+    release: f64,
+    polled: Vec<Touch>,
+    events: Vec<Touch>,
+    keys_down: u32,
+    keys_delta: i32,
+    key_touches: Vec<Touch>,
 }
 
 static SUBSCRIBER_ID: Lazy<usize> = Lazy::new(register_input_subscriber);
@@ -264,39 +397,149 @@ thread_local! {
     static TOUCHES: RefCell<(Vec<Touch>, i32, u32)> = RefCell::default();
 }
 
+/// Real notes of each line at or after `from`, sorted by time.
+fn note_lists(chart: &Chart, from: f32) -> Vec<(Vec<u32>, usize)> {
+    chart
+        .lines
+        .iter()
+        .map(|line| {
+            let mut idx: Vec<u32> = (0..(line.notes.len() as u32))
+                .filter(|it| {
+                    let note = &line.notes[*it as usize];
+                    !note.fake && note.time >= from
+                })
+                .collect();
+            idx.sort_by_key(|id| line.notes[*id as usize].time.not_nan());
+            (idx, 0)
+        })
+        .collect()
+}
+
 impl Judge {
     pub fn new(chart: &Chart) -> Self {
-        let notes = chart
-            .lines
-            .iter()
-            .map(|line| {
-                let mut idx: Vec<u32> = (0..(line.notes.len() as u32)).filter(|it| !line.notes[*it as usize].fake).collect();
-                idx.sort_by_key(|id| line.notes[*id as usize].time.not_nan());
-                (idx, 0)
-            })
-            .collect();
+        let notes = note_lists(chart, f32::NEG_INFINITY);
+        let num_of_notes = chart.lines.iter().map(|it| it.notes.iter().filter(|it| !it.fake).count() as u32).sum();
         Self {
             notes,
             trackers: HashMap::new(),
             last_time: 0.,
 
             key_down_count: 0,
+            key_touches: Vec::new(),
+
+            active_touches: HashSet::new(),
+            max_touches: 0,
+            total_touches: 0,
 
-            inner: JudgeInner::new(chart.lines.iter().map(|it| it.notes.iter().filter(|it| !it.fake).count() as u32).sum()),
+            autoplay_plan: HashMap::new(),
+
+            score_mode: ScoreMode::default(),
+            max_combo: 0,
+            num_of_notes,
+            timing_errors: Vec::new(),
+            inner: JudgeInner::new(num_of_notes),
             judgements: RefCell::new(Vec::new()),
+            hit_popups: Vec::new(),
+            hit_errors: VecDeque::with_capacity(HIT_ERROR_CAPACITY),
+
+            delayed_input: VecDeque::new(),
+            last_polled: Vec::new(),
+
+            touch_starts: HashMap::new(),
+        }
+    }
+
+    /// Leaves the notes before `time` out of the run and its result, marking them as judged, e.g. when practice starts
+    /// midway through the chart. Resetting the chart brings them back, so this has to be called again after that.
+    pub fn skip_before(&mut self, chart: &mut Chart, time: f32) {
+        self.notes = note_lists(chart, time);
+        self.num_of_notes = self.notes.iter().map(|it| it.0.len() as u32).sum();
+        self.inner = JudgeInner::new(self.num_of_notes);
+        for note in chart.lines.iter_mut().flat_map(|it| it.notes.iter_mut()) {
+            if !note.fake && note.time < time {
+                note.judge = JudgeStatus::Judged;
+            }
         }
     }
 
     pub fn reset(&mut self) {
         self.notes.iter_mut().for_each(|it| it.1 = 0);
         self.trackers.clear();
+        self.active_touches.clear();
+        self.max_touches = 0;
+        self.total_touches = 0;
+        self.autoplay_plan.clear();
+        self.max_combo = 0;
+        self.timing_errors.clear();
         self.inner.reset();
         self.judgements.borrow_mut().clear();
+        self.hit_popups.clear();
+        self.hit_errors.clear();
+        self.delayed_input.clear();
+        self.last_polled.clear();
+        self.touch_starts.clear();
+    }
+
+    /// Drops touches, keys and autoplay plans in flight while keeping the judged notes and the score, e.g. when
+    /// autoplay is switched mid-run.
+    pub fn reset_input(&mut self, res: &Resource) {
+        self.key_down_count = 0;
+        self.key_touches.clear();
+        self.trackers.clear();
+        self.active_touches.clear();
+        self.autoplay_plan.clear();
+        self.delayed_input.clear();
+        self.last_polled.clear();
+        self.touch_starts.clear();
+        // autoplay doesn't keep this up to date
+        self.last_time = res.time / res.config.speed;
+    }
+
+    /// Queues this frame's input and returns the input due after `latency` seconds.
+    fn delay_input(&mut self, latency: f64, input: FrameInput) -> FrameInput {
+        let now = get_time();
+        self.delayed_input.push_back(FrameInput { release: now + latency, ..input });
+        let mut due = FrameInput {
+            release: now,
+            polled: std::mem::take(&mut self.last_polled),
+            events: Vec::new(),
+            keys_down: 0,
+            keys_delta: 0,
+            key_touches: Vec::new(),
+        };
+        while self.delayed_input.front().map_or(false, |it| it.release <= now) {
+            let mut input = self.delayed_input.pop_front().unwrap();
+            for touch in input.polled.iter_mut().chain(input.events.iter_mut()).chain(input.key_touches.iter_mut()) {
+                if touch.time.is_finite() {
+                    touch.time += latency;
+                }
+            }
+            due.polled = input.polled;
+            due.events.extend(input.events);
+            due.keys_down += input.keys_down;
+            due.keys_delta += input.keys_delta;
+            due.key_touches.extend(input.key_touches);
+        }
+        // touches still down are repeated until newer input is due, without starting again
+        self.last_polled = due
+            .polled
+            .iter()
+            .filter(|it| !matches!(it.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+            .map(|it| Touch {
+                phase: TouchPhase::Stationary,
+                ..it.clone()
+            })
+            .collect();
+        due
     }
 
     pub fn commit(&mut self, t: f32, what: Judgement, line_id: u32, note_id: u32, diff: f32) {
         self.judgements.borrow_mut().push((t, line_id, note_id, Ok(what)));
         self.inner.commit(what, diff);
+        self.max_combo = self.max_combo.max(self.inner.combo());
+        if matches!(what, Judgement::Perfect | Judgement::Good) {
+            self.timing_errors.push(diff);
+        }
     }
 
     #[inline]
@@ -314,6 +557,12 @@ impl Judge {
         self.inner.score()
     }
 
+    /// The score under [`Self::score_mode`]. Records and uploads always use [`Self::score`].
+    #[inline]
+    pub fn display_score(&self) -> u32 {
+        self.score_mode.apply(self.inner.score(), self.inner.accuracy())
+    }
+
     pub(crate) fn on_new_frame() {
         let mut handler = Handler(Vec::new(), 0, 0);
         repeat_all_miniquad_input(&mut handler, *SUBSCRIBER_ID);
@@ -323,6 +572,18 @@ impl Judge {
         });
     }
 
+    /// Id of the synthesized touch of the `lane`-th key binding.
+    pub fn lane_touch_id(lane: usize) -> u64 {
+        u64::MAX - 16 - lane as u64
+    }
+
+    /// Sets touches (in screen coordinates) synthesized from key bindings for the next update.
+    ///
+    /// Keys fed here no longer count as a tap anywhere on the screen.
+    pub fn feed_key_touches(&mut self, touches: Vec<Touch>) {
+        self.key_touches = touches;
+    }
+
     fn touch_transform(flip_x: bool, scale: f32) -> impl Fn(&mut Touch) {
         let vp = get_viewport();
         move |touch| {
@@ -354,12 +615,37 @@ impl Judge {
         })
     }
 
+    /// Records touch statistics of this frame. This has no effect on judgement.
+    fn track_touches(&mut self) {
+        for touch in Self::get_touches(1.) {
+            match touch.phase {
+                TouchPhase::Started => {
+                    if self.active_touches.insert(touch.id) {
+                        self.total_touches += 1;
+                    }
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    self.active_touches.remove(&touch.id);
+                }
+                _ => {}
+            }
+        }
+        self.max_touches = self.max_touches.max(self.active_touches.len() as u32);
+    }
+
     pub fn update(&mut self, res: &mut Resource, chart: &mut Chart, bad_notes: &mut Vec<BadNote>) {
-        if res.config.autoplay() {
+        let mut key_touches = std::mem::take(&mut self.key_touches);
+        if res.config.autoplay() || !res.config.interactive {
             self.auto_play_update(res, chart);
             return;
         }
+        self.track_touches();
         const X_DIFF_MAX: f32 = 0.21 / (16. / 9.) * 2.;
+        // with `lenient_position` touches reach notes anywhere, the closest note in time is picked
+        let lenient = res.config.lenient_position;
+        let x_diff_max = if lenient { f32::INFINITY } else { X_DIFF_MAX };
+        let hold_tail = hold_tail(&res.config);
+        let dist_penalty = |dist: f32| if lenient { 0. } else { (dist / NOTE_WIDTH_RATIO_BASE - 1.).max(0.) * DIST_FACTOR };
         let spd = res.config.speed;
 
         #[cfg(not(target_os = "windows"))]
@@ -367,7 +653,7 @@ impl Judge {
 
         let t = res.time;
         // TODO optimize
-        let mut touches: HashMap<u64, Touch> = {
+        let mut polled = {
             let mut touches = touches();
             let btn = MouseButton::Left;
             let id = button_to_id(btn);
@@ -396,8 +682,38 @@ impl Judge {
                     time: f64::NEG_INFINITY,
                 });
             }
-            let tr = Self::touch_transform(res.config.flip_x(), res.config.chart_ratio);
             touches
+        };
+        let (mut events, mut keys_down, mut keys_delta) = TOUCHES.with(|it| {
+            let guard = it.borrow();
+            (guard.0.clone(), guard.2, guard.1)
+        });
+        let latency = res.config.simulated_input_latency_ms.clamp(0., MAX_SIMULATED_LATENCY_MS) as f64 / 1000.;
+        if latency > 0. {
+            let input = FrameInput {
+                release: 0.,
+                polled,
+                events,
+                keys_down,
+                keys_delta,
+                key_touches,
+            };
+            FrameInput {
+                polled,
+                events,
+                keys_down,
+                keys_delta,
+                key_touches,
+                ..
+            } = self.delay_input(latency, input);
+        } else if !self.delayed_input.is_empty() {
+            self.delayed_input.clear();
+            self.last_polled.clear();
+        }
+        let mut touches: HashMap<u64, Touch> = {
+            polled.extend(key_touches.iter().cloned());
+            let tr = Self::touch_transform(res.config.flip_x(), res.config.chart_ratio);
+            polled
                 .into_iter()
                 .map(|mut it| {
                     tr(&mut it);
@@ -405,17 +721,21 @@ impl Judge {
                 })
                 .collect()
         };
-        let (events, keys_down) = TOUCHES.with(|it| {
-            let guard = it.borrow();
-            (guard.0.clone(), guard.2)
-        });
-        self.key_down_count = self.key_down_count.saturating_add_signed(TOUCHES.with(|it| it.borrow().1));
+        // bound keys act on their own lanes instead of anywhere
+        let count_phase = |phase| key_touches.iter().filter(|it| it.phase == phase).count();
+        let (key_started, key_ended) = (count_phase(TouchPhase::Started), count_phase(TouchPhase::Ended));
+        let keys_down = keys_down.saturating_sub(key_started as u32);
+        self.key_down_count = self
+            .key_down_count
+            .saturating_add_signed(keys_delta - key_started as i32 + key_ended as i32);
+        events.extend(key_touches.into_iter().filter(|it| matches!(it.phase, TouchPhase::Started | TouchPhase::Ended)));
         {
             fn to_local(Vec2 { x, y }: Vec2) -> Point {
                 Point::new(x / screen_width() * 2. - 1., y / screen_height() * 2. - 1.)
             }
             let delta = (t / spd - self.last_time) as f64 / (events.len() + 1) as f64;
             let mut t = self.last_time as f64;
+            let debounce = res.config.touch_debounce_ms as f64 / 1000.;
             for Touch {
                 id,
                 phase,
@@ -428,7 +748,10 @@ impl Judge {
                 let p = to_local(p);
                 match phase {
                     TouchPhase::Started => {
-                        self.trackers.insert(id, FlickTracker::new(res.dpi, t, p));
+                        self.trackers.insert(id, FlickTracker::new(res.dpi, t, p).with_dead_zone(res.config.touch_dead_zone));
+                        if time.is_finite() {
+                            self.touch_starts.insert(id, time);
+                        }
                         touches
                             .entry(id)
                             .or_insert_with(|| Touch {
@@ -446,6 +769,12 @@ impl Judge {
                     }
                     TouchPhase::Ended | TouchPhase::Cancelled => {
                         self.trackers.remove(&id);
+                        // phantom touches that vanish right after appearing, possibly in an earlier frame
+                        if let Some(start) = self.touch_starts.remove(&id) {
+                            if debounce > 0. && !(time - start > debounce) {
+                                touches.remove(&id);
+                            }
+                        }
                     }
                 }
             }
@@ -508,7 +837,7 @@ impl Judge {
                 continue;
             }
             let t = time_of(touch);
-            let mut closest = (None, X_DIFF_MAX, LIMIT_BAD, LIMIT_BAD + (X_DIFF_MAX / NOTE_WIDTH_RATIO_BASE - 1.).max(0.) * DIST_FACTOR, 0.);
+            let mut closest = (None, x_diff_max, LIMIT_BAD, LIMIT_BAD + dist_penalty(X_DIFF_MAX), 0.);
             for (line_id, ((line, pos), (idx, st))) in chart.lines.iter_mut().zip(pos.iter()).zip(self.notes.iter_mut()).enumerate() {
                 let Some(pos) = pos[id] else { continue; };
                 for id in &idx[*st..] {
@@ -528,7 +857,7 @@ impl Judge {
                     x.set_time(t);
                     let posx = pos.x;
                     let dist = (x.now() - posx).abs();
-                    if dist > X_DIFF_MAX {
+                    if dist > x_diff_max {
                         continue;
                     }
                     if dt
@@ -545,7 +874,7 @@ impl Judge {
                     } else {
                         dt
                     };
-                    let key = dt + (dist / NOTE_WIDTH_RATIO_BASE - 1.).max(0.) * DIST_FACTOR;
+                    let key = dt + dist_penalty(dist);
                     if key < closest.3 {
                         closest = (Some((line_id, *id)), dist, dt, key, posx);
                     }
@@ -559,7 +888,7 @@ impl Judge {
                     let dist = (dist2 - dist).abs();
                     let judge_time = t - note.time;
                     matches!(note.kind, NoteKind::Drag | NoteKind::Flick)
-                        && dist <= X_DIFF_MAX
+                        && dist <= x_diff_max
                         && !note.fake
                         && !note.attr
                         && judge_time >= -LIMIT_GOOD
@@ -593,7 +922,7 @@ impl Judge {
                                 judgements.push((if dt <= LIMIT_PERFECT { Judgement::Perfect } else { Judgement::Good }, line_id, id, Some(t)));
                             }
                             NoteKind::Hold { .. } => {
-                                play_sfx(&mut res.sfx_click, &res.config);
+                                HitSound::Click.play_judged(res, &note.kind, if dt <= LIMIT_PERFECT { Judgement::Perfect } else { Judgement::Good });
                                 self.judgements.borrow_mut().push((t, line_id as _, id, Err(dt <= LIMIT_PERFECT)));
                                 note.judge = JudgeStatus::Hold(dt <= LIMIT_PERFECT, t, t, false, f32::INFINITY);
                             }
@@ -655,7 +984,7 @@ impl Judge {
                             ));
                         }
                         NoteKind::Hold { .. } => {
-                            note.hitsound.play(res);
+                            note.hitsound.play_judged(res, &note.kind, if dt <= LIMIT_PERFECT { Judgement::Perfect } else { Judgement::Good });
                             self.judgements.borrow_mut().push((t, line_id as _, id, Err(dt <= LIMIT_PERFECT)));
                             note.judge = JudgeStatus::Hold(dt <= LIMIT_PERFECT, t, (t - note.time) / spd, false, f32::INFINITY);
                         }
@@ -666,28 +995,43 @@ impl Judge {
                 break;
             }
         }
+        // without positions a held touch would reach every drag, so each one only takes the drag closest to it in time
+        let lenient_drags: HashSet<(usize, u32)> = if lenient {
+            let held = (0..touches.len()).filter(|id| pos.iter().any(|it| it[*id].is_some())).count();
+            let mut drags: Vec<_> = chart
+                .lines
+                .iter()
+                .zip(self.notes.iter())
+                .enumerate()
+                .flat_map(|(line_id, (line, (idx, st)))| {
+                    idx[*st..].iter().filter_map(move |id| {
+                        let note = &line.notes[*id as usize];
+                        let dt = ((t - note.time) / spd).abs();
+                        (matches!(note.kind, NoteKind::Drag) && matches!(note.judge, JudgeStatus::NotJudged) && dt <= LIMIT_BAD)
+                            .then_some((dt, line_id, *id))
+                    })
+                })
+                .collect();
+            drags.sort_by_key(|it| it.0.not_nan());
+            drags.into_iter().take(held).map(|(_, line_id, id)| (line_id, id)).collect()
+        } else {
+            HashSet::new()
+        };
         for (line_id, ((line, pos), (idx, st))) in chart.lines.iter_mut().zip(pos.iter()).zip(self.notes.iter()).enumerate() {
             line.object.set_time(t);
             for id in &idx[*st..] {
                 let note = &mut line.notes[*id as usize];
                 if let NoteKind::Hold { end_time, .. } = &note.kind {
-                    if let JudgeStatus::Hold(.., ref mut pre_judge, ref mut up_time) = note.judge {
-                        if (*end_time - t) / spd <= LIMIT_BAD {
-                            *pre_judge = true;
-                            continue;
-                        }
+                    if matches!(note.judge, JudgeStatus::Hold(..)) {
                         let x = &mut note.object.translation.0;
-                        x.set_time(t);
-                        let x = x.now();
-                        if self.key_down_count == 0 && !pos.iter().any(|it| it.map_or(false, |it| (it.x - x).abs() <= X_DIFF_MAX)) {
-                            if t > *up_time + UP_TOLERANCE {
-                                note.judge = JudgeStatus::Judged;
-                                judgements.push((Judgement::Miss, line_id, *id, None));
-                            } else if up_time.is_infinite() {
-                                *up_time = t;
-                            }
-                        } else {
-                            *up_time = f32::INFINITY;
+                        let key_down = self.key_down_count != 0;
+                        let touching = || {
+                            x.set_time(t);
+                            let x = x.now();
+                            key_down || pos.iter().any(|it| it.map_or(false, |it| (it.x - x).abs() <= x_diff_max))
+                        };
+                        if update_hold(&mut note.judge, *end_time, t, spd, hold_tail, touching) {
+                            judgements.push((Judgement::Miss, line_id, *id, None));
                         }
                         continue;
                     }
@@ -713,12 +1057,16 @@ impl Judge {
                 x.set_time(t);
                 let x = x.now();
                 if self.key_down_count != 0
-                    || pos.iter().any(|it| {
-                        it.map_or(false, |it| {
-                            let dx = (it.x - x).abs();
-                            dx <= X_DIFF_MAX && dt <= (LIMIT_BAD - LIMIT_PERFECT * (dx - 0.9).max(0.))
+                    || if lenient {
+                        lenient_drags.contains(&(line_id, *id))
+                    } else {
+                        pos.iter().any(|it| {
+                            it.map_or(false, |it| {
+                                let dx = (it.x - x).abs();
+                                dx <= X_DIFF_MAX && dt <= (LIMIT_BAD - LIMIT_PERFECT * (dx - 0.9).max(0.))
+                            })
                         })
-                    })
+                    }
                 {
                     note.judge = JudgeStatus::PreJudge;
                 }
@@ -768,19 +1116,31 @@ impl Judge {
             let line = &chart.lines[line_id];
             let note = &line.notes[id as usize];
             let line_tr = line.now_transform(res, &chart.lines);
-            self.commit(
-                t,
-                judgement,
-                line_id as _,
-                id,
-                if matches!(judgement, Judgement::Miss) {
-                    0.25
-                } else if matches!(note.kind, NoteKind::Drag | NoteKind::Flick) {
-                    0.
-                } else {
-                    (diff.unwrap_or(t) - note.time) / spd
-                },
-            );
+            let error = if matches!(judgement, Judgement::Miss) {
+                0.25
+            } else if matches!(note.kind, NoteKind::Drag | NoteKind::Flick) {
+                0.
+            } else {
+                (diff.unwrap_or(t) - note.time) / spd
+            };
+            self.commit(t, judgement, line_id as _, id, error);
+            // holds are committed when they end, only taps have their error at hand here
+            let timed = matches!(judgement, Judgement::Perfect | Judgement::Good) && matches!(note.kind, NoteKind::Click);
+            let popup = matches!(judgement, Judgement::Good) && res.config.hit_error_popup;
+            let timed = timed && (res.config.show_hit_ms || res.config.timing_meter);
+            if popup || timed {
+                let mut position = Point::origin();
+                res.with_model(line_tr * note.object.now(res), |res| position = res.world_to_screen(Point::origin()));
+                if popup {
+                    self.hit_popups.push((t, position, error > 0.));
+                }
+                if timed {
+                    if self.hit_errors.len() == HIT_ERROR_CAPACITY {
+                        self.hit_errors.pop_front();
+                    }
+                    self.hit_errors.push_back((t, position, error));
+                }
+            }
             if matches!(note.kind, NoteKind::Hold { .. }) {
                 continue;
             }
@@ -794,7 +1154,7 @@ impl Judge {
                     true
                 }
                 Judgement::Bad => {
-                    if !matches!(note.kind, NoteKind::Hold { .. }) {
+                    if !matches!(note.kind, NoteKind::Hold { .. }) && res.config.bad_note_style != BadNoteStyle::Hidden {
                         bad_notes.push(BadNote {
                             time: t,
                             kind: note.kind.clone(),
@@ -818,7 +1178,7 @@ impl Judge {
                 }
                 _ => false,
             } {
-                note.hitsound.play(res);
+                note.hitsound.play_judged(res, &note.kind, judgement);
             }
         }
         for (line, (idx, st)) in chart.lines.iter().zip(self.notes.iter_mut()) {
@@ -841,6 +1201,7 @@ impl Judge {
         } else {
             (Judgement::Perfect, Judgement::Perfect, 0., res.res_pack.info.fx_perfect())
         };
+        let imperfect = res.config.autoplay_accuracy.filter(|_| !res.config.all_bad && !res.config.all_good);
         //let spd = res.config.speed;
         let mut judgements = Vec::new();
         for (line_id, (line, (idx, st))) in chart.lines.iter_mut().zip(self.notes.iter_mut()).enumerate() {
@@ -849,8 +1210,14 @@ impl Judge {
                 if let JudgeStatus::Hold(..) = note.judge {
                     if let NoteKind::Hold { end_time, .. } = note.kind {
                         if t >= end_time {
+                            let plan = match note.judge {
+                                JudgeStatus::Hold(perfect, _, diff, ..) if imperfect.is_some() => {
+                                    Some((if perfect { Judgement::Perfect } else { Judgement::Good }, diff))
+                                }
+                                _ => None,
+                            };
                             note.judge = JudgeStatus::Judged;
-                            judgements.push((line_id, *id));
+                            judgements.push((line_id, *id, plan));
                             continue;
                         }
                     }
@@ -858,20 +1225,38 @@ impl Judge {
                 if !matches!(note.judge, JudgeStatus::NotJudged) {
                     continue;
                 }
-                if note.time > t {
-                    break;
-                }
+                let plan = if let Some(target) = imperfect {
+                    if note.time - LIMIT_BAD > t {
+                        break;
+                    }
+                    let plan = *self
+                        .autoplay_plan
+                        .entry((line_id, *id))
+                        .or_insert_with(|| plan_autoplay_hit(&note.kind, target, self.inner.real_time_accuracy() as f32));
+                    if note.time + plan.1 > t {
+                        continue;
+                    }
+                    Some(plan)
+                } else {
+                    if note.time > t {
+                        break;
+                    }
+                    None
+                };
                 note.judge = if matches!(note.kind, NoteKind::Hold { .. }) {
                     if !res.config.disable_audio {
-                        note.hitsound.play(res);
+                        note.hitsound.play_judged(res, &note.kind, plan.map_or(judge_type_hold, |it| it.0));
                     }
                     self.judgements.borrow_mut().push((t, line_id as _, *id, Err(true)));
                     //println!("{}\t{}\t{}", t, note.time, t - note.time);
                     // 都是AutoPlay了为什么还要输出判定时间差
                     //JudgeStatus::Hold(true, t, (t - note.time) / spd, false, f32::INFINITY)
-                    JudgeStatus::Hold(true, t, judge_time, true, f32::INFINITY)
+                    match plan {
+                        Some((judgement, diff)) => JudgeStatus::Hold(matches!(judgement, Judgement::Perfect), t, diff, true, f32::INFINITY),
+                        None => JudgeStatus::Hold(true, t, judge_time, true, f32::INFINITY),
+                    }
                 } else {
-                    judgements.push((line_id, *id));
+                    judgements.push((line_id, *id, plan));
                     JudgeStatus::Judged
                 };
             }
@@ -882,7 +1267,7 @@ impl Judge {
                 *st += 1;
             }
         }
-        for (line_id, id) in judgements.into_iter() {
+        for (line_id, id, plan) in judgements.into_iter() {
             let (note_transform, note_kind, note_hitsound) = {
                 let line = &mut chart.lines[line_id];
                 let note = &mut line.notes[id as usize];
@@ -892,6 +1277,23 @@ impl Judge {
                 (note.object.now(res), note.kind.clone(), note.hitsound.clone())
             };
             let line = &chart.lines[line_id];
+            if let Some((judgement, diff)) = plan {
+                self.commit(t, judgement, line_id as _, id, diff);
+                let color = match judgement {
+                    Judgement::Perfect => Some(res.res_pack.info.fx_perfect()),
+                    Judgement::Good => Some(res.res_pack.info.fx_good()),
+                    _ => None,
+                };
+                if let (Some(color), false) = (color, matches!(note_kind, NoteKind::Hold { .. })) {
+                    res.with_model(line.now_transform(res, &chart.lines) * note_transform, |res| {
+                        res.emit_at_origin(line.notes[id as usize].rotation(line), color)
+                    });
+                }
+                if !res.config.disable_audio && !matches!(note_kind, NoteKind::Hold { .. }) && color.is_some() {
+                    note_hitsound.play_judged(res, &note_kind, judgement);
+                }
+                continue;
+            }
             match note_kind {
                 NoteKind::Click => {
                     self.commit(t, judge_type, line_id as _, id, 0.);
@@ -914,18 +1316,21 @@ impl Judge {
 
             if !res.config.disable_audio {
                 match note_kind {
-                    NoteKind::Click => if !res.config.all_bad {note_hitsound.play(res)},
+                    NoteKind::Click => if !res.config.all_bad {note_hitsound.play_judged(res, &note_kind, judge_type)},
                     NoteKind::Hold { .. } => (),
-                    _ => note_hitsound.play(res),
+                    _ => note_hitsound.play_judged(res, &note_kind, Judgement::Perfect),
                 }
             }
 
         }
     }
 
-    #[inline]
     pub fn result(&self) -> PlayResult {
-        self.inner.result()
+        let mut result = self.inner.result();
+        result.hit_errors = self.timing_errors.clone();
+        result.max_touches = self.max_touches;
+        result.total_touches = self.total_touches;
+        result
     }
 
     #[inline]
@@ -933,10 +1338,43 @@ impl Judge {
         self.inner.combo()
     }
 
+    pub fn live_stats(&self) -> LiveStats {
+        LiveStats {
+            score: self.score(),
+            accuracy: self.inner.real_time_accuracy(),
+            combo: self.inner.combo(),
+            max_combo: self.max_combo,
+            counts: self.inner.counts(),
+            num_of_notes: self.num_of_notes,
+        }
+    }
+
     #[inline]
     pub fn counts(&self) -> [u32; 4] {
         self.inner.counts()
     }
+
+    /// Number of notes judged so far, reaches [`Self::total_notes`] at the end of the chart.
+    #[inline]
+    pub fn judged_count(&self) -> u32 {
+        self.inner.counts().iter().sum()
+    }
+
+    /// Number of notes to be judged, fake notes are excluded as in [`PlayResult::num_of_notes`].
+    #[inline]
+    pub fn total_notes(&self) -> u32 {
+        self.num_of_notes
+    }
+
+    pub fn snapshot(&self) -> JudgeSnapshot {
+        JudgeSnapshot {
+            score: self.score(),
+            combo: self.inner.combo(),
+            max_combo: self.max_combo,
+            accuracy: self.inner.real_time_accuracy(),
+            counts: self.inner.counts(),
+        }
+    }
 }
 
 struct Handler(Vec<Touch>, i32, u32);
@@ -1003,9 +1441,43 @@ impl EventHandler for Handler {
     fn key_up_event(&mut self, _ctx: &mut miniquad::Context, _keycode: KeyCode, _keymods: miniquad::KeyMods) {
         self.1 -= 1;
     }
+
+    fn window_minimized_event(&mut self, _ctx: &mut miniquad::Context) {
+        crate::scene::set_window_focused(false);
+    }
+
+    fn window_restored_event(&mut self, _ctx: &mut miniquad::Context) {
+        crate::scene::set_window_focused(true);
+    }
 }
 
-#[derive(Default)]
+/// Score of a play sampled over time, enough to race against it as a ghost.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReplayData {
+    /// `(time, score)` pairs in time order, recorded whenever the score changes
+    pub samples: Vec<(f32, u32)>,
+}
+
+impl ReplayData {
+    pub fn push(&mut self, time: f32, score: u32) {
+        // after seeking back, the samples past `time` belong to a part of the run that is being replayed
+        let kept = self.samples.partition_point(|it| it.0 <= time);
+        self.samples.truncate(kept);
+        if self.samples.last().map_or(true, |it| it.1 != score) {
+            self.samples.push((time, score));
+        }
+    }
+
+    pub fn score_at(&self, time: f32) -> u32 {
+        match self.samples.partition_point(|it| it.0 <= time) {
+            0 => 0,
+            idx => self.samples[idx - 1].1,
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PlayResult {
     pub score: u32,
     pub accuracy: f64,
@@ -1015,6 +1487,43 @@ pub struct PlayResult {
     pub early: u32,
     pub late: u32,
     pub std: f32,
+    /// Signed timing errors (in seconds) of every Perfect and Good judgement
+    pub hit_errors: Vec<f32>,
+    /// Maximum number of fingers on the screen at the same time
+    pub max_touches: u32,
+    /// Total number of touches during the play
+    pub total_touches: u32,
+}
+
+impl PlayResult {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+/// A lightweight snapshot of the judgement state while playing.
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveStats {
+    pub score: u32,
+    /// Accuracy over the notes judged so far
+    pub accuracy: f64,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub counts: [u32; 4],
+    pub num_of_notes: u32,
+}
+
+/// Current judgement state, see [`Judge::snapshot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JudgeSnapshot {
+    pub score: u32,
+    pub combo: u32,
+    pub max_combo: u32,
+    /// Accuracy over the notes judged so far
+    pub accuracy: f64,
+    pub counts: [u32; 4],
 }
 
 pub fn icon_index(score: u32, full_combo: bool) -> usize {
@@ -1029,3 +1538,122 @@ pub fn icon_index(score: u32, full_combo: bool) -> usize {
         (_, false) => 5,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: f32 = 1.;
+    const END: f32 = 3.;
+    const FRAME: f32 = 1. / 60.;
+
+    /// Plays a single hold from `START` to `END` released at `release`, returns whether it broke.
+    fn play_hold(config: &Config, release: f32) -> bool {
+        let tail = hold_tail(config);
+        let mut status = JudgeStatus::Hold(true, START, 0., false, f32::INFINITY);
+        let mut t = START;
+        while t < END + LIMIT_BAD {
+            if update_hold(&mut status, END, t, config.speed, tail, || t < release) {
+                assert!(matches!(status, JudgeStatus::Judged));
+                return true;
+            }
+            t += FRAME;
+        }
+        assert!(matches!(status, JudgeStatus::Hold(_, _, _, true, _)), "hold should be pre-judged at the end");
+        false
+    }
+
+    fn config(forgiving_holds: bool) -> Config {
+        Config {
+            forgiving_holds,
+            forgiving_hold_window: 0.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hold_released_at_end() {
+        assert!(!play_hold(&config(false), END));
+        assert!(!play_hold(&config(true), END));
+    }
+
+    #[test]
+    fn hold_released_just_before_end() {
+        let release = END - LIMIT_BAD * 0.5;
+        assert!(!play_hold(&config(false), release));
+        assert!(!play_hold(&config(true), release));
+    }
+
+    #[test]
+    fn hold_released_within_forgiving_window() {
+        let release = END - 0.4;
+        assert!(play_hold(&config(false), release));
+        assert!(!play_hold(&config(true), release));
+    }
+
+    #[test]
+    fn hold_released_well_before_end() {
+        let release = END - 1.;
+        assert!(play_hold(&config(false), release));
+        assert!(play_hold(&config(true), release));
+    }
+
+    fn chart() -> Chart {
+        // four notes and a fake one on a single line
+        let source = "0\nbp 0 120\nn1 0 1 0 1 0\nn2 0 2 4 512 1 0\nn3 0 5 0 1 0\nn4 0 6 -512 1 0\nn1 0 7 0 1 1\n";
+        crate::parse::parse_pec(source, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn snapshot_matches_result() {
+        let mut judge = Judge::new(&chart());
+        assert_eq!(judge.total_notes(), 4);
+        for (id, (what, diff)) in [(Judgement::Perfect, 0.01), (Judgement::Good, -0.1), (Judgement::Miss, 0.), (Judgement::Perfect, 0.)]
+            .into_iter()
+            .enumerate()
+        {
+            judge.commit(id as f32, what, 0, id as u32, diff);
+            let (snapshot, result) = (judge.snapshot(), judge.result());
+            assert_eq!(snapshot.score, result.score);
+            assert_eq!(snapshot.max_combo, result.max_combo);
+            assert_eq!(snapshot.counts, result.counts);
+            assert_eq!(snapshot.combo, judge.combo());
+        }
+        let (snapshot, result) = (judge.snapshot(), judge.result());
+        // the accuracy of the snapshot only covers judged notes, which are all of them by now
+        assert_eq!(snapshot.accuracy, result.accuracy);
+        assert_eq!((snapshot.combo, snapshot.max_combo), (1, 2));
+        assert_eq!(result.counts, [2, 1, 0, 1]);
+        assert_eq!(result.hit_errors, [0.01, -0.1, 0.]);
+
+        judge.reset();
+        let (snapshot, result) = (judge.snapshot(), judge.result());
+        assert_eq!((snapshot.score, snapshot.max_combo, snapshot.counts), (0, 0, [0; 4]));
+        assert_eq!((result.max_combo, result.num_of_notes), (0, 4));
+        assert!(result.hit_errors.is_empty());
+    }
+
+    #[test]
+    fn short_release_is_tolerated() {
+        let tail = hold_tail(&config(false));
+        let mut status = JudgeStatus::Hold(true, START, 0., false, f32::INFINITY);
+        assert!(!update_hold(&mut status, END, 2., 1., tail, || false));
+        assert!(!update_hold(&mut status, END, 2. + UP_TOLERANCE * 0.5, 1., tail, || false));
+        assert!(!update_hold(&mut status, END, 2. + UP_TOLERANCE * 0.8, 1., tail, || true));
+        assert!(!update_hold(&mut status, END, 2. + UP_TOLERANCE * 2., 1., tail, || true));
+        assert!(matches!(status, JudgeStatus::Hold(_, _, _, false, up) if up.is_infinite()));
+    }
+
+    #[test]
+    fn replay_rewinds_on_seek() {
+        let mut replay = ReplayData::default();
+        for (time, score) in [(1., 100), (2., 200), (3., 300)] {
+            replay.push(time, score);
+        }
+        replay.push(1.5, 100);
+        replay.push(2.5, 150);
+        assert_eq!(replay.samples, [(1., 100), (2.5, 150)]);
+        assert_eq!(replay.score_at(2.), 100);
+        assert_eq!(replay.score_at(3.), 150);
+    }
+}
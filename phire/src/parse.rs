@@ -1,6 +1,12 @@
+mod expr;
+pub use expr::parse_expr_line;
+
 mod extra;
 pub use extra::parse_extra;
 
+mod osu;
+pub use osu::{is_osu, parse_osu, OSU_HEADER};
+
 mod pec;
 pub use pec::parse_pec;
 
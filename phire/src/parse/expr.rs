@@ -0,0 +1,468 @@
+//! A small expression language driving line motion, e.g. `sin(t * 2) * 100`.
+//!
+//! Only arithmetic, the variable `t` (seconds since the motion starts), the constants `pi` and `e` and a fixed set of
+//! math functions are available, so expressions can't do anything but compute a number.
+
+crate::tl_file!("parser" ptl);
+
+use crate::core::{AnimFloat, Keyframe};
+use anyhow::Result;
+use std::ops::Range;
+
+/// Deeper nesting is rejected instead of risking a stack overflow.
+const MAX_DEPTH: usize = 64;
+/// Longer expressions are rejected, as flat chains like `1+1+…` still nest one level per operator when evaluated.
+const MAX_NODES: usize = 1024;
+/// At most this many keyframes are sampled for one expression, the step is widened beyond it.
+const MAX_SAMPLES: usize = 100_000;
+
+#[derive(Clone, Copy)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Floor,
+    Ceil,
+    Round,
+    Sign,
+    Min,
+    Max,
+    Pow,
+    Clamp,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        use Func::*;
+        Some(match name {
+            "sin" => Sin,
+            "cos" => Cos,
+            "tan" => Tan,
+            "asin" => Asin,
+            "acos" => Acos,
+            "atan" => Atan,
+            "atan2" => Atan2,
+            "abs" => Abs,
+            "sqrt" => Sqrt,
+            "exp" => Exp,
+            "ln" => Ln,
+            "floor" => Floor,
+            "ceil" => Ceil,
+            "round" => Round,
+            "sign" => Sign,
+            "min" => Min,
+            "max" => Max,
+            "pow" => Pow,
+            "clamp" => Clamp,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Atan2 | Func::Min | Func::Max | Func::Pow => 2,
+            Func::Clamp => 3,
+            _ => 1,
+        }
+    }
+
+    fn call(self, args: &[f32]) -> f32 {
+        let x = args[0];
+        match self {
+            Func::Sin => x.sin(),
+            Func::Cos => x.cos(),
+            Func::Tan => x.tan(),
+            Func::Asin => x.asin(),
+            Func::Acos => x.acos(),
+            Func::Atan => x.atan(),
+            Func::Atan2 => x.atan2(args[1]),
+            Func::Abs => x.abs(),
+            Func::Sqrt => x.sqrt(),
+            Func::Exp => x.exp(),
+            Func::Ln => x.ln(),
+            Func::Floor => x.floor(),
+            Func::Ceil => x.ceil(),
+            Func::Round => x.round(),
+            Func::Sign => {
+                if x == 0. {
+                    0.
+                } else {
+                    x.signum()
+                }
+            }
+            Func::Min => x.min(args[1]),
+            Func::Max => x.max(args[1]),
+            Func::Pow => x.powf(args[1]),
+            Func::Clamp => x.max(args[1]).min(args[2]),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+enum Expr {
+    Num(f32),
+    Time,
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, t: f32) -> f32 {
+        match self {
+            Expr::Num(value) => *value,
+            Expr::Time => t,
+            Expr::Neg(expr) => -expr.eval(t),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(t), rhs.eval(t));
+                match op {
+                    Op::Add => lhs + rhs,
+                    Op::Sub => lhs - rhs,
+                    Op::Mul => lhs * rhs,
+                    Op::Div => lhs / rhs,
+                    Op::Rem => lhs % rhs,
+                    Op::Pow => lhs.powf(rhs),
+                }
+            }
+            Expr::Call(func, args) => {
+                let mut values = [0.; 3];
+                for (value, arg) in values.iter_mut().zip(args) {
+                    *value = arg.eval(t);
+                }
+                func.call(&values[..args.len()])
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    depth: usize,
+    nodes: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.source[self.pos..].trim_start().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.source.len() - self.source[self.pos..].trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.skip_whitespace();
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn unexpected(&mut self) -> anyhow::Error {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) => ptl!(err "expr-unexpected-char", "char" => c.to_string(), "pos" => self.pos + 1),
+            None => ptl!(err "expr-unexpected-end"),
+        }
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ptl!(err "expr-too-deep"));
+        }
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Counts `expr` against [`MAX_NODES`].
+    fn node(&mut self, expr: Expr) -> Result<Expr> {
+        self.nodes += 1;
+        if self.nodes > MAX_NODES {
+            return Err(ptl!(err "expr-too-long"));
+        }
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.nested(|p| {
+            let mut lhs = p.term()?;
+            loop {
+                let op = if p.eat('+') {
+                    Op::Add
+                } else if p.eat('-') {
+                    Op::Sub
+                } else {
+                    break Ok(lhs);
+                };
+                let rhs = p.term()?;
+                lhs = p.node(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))?;
+            }
+        })
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else if self.eat('%') {
+                Op::Rem
+            } else {
+                break Ok(lhs);
+            };
+            let rhs = self.unary()?;
+            lhs = self.node(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))?;
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        self.nested(|p| {
+            if p.eat('-') {
+                let inner = p.unary()?;
+                p.node(Expr::Neg(Box::new(inner)))
+            } else if p.eat('+') {
+                p.unary()
+            } else {
+                let base = p.atom()?;
+                // right associative, and binds tighter than a leading minus: -2^2 = -4
+                if p.eat('^') {
+                    let exp = p.unary()?;
+                    p.node(Expr::Binary(Op::Pow, Box::new(base), Box::new(exp)))
+                } else {
+                    Ok(base)
+                }
+            }
+        })
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        self.skip_whitespace();
+        let rest = &self.source[self.pos..];
+        let Some(c) = rest.chars().next() else {
+            return Err(self.unexpected());
+        };
+        if c == '(' {
+            self.pos += 1;
+            let expr = self.expr()?;
+            self.expect(')')?;
+            return Ok(expr);
+        }
+        if c.is_ascii_digit() || c == '.' {
+            let len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+            let value = rest[..len]
+                .parse::<f32>()
+                .map_err(|_| ptl!(err "expr-invalid-number", "number" => rest[..len].to_owned()))?;
+            self.pos += len;
+            return self.node(Expr::Num(value));
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let name = &rest[..len];
+            self.pos += len;
+            return match name {
+                "t" => self.node(Expr::Time),
+                "pi" => self.node(Expr::Num(std::f32::consts::PI)),
+                "e" => self.node(Expr::Num(std::f32::consts::E)),
+                _ => {
+                    let func = Func::from_name(name).ok_or_else(|| ptl!(err "expr-unknown-name", "name" => name.to_owned()))?;
+                    self.expect('(')?;
+                    let mut args = vec![self.expr()?];
+                    while self.eat(',') {
+                        args.push(self.expr()?);
+                    }
+                    self.expect(')')?;
+                    if args.len() != func.arity() {
+                        return Err(ptl!(err "expr-wrong-arity", "name" => name.to_owned(), "expected" => func.arity(), "got" => args.len()));
+                    }
+                    self.node(Expr::Call(func, args))
+                }
+            };
+        }
+        Err(self.unexpected())
+    }
+}
+
+fn parse_expr(source: &str) -> Result<Expr> {
+    let mut parser = Parser { source, pos: 0, depth: 0, nodes: 0 };
+    let expr = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected());
+    }
+    Ok(expr)
+}
+
+/// Samples the expression every `step` seconds over `range` into linear keyframes, `t` counting from the start of
+/// `range`. The value at either end holds outside of it.
+pub fn parse_expr_line(source: &str, range: Range<f32>, step: f32) -> Result<AnimFloat> {
+    let expr = parse_expr(source)?;
+    let duration = (range.end - range.start).max(0.);
+    let step = step.max(duration / MAX_SAMPLES as f32).max(1e-3);
+    let count = (duration / step).ceil() as usize;
+    let mut kfs = Vec::with_capacity(count + 1);
+    for i in 0..=count {
+        let t = (i as f32 * step).min(duration);
+        let value = expr.eval(t);
+        if !value.is_finite() {
+            return Err(ptl!(err "expr-not-finite", "time" => format!("{t:.3}")));
+        }
+        kfs.push(Keyframe::new(range.start + t, value, if i == count { 0 } else { 2 }));
+    }
+    Ok(AnimFloat::new(kfs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> f32 {
+        parse_expr(source).unwrap().eval(1.5)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_close(eval("1 + 2 * 3"), 7.);
+        assert_close(eval("(1 + 2) * 3"), 9.);
+        assert_close(eval("10 - 4 - 3"), 3.);
+        assert_close(eval("12 / 3 / 2"), 2.);
+        assert_close(eval("7 % 4 * 2"), 6.);
+        assert_close(eval("-2^2"), -4.);
+        assert_close(eval("(-2)^2"), 4.);
+        assert_close(eval("2^3^2"), 512.);
+        assert_close(eval("2 * -t"), -3.);
+        assert_close(eval(" t*2+ pi - e "), 3. + std::f32::consts::PI - std::f32::consts::E);
+    }
+
+    #[test]
+    fn functions() {
+        for (source, expected) in [
+            ("sin(pi / 2)", 1.),
+            ("cos(0)", 1.),
+            ("tan(0)", 0.),
+            ("asin(1)", std::f32::consts::FRAC_PI_2),
+            ("acos(1)", 0.),
+            ("atan(1)", std::f32::consts::FRAC_PI_4),
+            ("atan2(1, 1)", std::f32::consts::FRAC_PI_4),
+            ("abs(-3)", 3.),
+            ("sqrt(16)", 4.),
+            ("exp(0)", 1.),
+            ("ln(e)", 1.),
+            ("floor(1.7)", 1.),
+            ("ceil(1.2)", 2.),
+            ("round(1.5)", 2.),
+            ("sign(-4)", -1.),
+            ("sign(0)", 0.),
+            ("min(2, 3)", 2.),
+            ("max(2, 3)", 3.),
+            ("pow(2, 10)", 1024.),
+            ("clamp(5, 0, 3)", 3.),
+            ("clamp(-1, 0, 3)", 0.),
+        ] {
+            assert_close(eval(source), expected);
+        }
+    }
+
+    #[test]
+    fn wrong_arity() {
+        assert!(parse_expr("min(1)").is_err());
+        assert!(parse_expr("sin(1, 2)").is_err());
+        assert!(parse_expr("clamp(1, 2)").is_err());
+    }
+
+    #[test]
+    fn unknown_names() {
+        assert!(parse_expr("x").is_err());
+        assert!(parse_expr("foo(1)").is_err());
+        assert!(parse_expr("sin").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(parse_expr("").is_err());
+        assert!(parse_expr("1 +").is_err());
+        assert!(parse_expr("(1").is_err());
+        assert!(parse_expr("1 2").is_err());
+        assert!(parse_expr("1..2").is_err());
+    }
+
+    #[test]
+    fn max_depth() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse_expr(&nested(MAX_DEPTH / 2 - 1)).is_ok());
+        assert!(parse_expr(&nested(MAX_DEPTH)).is_err());
+        assert!(parse_expr(&format!("{}1", "-".repeat(MAX_DEPTH / 2))).is_ok());
+        assert!(parse_expr(&format!("{}1", "-".repeat(MAX_DEPTH))).is_err());
+    }
+
+    #[test]
+    fn max_nodes() {
+        let chain = |terms: usize| vec!["1"; terms].join("+");
+        assert_eq!(parse_expr(&chain(MAX_NODES / 2)).unwrap().eval(0.), (MAX_NODES / 2) as f32);
+        assert!(parse_expr(&chain(MAX_NODES)).is_err());
+        // would overflow the stack when evaluated or dropped if it were accepted
+        assert!(parse_expr(&chain(300_000)).is_err());
+        assert!(parse_expr(&vec!["t"; 300_000].join("*")).is_err());
+    }
+
+    #[test]
+    fn non_finite() {
+        assert!(parse_expr_line("1 / t", 0.0..1.0, 0.1).is_err());
+        assert!(parse_expr_line("ln(t - 2)", 0.0..1.0, 0.1).is_err());
+        assert!(parse_expr_line("1 / (t + 1)", 0.0..1.0, 0.1).is_ok());
+    }
+
+    #[test]
+    fn keyframes() {
+        let anim = parse_expr_line("t * 4", 2.0..3.0, 0.25).unwrap();
+        let times: Vec<f32> = anim.keyframes.iter().map(|it| it.time).collect();
+        assert_eq!(times, [2.0, 2.25, 2.5, 2.75, 3.0]);
+        assert_close(anim.at(2.5), 2.);
+        assert_close(anim.at(3.0), 4.);
+        assert_close(anim.at(10.0), 4.);
+        assert_close(anim.at(0.0), 0.);
+
+        // the last sample is clamped to the end of the range
+        let anim = parse_expr_line("t", 2.0..3.0, 0.3).unwrap();
+        assert_eq!(anim.keyframes.len(), 5);
+        assert_close(anim.keyframes.last().unwrap().time, 3.0);
+        assert_close(anim.at(3.0), 1.);
+
+        let anim = parse_expr_line("1", 1.0..1.0, 0.1).unwrap();
+        assert_eq!(anim.keyframes.len(), 1);
+    }
+}
@@ -1,9 +1,9 @@
 crate::tl_file!("parser" ptl);
 
-use super::RPE_TWEEN_MAP;
+use super::{parse_expr_line, RPE_HEIGHT, RPE_TWEEN_MAP, RPE_WIDTH};
 use crate::{
-    core::{Anim, BpmList, ChartExtra, ClampedTween, Effect, Keyframe, StaticTween, Triple, Tweenable, Uniform, Video, EPS},
-    ext::ScaleType,
+    core::{Anim, AnimFloat, BpmList, ChartExtra, ClampedTween, Effect, Keyframe, LineMotion, StaticTween, Triple, Tweenable, Uniform, Video, EPS},
+    ext::{SafeTexture, ScaleType},
     fs::FileSystem,
 };
 use anyhow::{Context, Result};
@@ -20,6 +20,10 @@ fn f32_one() -> f32 {
     1.
 }
 
+fn default_motion_step() -> f32 {
+    1. / 60.
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ExtKeyframe<T> {
@@ -118,7 +122,11 @@ struct ExtEffect {
     #[serde(default)]
     vars: HashMap<String, Variable>,
     #[serde(default)]
+    textures: HashMap<String, String>,
+    #[serde(default)]
     global: bool,
+    #[serde(default, rename = "zIndex")]
+    z_index: Option<i32>,
 }
 
 #[derive(Deserialize)]
@@ -134,6 +142,26 @@ struct ExtVideo {
     dim: ExtAnim<f32>,
 }
 
+/// Line motion given by expressions of `t`, the seconds since `start`. `x` and `y` are in RPE units, `rotation` in
+/// degrees (clockwise) and `alpha` from 0 to 255, all added to the line's own motion.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtLineMotion {
+    line: usize,
+    start: Triple,
+    end: Triple,
+    #[serde(default = "default_motion_step")]
+    step: f32,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+    #[serde(default)]
+    rotation: Option<String>,
+    #[serde(default)]
+    alpha: Option<String>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Extra {
@@ -142,6 +170,29 @@ struct Extra {
     effects: Vec<ExtEffect>,
     #[serde(default)]
     videos: Vec<ExtVideo>,
+    #[serde(default)]
+    line_motions: Vec<ExtLineMotion>,
+}
+
+fn parse_line_motion(r: &mut BpmList, motion: ExtLineMotion) -> Result<LineMotion> {
+    let range = r.time(&motion.start)..r.time(&motion.end);
+    let parse = |source: Option<String>, factor: f32, name: &str| -> Result<Option<AnimFloat>> {
+        source
+            .map(|source| {
+                let mut anim = parse_expr_line(&source, range.clone(), motion.step)
+                    .with_context(|| ptl!("expr-parse-failed", "property" => name.to_owned(), "expr" => source.clone()))?;
+                anim.map_value(|it| it * factor);
+                Ok(anim)
+            })
+            .transpose()
+    };
+    Ok(LineMotion {
+        line: motion.line,
+        x: parse(motion.x, 2. / RPE_WIDTH, "x")?,
+        y: parse(motion.y, 2. / RPE_HEIGHT, "y")?,
+        rotation: parse(motion.rotation, -1., "rotation")?,
+        alpha: parse(motion.alpha, 1. / 255., "alpha")?,
+    })
 }
 
 async fn parse_effect(r: &mut BpmList, rpe: ExtEffect, fs: &mut dyn FileSystem) -> Result<Effect> {
@@ -157,8 +208,17 @@ async fn parse_effect(r: &mut BpmList, rpe: ExtEffect, fs: &mut dyn FileSystem)
             })
         })
         .collect::<Result<_>>()?;
+    let mut textures = Vec::new();
+    for (name, path) in rpe.textures {
+        let texture = SafeTexture::from(image::load_from_memory(
+            &fs.load_file(&path)
+                .await
+                .with_context(|| ptl!("texture-load-failed", "path" => path.clone()))?,
+        )?);
+        textures.push((name, texture));
+    }
     let string;
-    Effect::new(
+    let mut effect = Effect::new(
         range,
         if let Some(path) = rpe.shader.strip_prefix('/') {
             if let Ok(file) = fs.load_file(path).await {
@@ -173,8 +233,11 @@ async fn parse_effect(r: &mut BpmList, rpe: ExtEffect, fs: &mut dyn FileSystem)
             Effect::get_preset(&rpe.shader).ok_or_else(|| ptl!(err "shader-not-found", "shader" => rpe.shader))?
         },
         vars,
+        textures,
         rpe.global,
-    )
+    )?;
+    effect.z_index = rpe.z_index;
+    Ok(effect)
 }
 
 pub async fn parse_extra(source: &str, fs: &mut dyn FileSystem) -> Result<ChartExtra> {
@@ -204,9 +267,16 @@ pub async fn parse_extra(source: &str, fs: &mut dyn FileSystem) -> Result<ChartE
             .with_context(|| ptl!("video-load-failed", "path" => video.path))?,
         );
     }
+    let line_motions = ext
+        .line_motions
+        .into_iter()
+        .enumerate()
+        .map(|(id, motion)| parse_line_motion(&mut r, motion).with_context(|| ptl!("line-motion-location", "id" => id)))
+        .collect::<Result<_>>()?;
     Ok(ChartExtra {
         effects,
         global_effects,
         videos,
+        line_motions,
     })
 }
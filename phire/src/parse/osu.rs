@@ -0,0 +1,304 @@
+crate::tl_file!("parser" ptl);
+
+use super::process_lines;
+use crate::{
+    core::{
+        AnimFloat, AnimVector, BpmList, Chart, ChartExtra, ChartSettings, JudgeLine, JudgeLineCache, JudgeLineKind, Keyframe, Note, NoteKind, Object,
+        HEIGHT_RATIO,
+    },
+    ext::NotNanExt,
+    judge::{HitSound, JudgeStatus},
+};
+use anyhow::{Context, Result};
+use std::{cell::RefCell, collections::HashMap};
+
+pub const OSU_HEADER: &str = "osu file format v";
+
+/// Whether `text` is an osu! beatmap, which may start with a byte order mark or blank lines.
+pub fn is_osu(text: &str) -> bool {
+    text.trim_start_matches('\u{feff}').trim_start().starts_with(OSU_HEADER)
+}
+
+/// The mode id of osu!mania in the `[General]` section.
+const MANIA_MODE: u32 = 3;
+/// Width of the playfield in osu! pixels, used to compute lanes from the hit object x coordinate.
+const PLAYFIELD_WIDTH: f32 = 512.;
+const HOLD_TYPE: u32 = 128;
+
+const LANE_WIDTH: f32 = 0.2;
+const LANES_SPAN: f32 = 1.6;
+const LINE_Y: f32 = -0.6;
+const SCROLL_SPEED: f32 = 2.;
+
+struct TimingPoint {
+    time: f32,
+    beat_length: f32,
+    uninherited: bool,
+}
+
+struct HitObject {
+    lane: usize,
+    time: f32,
+    end_time: Option<f32>,
+}
+
+#[derive(Default)]
+struct OsuChart {
+    mode: u32,
+    keys: usize,
+    timing_points: Vec<TimingPoint>,
+    hit_objects: Vec<HitObject>,
+}
+
+fn parse_f32(s: &str) -> Result<f32> {
+    s.trim().parse::<f32>().with_context(|| ptl!("expected-f32"))
+}
+
+fn parse_timing_point(line: &str) -> Result<TimingPoint> {
+    let parts: Vec<_> = line.split(',').collect();
+    if parts.len() < 2 {
+        ptl!(bail "unexpected-eol");
+    }
+    Ok(TimingPoint {
+        time: parse_f32(parts[0])? / 1000.,
+        beat_length: parse_f32(parts[1])?,
+        // Files before v6 have no `uninherited` column, every point is a red line there
+        uninherited: parts.get(6).map_or(true, |it| it.trim() != "0"),
+    })
+}
+
+fn parse_hit_object(line: &str, keys: usize) -> Result<HitObject> {
+    let parts: Vec<_> = line.split(',').collect();
+    if parts.len() < 4 {
+        ptl!(bail "unexpected-eol");
+    }
+    let x = parse_f32(parts[0])?;
+    let time = parse_f32(parts[2])? / 1000.;
+    let kind = parts[3].trim().parse::<u32>().with_context(|| ptl!("expected-usize"))?;
+    let end_time = if kind & HOLD_TYPE != 0 {
+        let params = parts.get(5).ok_or_else(|| ptl!(err "unexpected-eol"))?;
+        Some(parse_f32(params.split(':').next().unwrap())? / 1000.)
+    } else {
+        None
+    };
+    let lane = ((x * keys as f32 / PLAYFIELD_WIDTH).floor().max(0.) as usize).min(keys - 1);
+    Ok(HitObject { lane, time, end_time })
+}
+
+fn parse_source(source: &str) -> Result<OsuChart> {
+    let mut chart = OsuChart {
+        keys: 4,
+        ..Default::default()
+    };
+    let mut section = "";
+    for (id, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = &line[1..line.len() - 1];
+            continue;
+        }
+        (|| -> Result<()> {
+            match section {
+                "General" | "Difficulty" => {
+                    let Some((key, value)) = line.split_once(':') else {
+                        return Ok(());
+                    };
+                    match key.trim() {
+                        "Mode" => chart.mode = value.trim().parse().with_context(|| ptl!("expected-usize"))?,
+                        "CircleSize" => chart.keys = (parse_f32(value)?.round() as usize).max(1),
+                        _ => {}
+                    }
+                }
+                "TimingPoints" => chart.timing_points.push(parse_timing_point(line)?),
+                "HitObjects" => chart.hit_objects.push(parse_hit_object(line, chart.keys)?),
+                _ => {}
+            }
+            Ok(())
+        })()
+        .with_context(|| ptl!("line-location", "lid" => id + 1))?;
+    }
+    Ok(chart)
+}
+
+fn parse_bpm_list(points: &[TimingPoint]) -> Result<BpmList> {
+    let mut red = points.iter().filter(|it| it.uninherited && it.beat_length > 0.);
+    let Some(first) = red.next() else {
+        ptl!(bail "osu-no-timing-points");
+    };
+    // BpmList starts counting beats at time 0, so the first red line is extended backwards
+    let mut ranges = vec![(0., 60000. / first.beat_length)];
+    let (mut beats, mut last_time, mut last_length) = (first.time * 1000. / first.beat_length, first.time, first.beat_length);
+    for point in red {
+        beats += (point.time - last_time) * 1000. / last_length;
+        ranges.push((beats, 60000. / point.beat_length));
+        last_time = point.time;
+        last_length = point.beat_length;
+    }
+    Ok(BpmList::new(ranges))
+}
+
+fn parse_height(points: &[TimingPoint], max_time: f32) -> AnimFloat {
+    let mut kfs = vec![Keyframe::new(0., 0., 2)];
+    let mut height = 0.;
+    let mut last_time = 0.;
+    let mut last_speed = 1.;
+    for point in points {
+        // Green lines scale the scroll velocity, red lines reset it
+        let speed = if point.uninherited {
+            1.
+        } else if point.beat_length < 0. {
+            (-100. / point.beat_length).clamp(0.1, 10.)
+        } else {
+            last_speed
+        };
+        let time = point.time.max(last_time);
+        height += (time - last_time) * last_speed * SCROLL_SPEED / HEIGHT_RATIO;
+        kfs.push(Keyframe::new(time, height, 2));
+        last_time = time;
+        last_speed = speed;
+    }
+    kfs.push(Keyframe::new(max_time, height + (max_time - last_time).max(0.) * last_speed * SCROLL_SPEED / HEIGHT_RATIO, 0));
+    AnimFloat::new(kfs)
+}
+
+pub fn parse_osu(source: &str, extra: ChartExtra) -> Result<Chart> {
+    let mut osu = parse_source(source)?;
+    if osu.mode != MANIA_MODE {
+        ptl!(bail "osu-unsupported-mode", "mode" => osu.mode);
+    }
+    osu.timing_points.sort_by_key(|it| (it.time.not_nan(), !it.uninherited));
+    let bpm_list = parse_bpm_list(&osu.timing_points)?;
+    let max_time = *osu
+        .hit_objects
+        .iter()
+        .map(|it| it.end_time.unwrap_or(it.time).not_nan())
+        .max()
+        .unwrap_or_default()
+        + 1.;
+    let mut height = parse_height(&osu.timing_points, max_time);
+
+    let lane_width = LANE_WIDTH.min(LANES_SPAN / osu.keys as f32);
+    let center = (osu.keys - 1) as f32 / 2.;
+    let mut notes = osu
+        .hit_objects
+        .into_iter()
+        .map(|it| {
+            height.set_time(it.time);
+            let note_height = height.now();
+            let kind = match it.end_time {
+                Some(end_time) if end_time > it.time => {
+                    height.set_time(end_time);
+                    NoteKind::Hold {
+                        end_time,
+                        end_height: height.now(),
+                        end_speed: 1.,
+                    }
+                }
+                _ => NoteKind::Click,
+            };
+            Note {
+                object: Object {
+                    translation: AnimVector(AnimFloat::fixed((it.lane as f32 - center) * lane_width), AnimFloat::default()),
+                    ..Default::default()
+                },
+                hitsound: HitSound::default_from_kind(&kind),
+                kind,
+                time: it.time,
+                height: note_height,
+                speed: 1.,
+
+                above: true,
+                multiple_hint: false,
+                fake: false,
+                judge: JudgeStatus::NotJudged,
+                attr: false,
+            }
+        })
+        .collect::<Vec<_>>();
+    let cache = JudgeLineCache::new(&mut notes);
+    let mut lines = vec![JudgeLine {
+        object: Object {
+            alpha: AnimFloat::fixed(1.),
+            translation: AnimVector(AnimFloat::default(), AnimFloat::fixed(LINE_Y)),
+            ..Default::default()
+        },
+        ctrl_obj: RefCell::default(),
+        kind: JudgeLineKind::Normal,
+        height,
+        incline: AnimFloat::default(),
+        notes,
+        color: Default::default(),
+        parent: None,
+        anchor: [0.5, 0.5],
+        z_index: 0,
+        show_below: false,
+        attach_ui: None,
+
+        cache,
+    }];
+    process_lines(&mut lines);
+    Ok(Chart::new(0., lines, bpm_list, ChartSettings::default(), extra, HashMap::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "\u{feff}osu file format v14
+
+[General]
+Mode: 3
+
+[Difficulty]
+CircleSize:4
+
+[TimingPoints]
+1000,500,4,2,0,100,1,0
+2000,250,4,2,0,100,1,0
+2500,-50,4,2,0,100,0,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+448,192,2000,128,0,3000:0:0:0:0:
+";
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn detects_header() {
+        assert!(is_osu(FIXTURE));
+        assert!(is_osu("\r\n  osu file format v7"));
+        assert!(!is_osu("{\"META\": {}}"));
+    }
+
+    #[test]
+    fn parses_mania() {
+        let chart = parse_osu(FIXTURE, ChartExtra::default()).unwrap();
+        assert_eq!(chart.lines.len(), 1);
+        let notes = &chart.lines[0].notes;
+        assert_eq!(notes.len(), 2);
+        assert!(matches!(notes[0].kind, NoteKind::Click));
+        assert_close(notes[0].time, 1.);
+        assert_close(notes[0].object.translation.0.now(), -0.3);
+        let NoteKind::Hold { end_time, .. } = notes[1].kind else {
+            panic!("expected a hold");
+        };
+        assert_close(notes[1].time, 2.);
+        assert_close(end_time, 3.);
+        assert_close(notes[1].object.translation.0.now(), 0.3);
+
+        let mut bpm_list = chart.bpm_list.borrow_mut();
+        assert_close(bpm_list.now_bpm(1.5), 120.);
+        assert_close(bpm_list.now_bpm(2.5), 240.);
+    }
+
+    #[test]
+    fn rejects_other_modes() {
+        assert!(parse_osu(&FIXTURE.replace("Mode: 3", "Mode: 0"), ChartExtra::default()).is_err());
+    }
+}
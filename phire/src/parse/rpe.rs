@@ -16,8 +16,14 @@ use image::{codecs::gif, AnimationDecoder, DynamicImage, ImageError};
 use macroquad::prelude::{Color, WHITE};
 use sasa::AudioClip;
 use serde::Deserialize;
-use std::{cell::RefCell, collections::HashMap, future::IntoFuture, rc::Rc, str::FromStr, time::Duration};
-use tracing::debug;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    future::IntoFuture,
+    rc::Rc,
+    time::Duration,
+};
+use tracing::{debug, warn};
 
 pub const RPE_WIDTH: f32 = 1350.;
 pub const RPE_HEIGHT: f32 = 900.;
@@ -117,7 +123,7 @@ struct RPENote {
     position_x: f32,
     y_offset: f32,
     alpha: u16,               // some alpha has 256...
-    hitsound: Option<String>, // TODO implement this feature
+    hitsound: Option<String>,
     size: f32,
     speed: f32,
     is_fake: u8,
@@ -325,6 +331,7 @@ async fn parse_notes(
     fs: &mut dyn FileSystem,
     height: &mut AnimFloat,
     hitsounds: &mut HitSoundMap,
+    failed_hitsounds: &mut HashSet<String>,
 ) -> Result<Vec<Note>> {
     let mut notes = Vec::new();
     for note in rpe {
@@ -356,16 +363,28 @@ async fn parse_notes(
                     HitSound::Click
                 } else if s == "drag.mp3" {
                     HitSound::Drag
-                } else {
-                    if hitsounds.get(&s).is_none() {
-                        let data = fs.load_file(&s).await;
-                        if let Ok(data) = data {
-                            hitsounds.insert(s.clone(), AudioClip::new(data)?);
-                        } else {
-                            ptl!(bail "hitsound-missing", "name" => s);
+                } else if hitsounds.contains_key(&s) {
+                    HitSound::Custom(s)
+                } else if failed_hitsounds.contains(&s) {
+                    // already warned about, don't load it again for every note using it
+                    HitSound::default_from_kind(&kind)
+                } else if let Ok(data) = fs.load_file(&s).await {
+                    match AudioClip::new(data) {
+                        Ok(clip) => {
+                            hitsounds.insert(s.clone(), clip);
+                            HitSound::Custom(s)
+                        }
+                        Err(err) => {
+                            warn!("failed to decode hitsound {s}: {err:?}");
+                            failed_hitsounds.insert(s);
+                            HitSound::default_from_kind(&kind)
                         }
                     }
-                    HitSound::Custom(String::from_str(&s)?)
+                } else {
+                    // fall back to the default sound instead of refusing the whole chart
+                    warn!("{}", ptl!("hitsound-missing", "name" => s));
+                    failed_hitsounds.insert(s);
+                    HitSound::default_from_kind(&kind)
                 }
             }
             None => HitSound::default_from_kind(&kind),
@@ -429,6 +448,7 @@ async fn parse_judge_line(
     fs: &mut dyn FileSystem,
     bezier_map: &BezierMap,
     hitsounds: &mut HitSoundMap,
+    failed_hitsounds: &mut HashSet<String>,
 ) -> Result<JudgeLine> {
     let mut line_texture_map: HashMap<String, SafeTexture> = Default::default();
     let event_layers: Vec<_> = rpe.event_layers.into_iter().flatten().collect();
@@ -452,7 +472,7 @@ async fn parse_judge_line(
         Ok(res)
     }
     let mut height = parse_speed_events(r, &event_layers, max_time)?;
-    let mut notes = parse_notes(r, rpe.notes.unwrap_or_default(), fs, &mut height, hitsounds).await?;
+    let mut notes = parse_notes(r, rpe.notes.unwrap_or_default(), fs, &mut height, hitsounds, failed_hitsounds).await?;
     let cache = JudgeLineCache::new(&mut notes);
     Ok(JudgeLine {
         object: Object {
@@ -647,6 +667,7 @@ pub async fn parse_rpe(source: &str, fs: &mut dyn FileSystem, extra: ChartExtra)
         v.iter().flat_map(|it| it.iter())
     }
     let mut hitsounds = HashMap::new();
+    let mut failed_hitsounds = HashSet::new();
     #[rustfmt::skip]
     let max_time = *rpe
         .judge_line_list
@@ -683,7 +704,7 @@ pub async fn parse_rpe(source: &str, fs: &mut dyn FileSystem, extra: ChartExtra)
     for (id, line) in rpe.judge_line_list.into_iter().enumerate() {
         let name = line.name.clone();
         lines.push(
-            parse_judge_line(bpm_list.clone(), line, max_time, fs, &bezier_map, &mut hitsounds)
+            parse_judge_line(bpm_list.clone(), line, max_time, fs, &bezier_map, &mut hitsounds, &mut failed_hitsounds)
                 .await
                 .with_context(move || ptl!("judge-line-location-name", "jlid" => id, "name" => name))?,
         );
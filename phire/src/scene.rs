@@ -1,15 +1,16 @@
 crate::tl_file!("scene" ttl);
 
 mod ending;
-pub use ending::{EndingScene, RecordUpdateState};
+pub use ending::{set_result_callback, EndingScene, RecordUpdateState, ResultFn};
 
 pub mod game;
-pub use game::{GameMode, GameScene, SimpleRecord};
+pub use game::{set_preset_callback, set_stats_callback, GameMode, GameScene, PresetFn, ResumePoint, SimpleRecord, StatsFn};
 
 mod loading;
-pub use loading::{BasicPlayer, LoadingScene, UpdateFn, UploadFn};
+pub use loading::{BasicPlayer, LoadingScene, ProgressFn, UpdateFn, UploadFn};
 
 use crate::{
+    config::BackgroundMode,
     ext::{draw_image, screen_aspect, LocalTask, SafeTexture, ScaleType},
     judge::Judge,
     time::TimeManager,
@@ -18,7 +19,14 @@ use crate::{
 use anyhow::{Error, Result};
 use cfg_if::cfg_if;
 use macroquad::prelude::*;
-use std::{any::Any, cell::RefCell, sync::Mutex};
+use std::{
+    any::Any,
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 use tracing::warn;
 
 #[derive(Default)]
@@ -39,6 +47,19 @@ thread_local! {
     pub static DIALOG: RefCell<Option<Dialog>> = RefCell::new(None);
 }
 
+static WINDOW_FOCUSED: AtomicBool = AtomicBool::new(true);
+
+/// Whether the window has focus. It's lost when the frontend reports a blur through [`set_window_focused`] (phire-ui
+/// does so from its pause/resume hook) or when the window is minimized.
+pub fn window_focused() -> bool {
+    WINDOW_FOCUSED.load(Ordering::Relaxed)
+}
+
+/// Reports a focus change of the window, meant to be called from the frontend's window or lifecycle callbacks.
+pub fn set_window_focused(focused: bool) {
+    WINDOW_FOCUSED.store(focused, Ordering::Relaxed);
+}
+
 #[inline]
 pub fn show_error(error: Error) {
     warn!("show error: {error:?}");
@@ -535,10 +556,38 @@ impl Main {
     }
 }
 
-fn draw_background(tex: Texture2D, dim: bool) {
+fn draw_background(tex: Texture2D, mode: BackgroundMode, dim: bool) {
+    draw_background_shifted(tex, mode, dim, Vec2::ZERO, 0.);
+}
+
+/// Draws the background with the illustration enlarged by `margin` on every side and moved by `offset`, which
+/// shouldn't exceed `margin` so that no edge shows up.
+fn draw_background_shifted(tex: Texture2D, mode: BackgroundMode, dim: bool, offset: Vec2, margin: f32) {
     let asp = screen_aspect();
     let top = 1. / asp;
-    draw_image(tex, Rect::new(-1., -top, 2., top * 2.), ScaleType::CropCenter);
+    match mode {
+        BackgroundMode::Illustration => draw_image(
+            tex,
+            Rect::new(-1. - margin + offset.x, -top - margin + offset.y, 2. + margin * 2., top * 2. + margin * 2.),
+            ScaleType::CropCenter,
+        ),
+        BackgroundMode::Solid(color) => draw_rectangle(-1., -top, 2., top * 2., Color::from_hex(color)),
+        BackgroundMode::Gradient(from, to) => {
+            let gl = unsafe { get_internal_gl() }.quad_gl;
+            let (from, to) = (Color::from_hex(from), Color::from_hex(to));
+            gl.texture(None);
+            gl.draw_mode(DrawMode::Triangles);
+            gl.geometry(
+                &[
+                    Vertex::new(-1., -top, 0., 0., 0., from),
+                    Vertex::new(1., -top, 0., 0., 0., from),
+                    Vertex::new(1., top, 0., 0., 0., to),
+                    Vertex::new(-1., top, 0., 0., 0., to),
+                ],
+                &[0, 1, 2, 0, 2, 3],
+            );
+        }
+    }
     if dim {
         draw_rectangle(-1., -top, 2., top * 2., Color::new(0., 0., 0., 0.5));
     }
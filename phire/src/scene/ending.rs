@@ -4,21 +4,34 @@ use super::{draw_background, game::{SimpleRecord, GameScene}, loading::UploadFn,
 use crate::{
     config::Config,
     ext::{
-        create_audio_manger, draw_illustration, draw_parallelogram, draw_parallelogram_ex, draw_text_aligned, draw_text_aligned_fix, SafeTexture, ScaleType,
+        create_audio_manger, draw_illustration, draw_long_number, draw_parallelogram, draw_parallelogram_ex, draw_text_aligned, draw_text_aligned_fix, level_color_with, SafeTexture, ScaleType,
         PARALLELOGRAM_SLOPE,
     },
     info::ChartInfo,
     judge::{icon_index, PlayResult},
-    scene::show_message,
+    scene::{show_message, DIALOG},
     task::Task,
     time::TimeManager,
     ui::{Dialog, MessageHandle, RectButton, Ui},
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
 use macroquad::prelude::*;
 use sasa::{AudioClip, AudioManager, Music, MusicParams};
 use serde::Deserialize;
 use std::{cell::RefCell, ops::DerefMut};
+use tracing::warn;
+
+pub type ResultFn = Box<dyn FnMut(serde_json::Value)>;
+
+thread_local! {
+    static RESULT_FN: RefCell<Option<ResultFn>> = RefCell::default();
+}
+
+/// Registers a callback receiving the result of every finished run as JSON.
+pub fn set_result_callback(f: Option<ResultFn>) {
+    RESULT_FN.with(|it| *it.borrow_mut() = f);
+}
 
 #[derive(Deserialize)]
 pub struct RecordUpdateState {
@@ -53,16 +66,23 @@ pub struct EndingScene {
 
     upload_fn: Option<UploadFn>,
     upload_task: Option<(Task<Result<RecordUpdateState>>, MessageHandle)>,
+    upload_attempt: u32,
+    upload_retry_at: Option<f64>,
+    auto_proceed_since: Option<f64>, // real time at which the upload was over
     record_data: Option<Vec<u8>>,
     record: Option<SimpleRecord>,
 
     btn_retry: RectButton,
     btn_proceed: RectButton,
     config: Config,
+    share_card: bool,
 }
 
 impl EndingScene {
     pub const BPM_WAIT_TIME: f64 = 0.24;
+    pub const SHARE_CARD_SIZE: (u32, u32) = (1920, 1080);
+    /// Time after which every animation on the result screen has finished.
+    const SETTLED_TIME: f64 = 3.;
     pub fn new(
         background: SafeTexture,
         illustration: SafeTexture,
@@ -126,14 +146,79 @@ impl EndingScene {
 
             upload_fn,
             upload_task,
+            upload_attempt: 1,
+            upload_retry_at: None,
+            auto_proceed_since: None,
             record_data,
             record,
 
             btn_retry: RectButton::new(),
             btn_proceed: RectButton::new(),
-            config: config.clone()
+            config: config.clone(),
+            share_card: false,
         })
     }
+
+    /// Whether an upload is in flight or waiting to be retried.
+    fn uploading(&self) -> bool {
+        self.upload_task.is_some() || self.upload_retry_at.is_some()
+    }
+
+    /// Seconds left before leaving by `config.ending_auto_proceed`, `None` if it's disabled or still waiting for the upload.
+    fn auto_proceed_left(&self, now: f64) -> Option<f32> {
+        let delay = self.config.ending_auto_proceed?;
+        let since = self.auto_proceed_since?;
+        Some((delay - (now - since) as f32).max(0.))
+    }
+
+    fn start_upload(&mut self) {
+        let message = if self.upload_attempt > 1 {
+            tl!("uploading-attempt", "attempt" => self.upload_attempt, "total" => self.config.upload_max_attempts)
+        } else {
+            tl!("uploading")
+        };
+        self.upload_task = self
+            .record_data
+            .clone()
+            .map(|data| ((self.upload_fn.as_ref().unwrap())(data), show_message(message).handle()));
+    }
+
+    /// Renders the settled result screen offscreen into a [`Self::SHARE_CARD_SIZE`] image, without the navigation buttons.
+    pub fn render_share_card(&mut self, ui: &mut Ui) -> Result<RgbaImage> {
+        let (width, height) = Self::SHARE_CARD_SIZE;
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Linear);
+        // a manual clock starts at zero, so move it to the settled time explicitly
+        let mut tm = TimeManager::manual(Box::new(|| 0.));
+        tm.seek_to(Self::SETTLED_TIME);
+        let old_target = self.target.replace(target);
+        let old_viewport = std::mem::replace(&mut ui.viewport, (0, 0, width as i32, height as i32));
+        self.share_card = true;
+        let result = self.render(&mut tm, ui);
+        unsafe { get_internal_gl() }.flush();
+        self.share_card = false;
+        self.target = old_target;
+        ui.viewport = old_viewport;
+        if let Err(err) = result {
+            target.delete();
+            return Err(err);
+        }
+        let data = target.texture.get_texture_data();
+        target.delete();
+        let image = RgbaImage::from_raw(width, height, data.bytes).ok_or_else(|| anyhow!("unexpected texture size"))?;
+        // render targets are read bottom-up
+        Ok(image::imageops::flip_vertical(&image))
+    }
+
+    pub fn result_json(&self) -> serde_json::Value {
+        let mut value = self.result.to_json();
+        let object = value.as_object_mut().unwrap();
+        object.insert("chartId".to_owned(), self.info.id.into());
+        object.insert("level".to_owned(), self.info.level.clone().into());
+        object.insert("speed".to_owned(), self.speed.into());
+        object.insert("autoplay".to_owned(), self.autoplay.into());
+        value
+    }
 }
 
 thread_local! {
@@ -145,6 +230,8 @@ impl Scene for EndingScene {
         tm.reset();
         tm.seek_to(-0.4);
         self.target = target;
+        // a retry picked in an earlier ending scene's dialog is not for this one
+        RE_UPLOAD.with(|it| *it.borrow_mut() = false);
         Ok(())
     }
 
@@ -161,8 +248,11 @@ impl Scene for EndingScene {
     }
 
     fn touch(&mut self, _tm: &mut TimeManager, touch: &Touch) -> Result<bool> {
+        if !self.config.interactive {
+            return Ok(false);
+        }
         if self.btn_retry.touch(touch) {
-            if self.upload_task.is_some() {
+            if self.uploading() {
                 show_message(tl!("still-uploading"));
             } else {
                 self.next = 1;
@@ -170,7 +260,7 @@ impl Scene for EndingScene {
             return Ok(true);
         }
         if self.btn_proceed.touch(touch) {
-            if self.upload_task.is_some() {
+            if self.uploading() {
                 show_message(tl!("still-uploading"));
             } else {
                 self.next = 2;
@@ -185,16 +275,25 @@ impl Scene for EndingScene {
         if tm.now() >= EndingScene::BPM_WAIT_TIME - self.config.offset as f64 && self.target.is_none() && self.bgm.paused() {
             self.bgm.play()?;
         }
-        if RE_UPLOAD.with(|it| std::mem::replace(it.borrow_mut().deref_mut(), false)) && self.upload_task.is_none() {
-            self.upload_task = self
-                .record_data
-                .clone()
-                .map(|data| ((self.upload_fn.as_ref().unwrap())(data), show_message(tl!("uploading")).handle()));
+        if RE_UPLOAD.with(|it| std::mem::replace(it.borrow_mut().deref_mut(), false)) && !self.uploading() {
+            self.upload_attempt = 1;
+            self.start_upload();
+        }
+        if self.upload_retry_at.map_or(false, |at| tm.real_time() >= at) {
+            self.upload_retry_at = None;
+            self.upload_attempt += 1;
+            self.start_upload();
         }
         if let Some((task, handle)) = &mut self.upload_task {
             if let Some(result) = task.take() {
                 handle.cancel();
+                self.upload_task = None;
                 match result {
+                    Err(err) if self.upload_attempt < self.config.upload_max_attempts => {
+                        let delay = self.config.upload_retry_delay as f64 * 2f64.powi(self.upload_attempt as i32 - 1);
+                        warn!("upload failed (attempt {}), retrying in {delay}s: {err:?}", self.upload_attempt);
+                        self.upload_retry_at = Some(tm.real_time() + delay);
+                    }
                     Err(err) => {
                         let error = format!("{:?}", err.context(tl!("upload-failed")));
                         Dialog::plain(tl!("upload-failed"), error)
@@ -211,7 +310,17 @@ impl Scene for EndingScene {
                         show_message(tl!("uploaded")).ok();
                     }
                 }
-                self.upload_task = None;
+            }
+        }
+        if self.config.ending_auto_proceed.is_some() {
+            // restarts if the player retries a failed upload, and waits for the upload failure dialog to be answered
+            if self.uploading() || DIALOG.with(|it| it.borrow().is_some()) {
+                self.auto_proceed_since = None;
+            } else if self.auto_proceed_since.is_none() {
+                self.auto_proceed_since = Some(tm.real_time());
+            }
+            if self.next == 0 && self.auto_proceed_left(tm.real_time()) == Some(0.) {
+                self.next = 2;
             }
         }
         Ok(())
@@ -239,6 +348,7 @@ impl Scene for EndingScene {
             text_miss,
             text_early,
             text_late,
+            text_max_touches,
         ) = if self.config.chinese{
             (
                 "最大连击数",
@@ -251,6 +361,7 @@ impl Scene for EndingScene {
                 "错过",
                 "太早",
                 "太晚",
+                "最多手指",
             )
         } else {
             (
@@ -264,6 +375,7 @@ impl Scene for EndingScene {
                 "Miss",
                 "Early",
                 "Late",
+                "Max Fingers",
             )
         };
             
@@ -277,7 +389,7 @@ impl Scene for EndingScene {
         cam.render_target = self.target;
         set_camera(&cam);
         if self.config.render_bg {
-            draw_background(*self.background, self.config.render_bg_dim);
+            draw_background(*self.background, self.config.background_mode, self.config.render_bg_dim);
         }
 
         fn ran(t: f32, l: f32, r: f32) -> f32 {
@@ -300,7 +412,7 @@ impl Scene for EndingScene {
             Color::new(0., 0., 0., 0.7),
             true,
         );
-        let rr = draw_text_aligned(ui, &self.info.level, r.right() - r.h / 7. * 13. * 0.13 - 0.029, r.bottom() - top / 18.5, (1., 1.), 0.40, WHITE); // 难度
+        let rr = draw_text_aligned(ui, &self.info.level, r.right() - r.h / 7. * 13. * 0.13 - 0.029, r.bottom() - top / 18.5, (1., 1.), 0.40, level_color_with(&self.info.level, &self.config.level_colors)); // 难度
         let p = (r.x + 0.055, r.bottom() - top / 14.5);
         let mw = rr.x - 0.02 - p.0;
         let mut text_size = 0.92;
@@ -353,9 +465,10 @@ impl Scene for EndingScene {
             };
             let pa = ran(t, 0.2, 0.6).powi(5);
             let r = draw_text_aligned(ui, &text, main.x + dx + 0.01, main.bottom() - 0.040, (0., 1.), 0.34, Color::new(1., 1., 1., pa)); // 分数下面的字
-            let score = if self.config.roman {GameScene::int_to_roman(res.score)} else if self.config.chinese {GameScene::int_to_chinese(res.score)} else {format!("{:07}", res.score)};
-            let r = draw_text_aligned_fix(ui, &score, r.x - 0.012, r.y - 0.019, (0., 1.), 1.05, Color::new(1., 1., 1., pa), 0.4); // 分数
-            let icon = icon_index(res.score, res.num_of_notes == res.max_combo);
+            let display_score = self.config.score_mode.apply(res.score, res.accuracy);
+            let score = if self.config.roman {GameScene::int_to_roman(display_score)} else if self.config.chinese {GameScene::int_to_chinese(display_score)} else {self.config.score_mode.format(display_score)};
+            let r = draw_long_number(ui, None, &score, (r.x - 0.012, r.y - 0.019), (0., 1.), 1.05, Color::new(1., 1., 1., pa), 0.4, self.config.long_number_mode, t); // 分数
+            let icon = icon_index(self.config.score_mode.normalize(display_score), res.num_of_notes == res.max_combo);
             let p = ran(t, 1.2, 1.6).powi(5);
             let p2 = ran(t, 1.65, 1.9).powi(3);
             let s = main.h * 0.72;
@@ -383,7 +496,7 @@ impl Scene for EndingScene {
             let dy = 0.025;
             let max_combo = if self.config.roman {GameScene::int_to_roman(res.max_combo)} else if self.config.chinese {GameScene::int_to_chinese(res.max_combo)} else {res.max_combo.to_string()};
             let r = draw_text_aligned(ui, text_max_combo, s1.x + dx - 0.005, s1.bottom() - dy, (0., 1.), 0.31, Color::new(1., 1., 1., pa)); // 连击数文本
-            draw_text_aligned_fix(ui, &max_combo, r.x, r.y - 0.006, (0., 1.), 0.65, Color::new(1., 1., 1., pa), 0.3); // 连击数
+            draw_long_number(ui, None, &max_combo, (r.x, r.y - 0.006), (0., 1.), 0.65, Color::new(1., 1., 1., pa), 0.3, self.config.long_number_mode, t); // 连击数
             let accuracy = if self.config.roman {
                 format!("{}%", GameScene::int_to_roman((res.accuracy * 100.) as u32))
             } else if self.config.chinese {
@@ -424,6 +537,34 @@ impl Scene for EndingScene {
             draw_text_aligned_fix(ui, &early, rt, r.bottom(), (1., 1.), sm, Color::new(1., 1., 1., pa), 0.1);
             let r = draw_text_aligned(ui, text_late, l, cy + dy2 / 2.3, (0., 0.), sm, Color::new(1., 1., 1., pa)); // Late
             draw_text_aligned_fix(ui, &late, rt, r.y, (1., 0.), sm, Color::new(1., 1., 1., pa), 0.1);
+
+            let mut y = s2.bottom() + d * 0.4; // 直方图下方 y
+            // hit error histogram, -200ms..200ms
+            if !res.hit_errors.is_empty() {
+                const BINS: usize = 20;
+                const RANGE: f32 = 0.2;
+                let mut bins = [0u32; BINS];
+                for error in &res.hit_errors {
+                    let index = ((error + RANGE) / (RANGE * 2.) * BINS as f32).floor();
+                    bins[(index.max(0.) as usize).min(BINS - 1)] += 1;
+                }
+                let max = *bins.iter().max().unwrap() as f32;
+                let hr = Rect::new(s2.x + s2.w * 0.08, s2.bottom() + d * 0.4, s2.w * 0.6, d * 1.2);
+                let bw = hr.w / BINS as f32;
+                for (i, count) in bins.iter().enumerate() {
+                    let h = hr.h * *count as f32 / max;
+                    ui.fill_rect(Rect::new(hr.x + bw * i as f32, hr.bottom() - h, bw * 0.8, h), Color::new(1., 1., 1., pa * 0.8));
+                }
+                ui.fill_rect(Rect::new(hr.center().x - 0.001, hr.y, 0.002, hr.h), Color::new(1., 0.92, 0.63, pa));
+                y = hr.bottom() + d * 0.2;
+            }
+
+            if res.max_touches > 0 {
+                // 最多手指数, 放在直方图下方
+                let r = draw_text_aligned(ui, text_max_touches, s2.x + s2.w * 0.08, y, (0., 0.), sm * 0.8, Color::new(1., 1., 1., pa));
+                let max_touches = if self.config.roman {GameScene::int_to_roman(res.max_touches)} else if self.config.chinese {GameScene::int_to_chinese(res.max_touches)} else {res.max_touches.to_string()};
+                draw_text_aligned_fix(ui, &max_touches, r.right() + 0.02, r.y, (0., 0.), sm * 0.8, Color::new(1., 1., 1., pa), 0.1);
+            }
         }
         gl.pop_model_matrix();
 
@@ -438,26 +579,31 @@ impl Scene for EndingScene {
             dest_size: Some(vec2(hs * 2., hs * 2.)),
             ..Default::default()
         };
-        tran(gl, -p * 0.1);
-        let r = Rect::new(-1. - h * slope, -top + dy, w, h);
-        draw_parallelogram(r, None, c, true);
-        draw_parallelogram(Rect::new(r.x + r.w * (1. - s), r.y, r.w * s, r.h), None, WHITE, false);
-        let ct = r.center();
-        draw_texture_ex(*self.icon_retry, ct.x - hs * 0.9, ct.y - hs, WHITE, params.clone());
-        gl.pop_model_matrix();
-        if p <= 0. {
-            self.btn_retry.set(ui, r);
-        }
+        if !self.share_card {
+            tran(gl, -p * 0.1);
+            let r = Rect::new(-1. - h * slope, -top + dy, w, h);
+            draw_parallelogram(r, None, c, true);
+            draw_parallelogram(Rect::new(r.x + r.w * (1. - s), r.y, r.w * s, r.h), None, WHITE, false);
+            let ct = r.center();
+            draw_texture_ex(*self.icon_retry, ct.x - hs * 0.9, ct.y - hs, WHITE, params.clone());
+            gl.pop_model_matrix();
+            if p <= 0. {
+                self.btn_retry.set(ui, r);
+            }
 
-        tran(gl, p2 * 0.1);
-        let r = Rect::new(1. + h * slope - w, top - dy - h, w, h);
-        draw_parallelogram(r, None, c, true);
-        draw_parallelogram(Rect::new(r.x, r.y, r.w * s, r.h), None, WHITE, false);
-        let ct = r.center();
-        draw_texture_ex(*self.icon_proceed, ct.x - hs * 0.8 - r.w * s / 2., ct.y - hs, WHITE, params);
-        gl.pop_model_matrix();
-        if p <= 0. {
-            self.btn_proceed.set(ui, r);
+            tran(gl, p2 * 0.1);
+            let r = Rect::new(1. + h * slope - w, top - dy - h, w, h);
+            draw_parallelogram(r, None, c, true);
+            draw_parallelogram(Rect::new(r.x, r.y, r.w * s, r.h), None, WHITE, false);
+            let ct = r.center();
+            draw_texture_ex(*self.icon_proceed, ct.x - hs * 0.8 - r.w * s / 2., ct.y - hs, WHITE, params);
+            if let Some(left) = self.auto_proceed_left(tm.real_time()) {
+                draw_text_aligned(ui, &tl!("auto-proceed", "seconds" => left.ceil() as u32), r.right() - h * slope, r.y - 0.01, (1., 1.), 0.35, WHITE);
+            }
+            gl.pop_model_matrix();
+            if p <= 0. {
+                self.btn_proceed.set(ui, r);
+            }
         }
 
         let alpha = ran(t, 0.65, 1.15); // rks
@@ -531,6 +677,11 @@ impl Scene for EndingScene {
     fn next_scene(&mut self, _tm: &mut TimeManager) -> NextScene {
         if self.next != 0 {
             let _ = self.bgm.pause();
+            RESULT_FN.with(|it| {
+                if let Some(f) = it.borrow_mut().as_mut() {
+                    f(self.result_json());
+                }
+            });
         }
         match self.next {
             0 => NextScene::None,
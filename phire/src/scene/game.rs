@@ -5,37 +5,39 @@ crate::tl_file!("game");
 use chinese_number::{ChineseCase, ChineseCountMethod, ChineseVariant, NumberToChinese, ChineseToNumber};
 use regex::Regex;
 use super::{
-    draw_background,
+    draw_background, draw_background_shifted,
     ending::RecordUpdateState,
-    loading::{BasicPlayer, UpdateFn, UploadFn},
-    request_input, return_input, show_message, take_input, EndingScene, NextScene, Scene,
+    loading::{BasicPlayer, ProgressFn, UpdateFn, UploadFn},
+    request_input, return_input, show_message, take_input, window_focused, EndingScene, NextScene, Scene,
 };
 use crate::{
     bin::{BinaryReader, BinaryWriter},
-    config::{Config, Mods},
-    core::{copy_fbo, BadNote, Chart, ChartExtra, Effect, Point, Resource, UIElement, Vector, BUFFER_SIZE},
-    ext::{ease_in_out_quartic, get_latency, parse_time, push_frame_time, screen_aspect, semi_white, validate_combo, RectExt, SafeTexture},
+    config::{AudioFilter, ChartPreset, Config, Mods},
+    core::{copy_fbo, BadNote, Chart, ChartExtra, Effect, MSRenderTarget, Point, Resource, UIElement, Vector, BUFFER_SIZE},
+    ext::{draw_long_number, ease_in_out_quartic, get_latency, parse_key_code, parse_time, push_frame_time, screen_aspect, semi_black, semi_white, spawn_task, validate_combo, FrameStats, RectExt, SafeTexture},
     fs::FileSystem,
     info::{ChartFormat, ChartInfo},
-    judge::Judge,
-    parse::{parse_extra, parse_pec, parse_phigros, parse_rpe},
+    judge::{play_sfx, HitSound, Judge, JudgeSnapshot, LiveStats, ReplayData, LIMIT_GOOD, LIMIT_PERFECT},
+    parse::{is_osu, parse_extra, parse_osu, parse_pec, parse_phigros, parse_rpe},
     task::Task,
     time::TimeManager,
-    ui::{RectButton, Ui},
+    ui::{MessageHandle, RectButton, Ui},
 };
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use concat_string::concat_string;
 use lyon::path::Path;
 use macroquad::{prelude::*, window::InternalGlContext};
-use sasa::{Music, MusicParams};
+use once_cell::sync::Lazy;
+use sasa::{AudioClip, Frame, Music, MusicParams, PlaySfxParams};
 use serde::{Deserialize, Serialize};
 use std::{
     any::Any,
     cell::RefCell,
+    collections::{HashMap, VecDeque},
     fs::File,
-    io::{Cursor, ErrorKind},
+    io::{Cursor, ErrorKind, Write},
     ops::{DerefMut, Range},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
     rc::Rc,
     sync::{Arc, Mutex},
@@ -44,6 +46,7 @@ use std::{
 use tracing::{debug, warn};
 
 const PAUSE_CLICK_INTERVAL: f32 = 0.7;
+const PAUSE_DUCK_LEVEL: f32 = 0.3;
 
 #[cfg(feature = "closed")]
 mod inner;
@@ -53,6 +56,172 @@ use inner::*;
 const WAIT_TIME: f32 = 0.5;
 const AFTER_TIME: f32 = 0.7;
 
+const NORMALIZE_TARGET_RMS: f32 = 0.2;
+
+const METRONOME_FLASH_TIME: f32 = 0.15;
+const BEATS_PER_BAR: f32 = 4.; // charts carry no time signature, assume 4/4
+
+/// Keys handled by `update` itself (pause, quit and seek), which can't be used as lane bindings
+const RESERVED_KEYS: [KeyCode; 4] = [KeyCode::Space, KeyCode::Q, KeyCode::Left, KeyCode::Right];
+
+const SPEED_CROSSFADE: f32 = 0.1;
+const MIN_LIVE_SPEED: f32 = 0.25; // lower rates drift too far from the judge timing
+
+const FINALE_SLOWMO_DURATION: f32 = 3.;
+const FINALE_SLOWMO_STEP: f32 = 0.05; // music is recreated on every change, so the ramp is quantized
+
+// fractions of the loading progress at which each phase starts
+const LOAD_PARSE_START: f32 = 0.2;
+const LOAD_TEXTURES_START: f32 = 0.4;
+const LOAD_AUDIO_START: f32 = 0.7;
+
+const PRACTICE_LEAD: f32 = 3.;
+
+const BG_PARALLAX_RANGE: f32 = 0.05; // largest background offset, at full strength
+const BG_PARALLAX_FOLLOW: f32 = 3.; // rate at which the background catches up, per second
+
+const ONSET_WINDOWS_PER_SEC: usize = 100;
+const ONSET_THRESHOLD: f32 = 0.1;
+
+const ENVELOPE_WINDOWS_PER_SEC: usize = 60;
+const ENVELOPE_RELEASE: f32 = 0.85; // per window, the envelope rises instantly and decays with this factor
+
+const WATERMARK_IMAGE_HEIGHT: f32 = 0.06;
+
+const PAUSE_PANEL_WIDTH: f32 = 0.7;
+const PAUSE_PANEL_HEIGHT: f32 = 0.2;
+
+const SEEK_PREVIEW_SIZE: (u32, u32) = (320, 180);
+const SEEK_PREVIEW_INTERVAL: f64 = 0.1;
+const SEEK_PREVIEW_WIDTH: f32 = 0.4;
+
+const PASSIVE_SEEK_TOLERANCE: f32 = 0.05;
+
+const TOUCH_TRAIL_LENGTH: usize = 16; // positions kept per touch
+
+const COMBO_POP_TIME: f32 = 0.3;
+const COMBO_POP_SCALE: f32 = 0.3;
+const COMBO_BURST_COUNT: usize = 5;
+
+const HIT_POPUP_TIME: f32 = 0.3;
+const HIT_POPUP_RISE: f32 = 0.04;
+const HIT_POPUP_STACK: f32 = 0.045; // vertical spacing of popups close to each other
+
+const TIMING_METER_HALF_WIDTH: f32 = 0.25; // the ends are the edges of the good window
+const TIMING_METER_FADE: f32 = 4.;
+
+const ACCURACY_TARGET_MIN_NOTES: u32 = 20; // the accuracy of the first few notes swings too much
+
+const COMBO_BREAK_TIME: f32 = 0.4;
+const COMBO_BREAK_SHAKE: f32 = 0.02;
+const COMBO_BREAK_FREQ: f32 = 40.;
+const COMBO_BREAK_COLOR: Color = Color::new(1., 0.35, 0.35, 1.);
+
+const AP_AURA_WIDTH: f32 = 0.12;
+const AP_AURA_ALPHA: f32 = 0.2;
+
+const GHOST_AHEAD_COLOR: Color = Color::new(0.4, 0.9, 0.45, 1.);
+const GHOST_BEHIND_COLOR: Color = Color::new(0.95, 0.35, 0.35, 1.);
+
+static NORMALIZE_GAINS: Lazy<Mutex<HashMap<String, f32>>> = Lazy::new(Mutex::default);
+// sasa can't filter in real time, so the last filtered music is kept to make retries cheap
+static FILTERED_MUSIC: Lazy<Mutex<Option<(String, AudioClip)>>> = Lazy::new(Mutex::default);
+
+/// Runs a one-pole filter over the clip.
+fn filter_clip(clip: &AudioClip, filter: AudioFilter) -> AudioClip {
+    let dt = 1. / clip.sample_rate() as f32;
+    let rc = 1. / (2. * std::f32::consts::PI * filter.cutoff().max(1.));
+    let mut frames = clip.frames().to_vec();
+    match filter {
+        AudioFilter::LowPass(_) => {
+            let alpha = dt / (rc + dt);
+            let mut last = Frame(0., 0.);
+            for frame in &mut frames {
+                last.0 += alpha * (frame.0 - last.0);
+                last.1 += alpha * (frame.1 - last.1);
+                *frame = last;
+            }
+        }
+        AudioFilter::HighPass(_) => {
+            let alpha = rc / (rc + dt);
+            let (mut last_in, mut last_out) = (Frame(0., 0.), Frame(0., 0.));
+            for frame in &mut frames {
+                let input = *frame;
+                last_out = Frame(alpha * (last_out.0 + input.0 - last_in.0), alpha * (last_out.1 + input.1 - last_in.1));
+                last_in = input;
+                *frame = last_out;
+            }
+        }
+    }
+    AudioClip::from_raw(frames, clip.sample_rate())
+}
+
+/// Gain bringing the clip's RMS loudness to [`NORMALIZE_TARGET_RMS`], limited so that the peak won't clip.
+fn normalize_gain(clip: &AudioClip) -> f32 {
+    let frames = clip.frames();
+    if frames.is_empty() {
+        return 1.;
+    }
+    let mut sum = 0.;
+    let mut peak = 0f32;
+    for frame in frames {
+        sum += (frame.0 * frame.0 + frame.1 * frame.1) as f64;
+        peak = peak.max(frame.0.abs()).max(frame.1.abs());
+    }
+    let rms = (sum / (frames.len() * 2) as f64).sqrt() as f32;
+    if rms <= 1e-4 {
+        return 1.;
+    }
+    (NORMALIZE_TARGET_RMS / rms).min(1. / peak.max(1e-4)).clamp(0.1, 4.)
+}
+
+/// Finds the time (in seconds) of the first strong onset in the clip, using the energy of short windows.
+pub fn detect_offset(clip: &AudioClip) -> f32 {
+    let frames = clip.frames();
+    let window = (clip.sample_rate() as usize / ONSET_WINDOWS_PER_SEC).max(1);
+    let energies: Vec<f32> = frames
+        .chunks(window)
+        .map(|chunk| chunk.iter().map(|it| it.0 * it.0 + it.1 * it.1).sum::<f32>() / chunk.len() as f32)
+        .collect();
+    let Some(max) = energies.iter().copied().reduce(f32::max) else {
+        return 0.;
+    };
+    if max <= 1e-8 {
+        return 0.;
+    }
+    let mut history = 0.;
+    for (i, energy) in energies.iter().enumerate() {
+        // a window much louder than what came before it, and loud compared to the whole track
+        if *energy >= max * ONSET_THRESHOLD && *energy > history * 4. {
+            return (i * window) as f32 / clip.sample_rate() as f32;
+        }
+        history = history * 0.8 + energy * 0.2;
+    }
+    0.
+}
+
+/// Loudness envelope of the clip with [`ENVELOPE_WINDOWS_PER_SEC`] values per second, normalized so that the loudest
+/// window is `1`. Returns `None` for silent or empty clips.
+fn amplitude_envelope(clip: &AudioClip) -> Option<Vec<f32>> {
+    let window = (clip.sample_rate() as usize / ENVELOPE_WINDOWS_PER_SEC).max(1);
+    let mut envelope: Vec<f32> = clip
+        .frames()
+        .chunks(window)
+        .map(|chunk| (chunk.iter().map(|it| it.0 * it.0 + it.1 * it.1).sum::<f32>() / (chunk.len() * 2) as f32).sqrt())
+        .collect();
+    let mut level = 0f32;
+    for value in &mut envelope {
+        level = value.max(level * ENVELOPE_RELEASE);
+        *value = level;
+    }
+    let max = envelope.iter().copied().reduce(f32::max)?;
+    if max <= 1e-4 {
+        return None;
+    }
+    envelope.iter_mut().for_each(|it| *it /= max);
+    Some(envelope)
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SimpleRecord {
@@ -80,6 +249,35 @@ impl SimpleRecord {
     }
 }
 
+pub type StatsFn = Box<dyn FnMut(f32, &LiveStats)>;
+
+thread_local! {
+    static STATS_FN: RefCell<Option<StatsFn>> = RefCell::default();
+}
+
+/// Registers a callback receiving the chart time and live judgement stats.
+///
+/// It is called once per frame from [`GameScene`]'s update, including while the game is paused.
+pub fn set_stats_callback(f: Option<StatsFn>) {
+    STATS_FN.with(|it| *it.borrow_mut() = f);
+}
+
+pub type PresetFn = Box<dyn FnMut(Option<ChartPreset>)>;
+
+thread_local! {
+    static PRESET_FN: RefCell<Option<PresetFn>> = RefCell::default();
+}
+
+/// Registers a callback storing the preset of the current chart, or clearing it with `None`.
+///
+/// The save and clear actions of the pause menu are only shown while a callback is registered.
+pub fn set_preset_callback(f: Option<PresetFn>) {
+    PRESET_FN.with(|it| *it.borrow_mut() = f);
+}
+
+/// Returned to the previous scene when the player quits in the middle of a chart.
+pub struct ResumePoint(pub f32);
+
 fn fmt_time(t: f32) -> String {
     let f = t < 0.;
     let t = t.abs();
@@ -104,6 +302,7 @@ pub enum GameMode {
     Exercise,
     NoRetry,
     View,
+    Practice,
 }
 
 #[derive(Clone)]
@@ -114,6 +313,13 @@ enum State {
     Ending,
 }
 
+/// Low-res snapshot of the playfield shown while dragging the exercise timeline.
+struct SeekPreview {
+    target: MSRenderTarget,
+    time: f32,
+    last_render: f64,
+}
+
 pub struct PauseRewind {
     time: Option<f64>,
     duration: Option<f64>,
@@ -133,18 +339,58 @@ pub struct GameScene {
     chart_bytes: Vec<u8>,
     info_offset: f32,
     effects: Vec<Effect>,
+    // index into the built-in presets followed by the RPE ones, the effect if it could be created, and
+    // `res.no_effect` from before the preview, restored when it's turned off
+    preset_preview: Option<(usize, Option<Effect>, bool)>,
+    // applied to the whole frame right before it's put on screen, regardless of `no_effect`
+    color_filter: Option<Effect>,
 
     first_in: bool,
     exercise_range: Range<f32>,
     exercise_press: Option<(i8, u64)>,
     exercise_btns: (RectButton, RectButton),
+    seek_preview: Option<SeekPreview>,
+    seek_preview_request: Option<f32>,
+    seek_request: Option<f32>, // position in the music, applied on the next update
 
     pub music: Music,
+    old_music: Option<(Music, f64)>, // fading out after a speed change
+    finale_base_speed: Option<f32>,
+
+    detect_task: Option<(Task<Result<f32>>, MessageHandle)>,
 
     state: State,
     pub last_update_time: f64,
     pause_rewind: PauseRewind,
     pause_first_time: f32,
+    intro_start: Option<f64>,
+
+    metronome_tick: i64,
+    bg_parallax: (f32, Vec2), // time of the last update and current offset of the background
+    metronome_flash: (f32, bool), // time, downbeat
+
+    last_combo: u32,
+    combo_pop_time: f32,
+    combo_break: (f32, u32), // time, broken combo
+    accuracy_warning: Option<f64>, // time the accuracy dropped below the target
+
+    audio_envelope: Option<Vec<f32>>,
+
+    /// Previous run to race against, only its score progression is compared
+    pub ghost: Option<ReplayData>,
+    ghost_diff: Option<i64>,
+    /// Score progression of the current run, can be used as a ghost later
+    pub replay: ReplayData,
+
+    frame_stats: FrameStats,
+
+    watermark: Option<SafeTexture>,
+    letterbox: Option<SafeTexture>,
+    ui_targets: [Option<RenderTarget>; 2],
+    key_bindings: Vec<KeyCode>,
+    autoplay_toggle_key: Option<KeyCode>,
+    /// Whether autoplay has been toggled on at any point of the current run
+    autoplay_used: bool,
 
     pub bad_notes: Vec<BadNote>,
 
@@ -152,6 +398,7 @@ pub struct GameScene {
     update_fn: Option<UpdateFn>,
 
     pub touch_points: Vec<(f32, f32)>,
+    touch_trails: HashMap<u64, VecDeque<Vec2>>,
 }
 
 macro_rules! reset {
@@ -159,13 +406,28 @@ macro_rules! reset {
         $self.bad_notes.clear();
         $self.judge.reset();
         $self.chart.reset();
-        $res.judge_line_color = Color::from_hex($res.res_pack.info.color_perfect_line);
+        $res.judge_line_color = if $res.config.judge_line_feedback { Color::from_hex($res.res_pack.info.color_perfect_line) } else { WHITE };
         $self.music.pause()?;
+        if $res.config.pause_keep_music {
+            $self.music.set_amplifier(GameScene::music_amplifier(&$res))?;
+        }
         $self.music.seek_to(0.)?;
         $tm.speed = $res.config.speed as _;
         $tm.reset();
         $self.last_update_time = $tm.now();
         $self.state = State::Starting;
+        $self.intro_start = None;
+        $self.metronome_tick = i64::MAX;
+        $self.last_combo = 0;
+        $self.combo_break = (f32::NEG_INFINITY, 0);
+        $self.accuracy_warning = None;
+        $self.replay = ReplayData::default();
+        $self.ghost_diff = None;
+        $self.autoplay_used = false;
+        if $self.mode == GameMode::Practice {
+            let start = $self.exercise_range.start - ($self.chart.offset + $res.config.offset + $self.info_offset);
+            $self.judge.skip_before(&mut $self.chart, start);
+        }
         $self.pause_rewind = PauseRewind {
             time: None,
             duration: None,
@@ -293,7 +555,19 @@ impl GameScene {
     }
     
 
-    pub async fn load_chart(fs: &mut dyn FileSystem, info: &ChartInfo) -> Result<(Chart, Vec<u8>, ChartFormat)> {
+    /// Loads and parses the chart, reporting the phase (`video`, `parsing`, `textures`) and a 0..1 fraction to `progress`.
+    pub async fn load_chart(
+        fs: &mut dyn FileSystem,
+        info: &ChartInfo,
+        mut progress: Option<&mut dyn FnMut(f32, &str)>,
+    ) -> Result<(Chart, Vec<u8>, ChartFormat)> {
+        let mut report = |fraction: f32, phase: &str| {
+            if let Some(f) = progress.as_mut() {
+                f(fraction, phase);
+            }
+        };
+        // videos are loaded along with extra
+        report(0., "video");
         let extra = if let Some(extra) = fs.load_file("extra.json").await.ok().map(String::from_utf8).transpose()? {
             parse_extra(&extra, fs).await.context("Failed to parse extra")?
         } else if let Some(extra) = fs.load_file("extra1.json").await.ok().map(String::from_utf8).transpose()? {
@@ -301,6 +575,7 @@ impl GameScene {
         } else {
             ChartExtra::default()
         };
+        report(LOAD_PARSE_START, "parsing");
         let bytes = Self::load_chart_bytes(fs, info).await.context("Failed to load chart")?;
         let format = info.format.clone().unwrap_or_else(|| {
             if let Ok(text) = std::str::from_utf8(&bytes) {
@@ -310,6 +585,8 @@ impl GameScene {
                     } else {
                         ChartFormat::Pgr
                     }
+                } else if is_osu(text) {
+                    ChartFormat::Osu
                 } else {
                     ChartFormat::Pec
                 }
@@ -321,12 +598,18 @@ impl GameScene {
             ChartFormat::Rpe => parse_rpe(&String::from_utf8_lossy(&bytes), fs, extra).await,
             ChartFormat::Pgr => parse_phigros(&String::from_utf8_lossy(&bytes), extra),
             ChartFormat::Pec => parse_pec(&String::from_utf8_lossy(&bytes), extra),
+            ChartFormat::Osu => parse_osu(&String::from_utf8_lossy(&bytes), extra),
             ChartFormat::Pbc => {
                 let mut r = BinaryReader::new(Cursor::new(&bytes));
                 r.read()
             }
         }?;
-        chart.load_textures(fs).await?;
+        report(LOAD_TEXTURES_START, "textures");
+        chart
+            .load_textures(fs, |loaded, total| {
+                report(LOAD_TEXTURES_START + (LOAD_AUDIO_START - LOAD_TEXTURES_START) * loaded as f32 / total as f32, "textures")
+            })
+            .await?;
         chart.settings.hold_partial_cover = info.hold_partial_cover;
         Ok((chart, bytes, format))
     }
@@ -341,6 +624,8 @@ impl GameScene {
         illustration: SafeTexture,
         upload_fn: Option<UploadFn>,
         update_fn: Option<UpdateFn>,
+        mut progress_fn: Option<ProgressFn>,
+        practice_start: Option<f32>,
     ) -> Result<Self> {
         match mode {
             GameMode::TweakOffset => {
@@ -348,16 +633,23 @@ impl GameScene {
             }
             _ => {}
         }
-        let (mut chart, chart_bytes, chart_format) = Self::load_chart(fs.deref_mut(), &info).await?;
+        let (mut chart, chart_bytes, chart_format) = Self::load_chart(fs.deref_mut(), &info, progress_fn.as_mut().map(|f| f.as_mut() as &mut dyn FnMut(f32, &str))).await?;
+        let color_filter = config
+            .color_filter
+            .preset()
+            .map(|preset| Effect::new(f32::NEG_INFINITY..f32::INFINITY, Effect::get_preset(preset).unwrap(), Vec::new(), Vec::new(), true).unwrap());
         let effects = std::mem::take(&mut chart.extra.global_effects);
         if config.fxaa {
             chart
                 .extra
                 .effects
-                .push(Effect::new(0.0..f32::INFINITY, include_str!("fxaa.glsl"), Vec::new(), false).unwrap());
+                .push(Effect::new(0.0..f32::INFINITY, include_str!("fxaa.glsl"), Vec::new(), Vec::new(), false).unwrap());
         }
 
         let info_offset = info.offset;
+        if let Some(f) = progress_fn.as_mut() {
+            f(LOAD_AUDIO_START, "audio");
+        }
         let mut res = Resource::new(
             config,
             chart_format,
@@ -370,16 +662,95 @@ impl GameScene {
         )
         .await
         .context("Failed to load resources")?;
-        let exercise_range = (chart.offset + info_offset + res.config.offset)..res.track_length;
+        if let Some(f) = progress_fn.as_mut() {
+            f(1., "audio");
+        }
+        // the visual flip would leave the judgement unflipped, which only makes sense when watching
+        if mode != GameMode::View && res.config.interactive {
+            res.config.visual_flip_x = false;
+        }
+        let mut exercise_range = (chart.offset + info_offset + res.config.offset)..res.track_length;
+        if mode == GameMode::Practice {
+            if let Some(time) = practice_start {
+                exercise_range.start = (time - PRACTICE_LEAD).clamp(exercise_range.start, exercise_range.end);
+            }
+        }
         
         // Prepare extra sfx from chart.hitsounds
-        chart.hitsounds.drain().for_each(|(name, clip)| {
-            if let Ok(clip) = res.audio.create_sfx(clip, Some(BUFFER_SIZE)) {
+        chart.hitsounds.drain().for_each(|(name, clip)| match res.audio.create_sfx(clip, Some(BUFFER_SIZE)) {
+            Ok(clip) => {
                 res.extra_sfxs.insert(name, clip);
             }
+            Err(err) => warn!("failed to create hitsound {name}: {err:?}"),
         });
+        // notes referring to hitsounds that could not be loaded use the default ones
+        for note in chart.lines.iter_mut().flat_map(|it| it.notes.iter_mut()) {
+            if matches!(&note.hitsound, HitSound::Custom(name) if !res.extra_sfxs.contains_key(name)) {
+                note.hitsound = HitSound::default_from_kind(&note.kind);
+            }
+        }
+
+        let mut judge = Judge::new(&chart);
+        let audio_envelope = if res.config.reactive_lines { amplitude_envelope(&res.music) } else { None };
+        judge.score_mode = res.config.score_mode;
+        if mode == GameMode::Practice {
+            // notes already passed when the run starts are neither played nor counted as misses
+            judge.skip_before(&mut chart, exercise_range.start - (chart.offset + res.config.offset + info_offset));
+        }
+
+        let watermark = res.config.watermark_image.as_ref().and_then(|path| match image::open(path) {
+            Ok(image) => Some(SafeTexture::from(image)),
+            Err(err) => {
+                warn!("failed to load watermark image {path}: {err:?}");
+                None
+            }
+        });
+        let letterbox = res.config.letterbox_image.as_ref().and_then(|path| match image::open(path) {
+            Ok(image) => Some(SafeTexture::from(image)),
+            Err(err) => {
+                warn!("failed to load letterbox image {path}: {err:?}");
+                None
+            }
+        });
+
+        #[cfg(debug_assertions)]
+        if res.config.minimal_render {
+            debug!(
+                "minimal render skips the background, {} particle slots, {} chart effects, {} global effects and {} videos",
+                res.config.particle_quality.max_particles(res.config.max_particles),
+                chart.extra.effects.len(),
+                effects.len(),
+                chart.extra.videos.len(),
+            );
+        }
 
-        let judge = Judge::new(&chart);
+        let key_bindings = res
+            .config
+            .key_bindings
+            .iter()
+            .filter_map(|name| {
+                let key = parse_key_code(name);
+                if key.is_none() {
+                    warn!("unknown key in key bindings: {name}");
+                }
+                key.filter(|key| {
+                    let reserved = RESERVED_KEYS.contains(key);
+                    if reserved {
+                        warn!("{name} is reserved for pausing, quitting and seeking, it can't be bound to a lane");
+                    }
+                    !reserved
+                })
+            })
+            .collect();
+        let autoplay_toggle_key = Some(res.config.autoplay_toggle_key.as_str())
+            .filter(|it| !it.trim().is_empty())
+            .and_then(|name| {
+                let key = parse_key_code(name);
+                if key.is_none() {
+                    warn!("unknown autoplay toggle key: {name}");
+                }
+                key
+            });
 
         let music = Self::new_music(&mut res)?;
         Ok(Self {
@@ -394,23 +765,57 @@ impl GameScene {
             player,
             chart_bytes,
             effects,
+            preset_preview: None,
+            color_filter,
             info_offset,
 
             first_in: false,
             exercise_range,
             exercise_press: None,
             exercise_btns: (RectButton::new(), RectButton::new()),
+            seek_preview: None,
+            seek_preview_request: None,
+            seek_request: None,
 
             music,
+            old_music: None,
+            finale_base_speed: None,
+
+            detect_task: None,
 
             state: State::Starting,
             last_update_time: 0.,
+            bg_parallax: (0., Vec2::ZERO),
             pause_rewind: PauseRewind {
                 time: None,
                 duration: None,
                 dim: false
             },
             pause_first_time: f32::NEG_INFINITY,
+            intro_start: None,
+
+            metronome_tick: i64::MAX,
+            metronome_flash: (f32::NEG_INFINITY, false),
+
+            last_combo: 0,
+            combo_pop_time: f32::NEG_INFINITY,
+            combo_break: (f32::NEG_INFINITY, 0),
+            accuracy_warning: None,
+
+            audio_envelope,
+
+            ghost: None,
+            ghost_diff: None,
+            replay: ReplayData::default(),
+
+            frame_stats: FrameStats::default(),
+
+            watermark,
+            letterbox,
+            ui_targets: [None, None],
+            key_bindings,
+            autoplay_toggle_key,
+            autoplay_used: false,
 
             bad_notes: Vec::new(),
 
@@ -418,20 +823,356 @@ impl GameScene {
             update_fn,
 
             touch_points: Vec::new(),
+            touch_trails: HashMap::new(),
         })
     }
 
+    /// Applies `config.intro_fade` and `config.outro_fade` on top of the built-in fades, taking the lower alpha so they don't stack.
+    fn apply_scene_fade(&mut self, tm: &TimeManager, time: f32) {
+        let (intro, outro) = (self.res.config.intro_fade, self.res.config.outro_fade);
+        let intro_alpha = (intro > 0. && self.mode != GameMode::Exercise && !matches!(self.state, State::Ending)).then(|| {
+            let start = *self.intro_start.get_or_insert_with(|| tm.real_time());
+            1. - (1. - ((tm.real_time() - start) as f32 / intro).clamp(0., 1.)).powi(3)
+        });
+        let outro_alpha = (outro > 0. && !matches!(self.state, State::Starting)).then(|| ((self.res.track_length - time) / outro).clamp(0., 1.));
+        if intro_alpha.is_some() || outro_alpha.is_some() {
+            let base = if matches!(self.state, State::Ending) { self.res.alpha } else { 1. };
+            self.res.alpha = base.min(intro_alpha.unwrap_or(1.)).min(outro_alpha.unwrap_or(1.));
+        }
+    }
+
+    /// Runs a UI pass with the given camera. With `config.ui_virtual_height` set, it's drawn on a fixed-size offscreen
+    /// target first and then stretched over the viewport, so touch coordinates are unaffected.
+    fn ui_pass(
+        &mut self,
+        slot: usize,
+        ui: &mut Ui,
+        tm: &mut TimeManager,
+        zoom: Vec2,
+        viewport: Option<(i32, i32, i32, i32)>,
+        onto: Option<RenderTarget>,
+        pass: fn(&mut Self, &mut Ui, &mut TimeManager) -> Result<()>,
+    ) -> Result<()> {
+        let Some(height) = self.res.config.ui_virtual_height.filter(|it| *it >= 1.) else {
+            if let Some(old) = self.ui_targets[slot].take() {
+                old.delete();
+            }
+            set_camera(&Camera2D {
+                zoom,
+                viewport,
+                render_target: onto,
+                ..Default::default()
+            });
+            return pass(self, ui, tm);
+        };
+        let vp = viewport.unwrap_or(ui.viewport);
+        let dim = (((height * vp.2 as f32 / vp.3 as f32).round() as u32).max(1), height.round() as u32);
+        let target = match self.ui_targets[slot] {
+            Some(it) if (it.texture.width() as u32, it.texture.height() as u32) == dim => it,
+            old => {
+                if let Some(old) = old {
+                    old.delete();
+                }
+                let target = render_target(dim.0, dim.1);
+                target.texture.set_filter(FilterMode::Linear);
+                target
+            }
+        };
+        self.ui_targets[slot] = Some(target);
+        self.gl.flush();
+        set_camera(&Camera2D {
+            zoom,
+            render_target: Some(target),
+            ..Default::default()
+        });
+        clear_background(Color::new(0., 0., 0., 0.));
+        pass(self, ui, tm)?;
+        set_camera(&Camera2D {
+            zoom: vec2(1., 1.),
+            viewport,
+            render_target: onto,
+            ..Default::default()
+        });
+        draw_texture_ex(
+            target.texture,
+            -1.,
+            -1.,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(2., 2.)),
+                ..Default::default()
+            },
+        );
+        self.gl.flush();
+        Ok(())
+    }
+
+    /// Touches of the bound keys this frame, each at the center of its lane.
+    fn key_touches(&self) -> Vec<Touch> {
+        // the GL viewport is only set to the camera's one later in `update`
+        let vp = self.res.camera.viewport.unwrap_or((0, 0, screen_width() as i32, screen_height() as i32));
+        let lanes = self.key_bindings.len() as f32;
+        let y = screen_height() - (vp.1 + vp.3) as f32 + vp.3 as f32 / 2.;
+        self.key_bindings
+            .iter()
+            .enumerate()
+            .filter_map(|(lane, key)| {
+                let phase = if is_key_pressed(*key) {
+                    TouchPhase::Started
+                } else if is_key_released(*key) {
+                    TouchPhase::Ended
+                } else if is_key_down(*key) {
+                    TouchPhase::Stationary
+                } else {
+                    return None;
+                };
+                let x = ((lane as f32 + 0.5) / lanes * 2. - 1.) * self.res.config.chart_ratio;
+                Some(Touch {
+                    id: Judge::lane_touch_id(lane),
+                    phase,
+                    position: vec2(vp.0 as f32 + (x + 1.) / 2. * vp.2 as f32, y),
+                    time: f64::NEG_INFINITY,
+                })
+            })
+            .collect()
+    }
+
+    fn music_amplifier(res: &Resource) -> f32 {
+        if res.config.mute_music {
+            return 0.;
+        }
+        let gain = if res.config.normalize_audio {
+            let key = format!("{:?}:{}:{}", res.info.id, res.info.name, res.info.music);
+            *NORMALIZE_GAINS
+                .lock()
+                .unwrap()
+                .entry(key)
+                .or_insert_with(|| normalize_gain(&res.music))
+        } else {
+            1.
+        };
+        res.config.volume_music * gain
+    }
+
+    /// Pauses the music, or only ducks it with `config.pause_keep_music`.
+    fn pause_music(music: &mut Music, res: &Resource) -> Result<()> {
+        if music.paused() {
+            return Ok(());
+        }
+        if res.config.pause_keep_music {
+            music.set_amplifier(Self::music_amplifier(res) * PAUSE_DUCK_LEVEL)
+        } else {
+            music.pause()
+        }
+    }
+
+    /// Undoes the ducking of [`Self::pause_music`]. The chart is frozen during pause while the music isn't,
+    /// so the music is moved back to `time`.
+    fn unpause_music(music: &mut Music, res: &Resource, time: f32) -> Result<()> {
+        if res.config.pause_keep_music && !music.paused() {
+            music.set_amplifier(Self::music_amplifier(res))?;
+            music.seek_to(time)?;
+        }
+        Ok(())
+    }
+
     fn new_music(res: &mut Resource) -> Result<Music> {
+        let clip = match res.config.music_filter {
+            Some(filter) => {
+                let key = format!("{:?}:{}:{}:{filter:?}", res.info.id, res.info.name, res.info.music);
+                let mut cache = FILTERED_MUSIC.lock().unwrap();
+                match cache.as_ref() {
+                    Some((cached, clip)) if *cached == key => clip.clone(),
+                    _ => {
+                        let clip = filter_clip(&res.music, filter);
+                        *cache = Some((key, clip.clone()));
+                        clip
+                    }
+                }
+            }
+            None => res.music.clone(),
+        };
         res.audio.create_music(
-            res.music.clone(),
+            clip,
             MusicParams {
-                amplifier: res.config.volume_music as _,
+                amplifier: Self::music_amplifier(res) as _,
                 playback_rate: res.config.speed as _,
                 ..Default::default()
             },
         )
     }
 
+    /// Drives one frame of update and render with the chart at `time` (in seconds of the chart),
+    /// without polling any input. The scene must be created with `config.interactive` disabled.
+    ///
+    /// Seeking backwards resets the judgement, so frames can be rendered in any order.
+    pub fn render_passive(&mut self, tm: &mut TimeManager, ui: &mut Ui, time: f32) -> Result<()> {
+        if self.res.config.interactive {
+            bail!("passive rendering requires a non-interactive config");
+        }
+        if !matches!(self.state, State::Playing) {
+            // skip the starting animation
+            self.state = State::Playing;
+            self.res.alpha = 1.;
+            self.music.play()?;
+        }
+        let target = time + self.offset();
+        let now = tm.now() as f32;
+        if target < now - PASSIVE_SEEK_TOLERANCE {
+            self.bad_notes.clear();
+            self.judge.reset();
+            self.chart.reset();
+            self.last_combo = 0;
+            self.seek_videos(time);
+        }
+        if (target - now).abs() > PASSIVE_SEEK_TOLERANCE {
+            self.music.seek_to(target.max(0.))?;
+            tm.seek_to(target as f64);
+        }
+        self.update(tm)?;
+        self.render(tm, ui)
+    }
+
+    /// Renders the whole chart into a video file with `ffmpeg`, which must be in `PATH`.
+    ///
+    /// Time advances by exactly `1 / fps` per frame no matter how long a frame takes to render. The music clip is
+    /// muxed as the audio track; hit sounds are not included. The scene must be created with `config.interactive`
+    /// disabled and at normal speed.
+    pub fn render_to_video(&mut self, ui: &mut Ui, path: impl AsRef<Path>, fps: u32, (width, height): (u32, u32)) -> Result<()> {
+        if self.res.config.interactive {
+            bail!("offline rendering requires a non-interactive config");
+        }
+        if self.res.config.speed != 1. {
+            bail!("offline rendering doesn't support speed changes");
+        }
+        let audio_path = std::env::temp_dir().join(format!("phire-render-{}.pcm", std::process::id()));
+        let mut audio = Vec::with_capacity(self.res.music.frames().len() * 8);
+        for frame in self.res.music.frames() {
+            audio.extend_from_slice(&frame.0.to_le_bytes());
+            audio.extend_from_slice(&frame.1.to_le_bytes());
+        }
+        std::fs::write(&audio_path, audio).context("failed to write audio")?;
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba", "-s"])
+            .arg(format!("{width}x{height}"))
+            .arg("-r")
+            .arg(fps.to_string())
+            .args(["-i", "-", "-f", "f32le", "-ac", "2", "-ar"])
+            .arg(self.res.music.sample_rate().to_string())
+            .arg("-i")
+            .arg(&audio_path)
+            // render targets are read bottom-up
+            .args(["-vf", "vflip", "-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac", "-shortest"])
+            .arg(path.as_ref())
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to start ffmpeg")?;
+        let mut stdin = ffmpeg.stdin.take().unwrap();
+
+        self.music.set_amplifier(0.)?;
+        let target = render_target(width, height);
+        let clock = Rc::new(std::cell::Cell::new(0.));
+        let mut tm = TimeManager::manual(Box::new({
+            let clock = Rc::clone(&clock);
+            move || clock.get()
+        }));
+        let old_camera_target = self.res.camera.render_target.replace(target);
+        let old_viewport = std::mem::replace(&mut ui.viewport, (0, 0, width as i32, height as i32));
+        let old_volume_sfx = std::mem::replace(&mut self.res.config.volume_sfx, 0.);
+        let result = (|| -> Result<()> {
+            let offset = self.offset();
+            let frames = (self.res.track_length * fps as f32).ceil() as u64;
+            for frame in 0..frames {
+                let time = frame as f64 / fps as f64;
+                clock.set(time);
+                self.render_passive(&mut tm, ui, time as f32 - offset)?;
+                self.gl.flush();
+                stdin.write_all(&target.texture.get_texture_data().bytes).context("failed to write frame")?;
+            }
+            Ok(())
+        })();
+        drop(stdin);
+        self.res.camera.render_target = old_camera_target;
+        ui.viewport = old_viewport;
+        self.res.config.volume_sfx = old_volume_sfx;
+        target.delete();
+        let status = ffmpeg.wait();
+        let _ = std::fs::remove_file(&audio_path);
+        self.music.set_amplifier(Self::music_amplifier(&self.res))?;
+        result?;
+        if !status?.success() {
+            bail!("ffmpeg exited with an error");
+        }
+        Ok(())
+    }
+
+    /// Renders the chart at each of the given chart times, e.g. for a preview filmstrip.
+    ///
+    /// All frames share one offscreen target. The judge and notes are reset before every capture so that each frame
+    /// shows the chart exactly as it is at that time, whatever the order of `times`; hit particles are not drawn. The
+    /// scene must be created with `config.interactive` disabled and is left at the last captured time.
+    pub fn capture_filmstrip(&mut self, ui: &mut Ui, times: &[f32], (width, height): (u32, u32)) -> Result<Vec<image::RgbaImage>> {
+        if self.res.config.interactive {
+            bail!("capturing frames requires a non-interactive config");
+        }
+        self.music.set_amplifier(0.)?;
+        let target = render_target(width, height);
+        let clock = Rc::new(std::cell::Cell::new(0.));
+        let mut tm = TimeManager::manual(Box::new({
+            let clock = Rc::clone(&clock);
+            move || clock.get()
+        }));
+        let old_camera_target = self.res.camera.render_target.replace(target);
+        let old_viewport = std::mem::replace(&mut ui.viewport, (0, 0, width as i32, height as i32));
+        let old_volume_sfx = std::mem::replace(&mut self.res.config.volume_sfx, 0.);
+        // notes before the capture time are all judged in a single update, their particles would pile up
+        let old_particle = std::mem::replace(&mut self.res.config.particle, false);
+        let result = (|| -> Result<Vec<image::RgbaImage>> {
+            let offset = self.offset();
+            let mut frames = Vec::with_capacity(times.len());
+            for &time in times {
+                self.bad_notes.clear();
+                self.judge.reset();
+                self.chart.reset();
+                self.last_combo = 0;
+                let pos = (time + offset).clamp(0., self.res.track_length);
+                self.music.seek_to(pos)?;
+                self.seek_videos(pos - offset);
+                clock.set(pos as f64);
+                self.render_passive(&mut tm, ui, pos - offset)?;
+                self.gl.flush();
+                let data = target.texture.get_texture_data();
+                let image = image::RgbaImage::from_raw(width, height, data.bytes).ok_or_else(|| anyhow!("invalid frame data"))?;
+                // render targets are read bottom-up
+                frames.push(image::imageops::flip_vertical(&image));
+            }
+            Ok(frames)
+        })();
+        self.res.camera.render_target = old_camera_target;
+        ui.viewport = old_viewport;
+        self.res.config.volume_sfx = old_volume_sfx;
+        self.res.config.particle = old_particle;
+        target.delete();
+        self.music.set_amplifier(Self::music_amplifier(&self.res))?;
+        result
+    }
+
+    /// Seeks to the given beat of the chart on the next update, clamped to the music.
+    pub fn seek_to_beat(&mut self, beat: f32) {
+        let time = self.chart.bpm_list.borrow_mut().time_beats(beat);
+        self.seek_request = Some((time + self.offset()).clamp(0., self.res.track_length));
+    }
+
+    /// The (fractional) beat of the chart at the current time.
+    pub fn current_beat(&self) -> f32 {
+        self.chart.bpm_list.borrow_mut().beat(self.res.time)
+    }
+
+    pub fn judge_snapshot(&self) -> JudgeSnapshot {
+        self.judge.snapshot()
+    }
+
     fn touch_scale(&self) -> f32 {
         (screen_width() / screen_height()) / self.res.aspect_ratio
     }
@@ -479,9 +1220,7 @@ impl GameScene {
                 self.pause_first_time = t;
             } else {
                 self.pause_first_time = f32::NEG_INFINITY;
-                if !self.music.paused() {
-                    self.music.pause()?;
-                }
+                Self::pause_music(&mut self.music, res)?;
                 tm.pause();
             }
         }
@@ -490,32 +1229,31 @@ impl GameScene {
         }
 
         let score = if res.config.roman {
-            Self::int_to_roman(self.judge.score())
+            Self::int_to_roman(self.judge.display_score())
         } else if res.config.chinese {
-            Self::int_to_chinese(self.judge.score())
+            Self::int_to_chinese(self.judge.display_score())
         }
         else {
-            format!("{:07}", self.judge.score())
+            res.config.score_mode.format(self.judge.display_score())
         };
         let score_top = top + eps * 2.2 - (1. - p) * 0.4;
         let ct = ui.text(&score).size(0.8 * aspect_ratio).center();
         ui.text("AA").color(Color::new(0., 0., 0., 0.)).draw(); //Fix first text disappear
         self.chart.with_element(ui, res, UIElement::Score, Some((-ct.x + aspect_ratio - margin, ct.y + score_top)), Some((aspect_ratio - margin + 0.001, top + eps * 2.8125)), |ui, color| {
-            let mut text_size = 0.71 * scale_ratio;
-            let mut text = ui.text(&score).size(text_size);
-            let max_width = 0.55 * aspect_ratio;
-            let text_width = text.measure().w;
-            if text_width > max_width {
-                text_size *= max_width / text_width
-            }
-            drop(text);
+            let mut font = res.chart_font.as_ref().map(|it| it.borrow_mut());
             if res.config.render_ui_score {
-                ui.text(score)
-                    .pos(aspect_ratio - margin + 0.001, top + eps * 2.8125 - (1. - p) * 0.4)
-                    .anchor(1., 0.)
-                    .size(text_size)
-                    .color(Color { a: color.a * c.a, ..color })
-                    .draw();
+                draw_long_number(
+                    ui,
+                    font.as_deref_mut(),
+                    &score,
+                    (aspect_ratio - margin + 0.001, top + eps * 2.8125 - (1. - p) * 0.4),
+                    (1., 0.),
+                    0.71 * scale_ratio,
+                    Color { a: color.a * c.a, ..color },
+                    0.55 * aspect_ratio,
+                    res.config.long_number_mode,
+                    time,
+                );
             }
             if res.config.show_acc {
                 ui.text(format!("{:05.2}%", self.judge.real_time_accuracy() * 100.))
@@ -523,7 +1261,17 @@ impl GameScene {
                     .anchor(1., 0.)
                     .size(0.4 * scale_ratio)
                     .color(Color { a: color.a * c.a * 0.7, ..color })
-                    .draw();
+                    .draw_with_font(font.as_deref_mut());
+            }
+            if let Some(diff) = self.ghost_diff {
+                let y = top + eps * 2.2 - (1. - p) * 0.4 + 0.12 + if res.config.show_acc { 0.05 } else { 0. };
+                let ghost_color = if diff >= 0 { GHOST_AHEAD_COLOR } else { GHOST_BEHIND_COLOR };
+                ui.text(format!("{diff:+}"))
+                    .pos(aspect_ratio - margin, y)
+                    .anchor(1., 0.)
+                    .size(0.4 * scale_ratio)
+                    .color(Color { a: color.a * c.a * 0.7, ..ghost_color })
+                    .draw_with_font(font.as_deref_mut());
             }
         });
         if res.config.render_ui_pause {
@@ -539,46 +1287,64 @@ impl GameScene {
                 ;
             });
         }
-        let unit_h = ui.text("0").size(scale_ratio).measure().h;
+        let unit_h = ui.text("0").size(scale_ratio).measure_with_font(res.chart_font.as_ref().map(|it| it.borrow_mut()).as_deref_mut()).h;
         let combo_y = top + eps * 1.55 - (1. - p) * 0.4;
-        if self.judge.combo() >= 3 && res.config.render_ui_combo {
+        let break_p = (time - self.combo_break.0) / COMBO_BREAK_TIME;
+        // keep the broken combo on screen while it shakes
+        let broken = (0.0..1.).contains(&break_p) && self.judge.combo() < 3;
+        let shown_combo = if broken { self.combo_break.1 } else { self.judge.combo() };
+        if shown_combo >= 3 && res.config.render_ui_combo {
             let combo = if res.config.roman {
-                Self::int_to_roman(self.judge.combo())
+                Self::int_to_roman(shown_combo)
             } else if res.config.chinese {
-                Self::int_to_chinese(self.judge.combo())
+                Self::int_to_chinese(shown_combo)
             }
             else {
-                self.judge.combo().to_string()
+                shown_combo.to_string()
+            };
+            let shake = if broken { COMBO_BREAK_SHAKE * (1. - break_p) * (break_p * COMBO_BREAK_FREQ).sin() } else { 0. };
+            let pop = 1. + COMBO_POP_SCALE * (1. - (time - self.combo_pop_time) / COMBO_POP_TIME).clamp(0., 1.).powi(2);
+            let show_number = res.config.combo_display_mode.shows(shown_combo, res.config.combo_display_step);
+            // the label takes the place of the number when it's hidden
+            let btm = if show_number {
+                self.chart.with_element(ui, res, UIElement::ComboNumber, Some((0., combo_y + unit_h / 2. * 0.98)), Some((0., combo_y + unit_h / 2. * 0.98)), |ui, color| {
+                    let mut font = res.chart_font.as_ref().map(|it| it.borrow_mut());
+                    let text_size = 0.98 * scale_ratio;
+                    let max_width = 0.55 * aspect_ratio;
+                    let text_btm = ui.text(&combo)
+                        .size(text_size)
+                        .color(Color::new(0., 0., 0., 0.))
+                        .pos(0., combo_y)
+                        .anchor(0.5, 0.)
+                        .draw_with_font(font.as_deref_mut())
+                        .bottom();
+                    let color = if broken { Color { a: color.a * (1. - break_p), ..COMBO_BREAK_COLOR } } else { color };
+                    draw_long_number(
+                        ui,
+                        font.as_deref_mut(),
+                        &combo,
+                        (shake, top + eps * 1.30 - (1. - p) * 0.4),
+                        (0.5, 0.),
+                        text_size * pop,
+                        Color { a: color.a * c.a, ..color },
+                        max_width * pop,
+                        res.config.long_number_mode,
+                        time,
+                    );
+                    text_btm
+                })
+            } else {
+                combo_y
             };
-            let btm = self.chart.with_element(ui, res, UIElement::ComboNumber, Some((0., combo_y + unit_h / 2. * 0.98)), Some((0., combo_y + unit_h / 2. * 0.98)), |ui, color| {
-                let mut text_size = 0.98 * scale_ratio;
-                let max_width = 0.55 * aspect_ratio;
-                let mut text = ui.text(&combo)
-                    .size(text_size)
-                    .color(Color::new(0., 0., 0., 0.))
-                    .pos(0., combo_y)
-                    .anchor(0.5, 0.);
-                let text_width = text.measure().w;
-                let text_btm = text.draw().bottom();
-                if text_width > max_width {
-                    text_size *= max_width / text_width
-                }
-                ui.text(&combo)
-                .pos(0., top + eps * 1.30 - (1. - p) * 0.4)
-                .anchor(0.5, 0.)
-                .color(Color { a: color.a * c.a, ..color })
-                .size(text_size)
-                .draw();
-                text_btm
-            });
             self.chart.with_element(ui, res, UIElement::Combo, Some((0., btm + 0.01 + unit_h / 2. * 0.34)), Some((0., btm + 0.01 + unit_h / 2. * 0.34)), |ui, color| {
+                let mut font = res.chart_font.as_ref().map(|it| it.borrow_mut());
                 if validate_combo(&res.config.combo) || res.config.combo.len() > 50 {
                     ui.text("AUTOPLAY")
                     .pos(0., btm + 0.01)
                     .anchor(0.5, 0.)
                     .size(0.34 * scale_ratio)
                     .color(Color { a: color.a * c.a, ..color })
-                    .draw();
+                    .draw_with_font(font.as_deref_mut());
                     return;
                 }
                 ui.text(&res.config.combo)
@@ -586,17 +1352,18 @@ impl GameScene {
                     .anchor(0.5, 0.)
                     .size(0.34 * scale_ratio)
                     .color(Color { a: color.a * c.a, ..color })
-                    .draw();
+                    .draw_with_font(font.as_deref_mut());
             });
         }
         let lf = -aspect_ratio + margin;
         let bt = -top - eps * 3.5;
         if res.config.render_ui_name {
             self.chart.with_element(ui, res, UIElement::Name, Some((lf + ct.x, bt - ct.y)), Some((lf, -top - eps * 2.)), |ui, color| {
+                let mut font = res.chart_font.as_ref().map(|it| it.borrow_mut());
                 let mut text_size = 0.505 * scale_ratio;
                 let mut text = ui.text(&res.info.name).size(text_size);
                 let max_width = 0.9 * aspect_ratio;
-                let text_width = text.measure().w;
+                let text_width = text.measure_with_font(font.as_deref_mut()).w;
                 if text_width > max_width {
                     text_size *= max_width / text_width
                 }
@@ -606,20 +1373,40 @@ impl GameScene {
                     .anchor(0., 1.)
                     .size(text_size)
                     .color(Color { a: color.a * c.a, ..color })
-                    .draw();
+                    .draw_with_font(font.as_deref_mut());
             });
         }
         if res.config.render_ui_level {
             self.chart.with_element(ui, res, UIElement::Level, Some((-lf - ct.x, bt - ct.y)), Some((-lf, -top - eps * 2.)), |ui, color| {
+                let mut font = res.chart_font.as_ref().map(|it| it.borrow_mut());
                 ui.text(&res.info.level)
                     .pos(-lf, bt + (1. - p) * 0.4)
                     .anchor(1., 1.)
                     .size(0.505 * scale_ratio)
                     .color(Color { a: color.a * c.a, ..color })
-                    .draw();
+                    .draw_with_font(font.as_deref_mut());
             });
         }
-        if !res.config.watermark.is_empty() {
+        if let Some(watermark) = &self.watermark {
+            let draw = |y: f32, scale: f32| {
+                let h = WATERMARK_IMAGE_HEIGHT * scale;
+                let w = h * watermark.width() / watermark.height();
+                draw_texture_ex(
+                    **watermark,
+                    -w / 2.,
+                    y - h,
+                    Color::new(1., 1., 1., 0.5 * c.a),
+                    DrawTextureParams {
+                        dest_size: Some(vec2(w, h)),
+                        ..Default::default()
+                    },
+                );
+            };
+            draw(-top * 0.98 + (1. - p) * 0.4, scale_ratio);
+            if res.config.chart_ratio <= 0.95 {
+                draw((-top * 0.98 + (1. - p) * 0.4) / res.config.chart_ratio, scale_ratio / res.config.chart_ratio);
+            }
+        } else if !res.config.watermark.is_empty() {
             ui.text(&res.config.watermark)
                 .pos(0., -top * 0.98 + (1. - p) * 0.4)
                 .anchor(0.5, 1.)
@@ -649,6 +1436,14 @@ impl GameScene {
                 ui.fill_rect(Rect::new(-aspect_ratio + dest - hw, top, hw * 2., height), Color::new(1., 1., 1., color.a * c.a));
             });
         }
+        if res.config.show_note_progress {
+            ui.text(format!("{}/{}", self.judge.judged_count(), self.judge.total_notes()))
+                .pos(0., top + height + 0.01 - (1. - p) * 0.4)
+                .anchor(0.5, 0.)
+                .size(0.35 * scale_ratio)
+                .color(Color::new(1., 1., 1., 0.7 * c.a))
+                .draw();
+        }
         Ok(())
     }
 
@@ -658,6 +1453,105 @@ impl GameScene {
         for pos in &self.touch_points {
             ui.fill_circle(pos.0, pos.1, 0.04, Color { a: 0.4, ..BLUE });
         }
+        if res.config.metronome {
+            let p = 1. - ((res.time - self.metronome_flash.0) / METRONOME_FLASH_TIME).clamp(0., 1.);
+            if p > 0. {
+                let color = if self.metronome_flash.1 { Color::new(1., 0.92, 0.63, p) } else { Color::new(1., 1., 1., p * 0.7) };
+                ui.fill_circle(0.95, ui.top - 0.05, 0.015, color);
+            }
+        }
+        if res.config.hit_error_popup {
+            self.judge.hit_popups.retain(|it| (0.0..HIT_POPUP_TIME).contains(&(res.time - it.0)));
+            if !res.config.autoplay() {
+                let (text_early, text_late) = (tl!("hit-early"), tl!("hit-late"));
+                let sx = if res.config.render_flip_x() { -1. } else { 1. } * res.config.chart_ratio;
+                for (i, (time, pos, late)) in self.judge.hit_popups.iter().enumerate() {
+                    let p = (res.time - time) / HIT_POPUP_TIME;
+                    // stack on top of the earlier popups nearby
+                    let stack = self.judge.hit_popups[..i].iter().filter(|it| (it.1 - pos).norm() < 0.1).count();
+                    let color = if *late { Color::new(1., 0.45, 0.35, 1. - p) } else { Color::new(0.4, 0.7, 1., 1. - p) };
+                    ui.text(if *late { text_late.as_ref() } else { text_early.as_ref() })
+                        .pos(pos.x * sx, -pos.y * res.config.chart_ratio - 0.05 - HIT_POPUP_RISE * p - HIT_POPUP_STACK * stack as f32)
+                        .anchor(0.5, 1.)
+                        .size(0.4)
+                        .color(Color { a: color.a * res.alpha, ..color })
+                        .draw();
+                }
+            }
+        }
+        if res.config.show_hit_ms && !res.config.autoplay() {
+            let sx = if res.config.render_flip_x() { -1. } else { 1. } * res.config.chart_ratio;
+            // the timing meter keeps older entries around, and seeking back leaves some in the future
+            for (time, pos, error) in self.judge.hit_errors.iter().filter(|it| (0.0..HIT_POPUP_TIME).contains(&(res.time - it.0))) {
+                let p = (res.time - time) / HIT_POPUP_TIME;
+                // from gold at no error to red at the edge of good
+                let k = (error.abs() / LIMIT_GOOD).min(1.);
+                let color = Color::new(1., 0.88 - 0.5 * k, 0.45 - 0.15 * k, (1. - p) * res.alpha);
+                ui.text(format!("{:+.0}ms", error * 1000.))
+                    .pos(pos.x * sx, -pos.y * res.config.chart_ratio + 0.05 + HIT_POPUP_RISE * p)
+                    .anchor(0.5, 0.)
+                    .size(0.35)
+                    .color(color)
+                    .draw();
+            }
+        }
+        if res.config.timing_meter && !res.config.autoplay() {
+            let (w, y) = (TIMING_METER_HALF_WIDTH, ui.top - 0.06);
+            let scale = w / LIMIT_GOOD;
+            ui.fill_rect(Rect::new(-w, y - 0.002, w * 2., 0.004), Color::new(1., 1., 1., 0.4 * res.alpha));
+            let perfect = LIMIT_PERFECT * scale;
+            ui.fill_rect(Rect::new(-perfect, y - 0.003, perfect * 2., 0.006), Color::new(1., 0.92, 0.63, 0.5 * res.alpha));
+            for x in [-w, 0., w] {
+                ui.fill_rect(Rect::new(x - 0.001, y - 0.015, 0.002, 0.03), Color::new(1., 1., 1., 0.8 * res.alpha));
+            }
+            for (time, _, error) in &self.judge.hit_errors {
+                let age = res.time - time;
+                if !(0.0..TIMING_METER_FADE).contains(&age) {
+                    continue;
+                }
+                let a = (1. - age / TIMING_METER_FADE) * res.alpha;
+                let color = if error.abs() <= LIMIT_PERFECT { Color::new(1., 0.92, 0.63, a) } else { Color::new(0.4, 0.7, 1., a) };
+                ui.fill_circle(error.clamp(-LIMIT_GOOD, LIMIT_GOOD) * scale, y, 0.008, color);
+            }
+        }
+        if let (Some(since), Some(target)) = (self.accuracy_warning, res.config.accuracy_target) {
+            let flash = 0.6 + 0.4 * ((tm.real_time() - since) as f32 * 8.).cos();
+            ui.text(tl!("accuracy-below-target", "target" => format!("{:.2}%", target * 100.)))
+                .pos(0., -ui.top + 0.2)
+                .anchor(0.5, 0.)
+                .size(0.5)
+                .color(Color::new(1., 0.35, 0.3, flash * res.alpha))
+                .draw();
+        }
+        if res.config.show_fps {
+            let (draw_calls, vertices) = res.note_buffer.borrow_mut().take_stats();
+            Self::render_frame_stats(ui, &mut self.frame_stats, draw_calls, vertices);
+        }
+        if let Some(id) = res.focus_line {
+            let object = &self.chart.lines[id].object;
+            let tr = object.now_translation(res);
+            let scale = object.scale.now_with_def(1., 1.);
+            ui.text(format!(
+                "x:{:.3} y:{:.3} rot:{:.1} a:{:.2} s:{:.2}x{:.2}",
+                tr.x,
+                tr.y,
+                object.rotation.now(),
+                object.now_alpha(),
+                scale.x,
+                scale.y
+            ))
+            .pos(-0.98, ui.top - 0.06)
+            .anchor(0., 1.)
+            .size(0.4)
+            .color(c)
+            .draw();
+            ui.text(format!("line #{id} z:{}", self.chart.lines[id].z_index))
+                .pos(-0.98, ui.top - 0.12)
+                .anchor(0., 1.)
+                .size(0.5)
+                .color(c)
+                .draw();
+        }
         if tm.paused() {
             let o = if self.mode == GameMode::Exercise { -0.3 } else { 0. };
             let s = 0.06;
@@ -683,6 +1577,63 @@ impl GameScene {
                     ..Default::default()
                 },
             );
+            {
+                // chart info panel
+                let tc = if no_retry { semi_white(res.alpha * 0.6) } else { c };
+                let r = Rect::new(-PAUSE_PANEL_WIDTH / 2., o - s - 0.05 - PAUSE_PANEL_HEIGHT, PAUSE_PANEL_WIDTH, PAUSE_PANEL_HEIGHT);
+                ui.fill_rect(r, semi_black(res.alpha * 0.4));
+                let pad = 0.02;
+                let tr = ui
+                    .text(&res.info.name)
+                    .pos(r.x + pad, r.y + pad)
+                    .size(0.55)
+                    .max_width(r.w - pad * 2.)
+                    .color(tc)
+                    .draw();
+                ui.text(format!("{}  {}", res.info.level, res.info.charter))
+                    .pos(r.x + pad, tr.bottom() + 0.01)
+                    .size(0.35)
+                    .max_width(r.w - pad * 2.)
+                    .color(tc)
+                    .draw();
+                let progress = (res.time / res.track_length).clamp(0., 1.);
+                let br = Rect::new(r.x + pad, r.bottom() - pad - 0.006, r.w - pad * 2., 0.006);
+                ui.fill_rect(br, semi_white(res.alpha * 0.3));
+                ui.fill_rect(Rect { w: br.w * progress, ..br }, tc);
+                ui.text(format!("{} / {}", fmt_time(res.time.max(0.)), fmt_time(res.track_length)))
+                    .pos(br.right(), br.y - 0.01)
+                    .anchor(1., 1.)
+                    .size(0.35)
+                    .color(tc)
+                    .draw();
+                if res.config.chart_preset.is_some() {
+                    ui.text(tl!("chart-preset-active"))
+                        .pos(r.right() - pad, r.y + pad)
+                        .anchor(1., 0.)
+                        .size(0.3)
+                        .color(Color::new(1., 0.92, 0.63, res.alpha))
+                        .draw();
+                }
+                if res.config.interactive && PRESET_FN.with(|it| it.borrow().is_some()) {
+                    let br = Rect::new(r.right() + 0.03, r.y, 0.3, 0.07);
+                    let preset = if ui.button("preset_save", br, tl!("chart-preset-save")) {
+                        Some(Some(ChartPreset::of(&res.config)))
+                    } else if res.config.chart_preset.is_some() && ui.button("preset_clear", Rect { y: br.bottom() + 0.02, ..br }, tl!("chart-preset-clear")) {
+                        Some(None)
+                    } else {
+                        None
+                    };
+                    if let Some(preset) = preset {
+                        PRESET_FN.with(|it| {
+                            if let Some(f) = it.borrow_mut().as_mut() {
+                                f(preset);
+                            }
+                        });
+                        show_message(if preset.is_some() { tl!("chart-preset-saved") } else { tl!("chart-preset-cleared") }).ok();
+                        res.config.chart_preset = preset;
+                    }
+                }
+            }
             draw_texture_ex(
                 *res.icon_resume,
                 s + w,
@@ -713,17 +1664,20 @@ impl GameScene {
                 if no_retry && clicked == Some(0) {
                     clicked = None;
                 }
+                if self.mode == GameMode::Practice {
+                    let start = self.chart.offset + self.info_offset + res.config.offset;
+                    if self.exercise_range.start > start && ui.button("from_start", Rect::new(-0.15, o + s + 0.04, 0.3, 0.07), tl!("practice-from-start")) {
+                        self.exercise_range.start = start;
+                        clicked = Some(0);
+                    }
+                }
+                if clicked == Some(1) {
+                    Self::unpause_music(&mut self.music, res, tm.now() as f32)?;
+                }
                 let mut pos = self.music.position();
                 if clicked.map_or(false, |it| it != -1) && (tm.speed - res.config.speed as f64).abs() > 0.01 {
                     debug!("recreating music");
-                    self.music = res.audio.create_music(
-                        res.music.clone(),
-                        MusicParams {
-                            amplifier: res.config.volume_music as _,
-                            playback_rate: res.config.speed as _,
-                            ..Default::default()
-                        },
-                    )?;
+                    self.music = Self::new_music(res)?;
                 }
                 match clicked {
                     Some(-1) => {
@@ -768,17 +1722,37 @@ impl GameScene {
                     _ => {}
                 }
             }
-            { //if self.mode == GameMode::Exercise
+            if self.res.config.interactive { //if self.mode == GameMode::Exercise
                 let asp = self.touch_scale();
                 for touch in ui.ensure_touches() {
                     touch.position *= asp;
                 }
                 if self.mode == GameMode::Exercise {
+                    let base_bpm = self.chart.bpm_list.borrow().dominant_bpm(self.res.track_length);
                     ui.scope(|ui| {
                         ui.dx(0.3);
                         ui.dy(-0.3);
-                        ui.slider(tl!("speed"), 0.5..2.0, 0.05, &mut self.res.config.speed, Some(0.5));
+                        let config = &mut self.res.config;
+                        if config.speed_as_bpm && base_bpm > 0. {
+                            let old = (base_bpm * config.speed).round();
+                            let mut bpm = old;
+                            ui.slider(tl!("speed-bpm"), (base_bpm * 0.5).round()..(base_bpm * 2.).round(), 1., &mut bpm, Some(0.5));
+                            // only write back on changes, the rounded BPM rarely maps back to the exact multiplier
+                            if bpm != old {
+                                config.speed = bpm / base_bpm;
+                            }
+                            ui.text(format!("{:.3}x", config.speed)).pos(0.52, 0.).size(0.4).draw();
+                        } else {
+                            ui.slider(tl!("speed"), 0.5..2.0, 0.05, &mut config.speed, Some(0.5));
+                            if base_bpm > 0. {
+                                ui.text(format!("{:.1} BPM", base_bpm * config.speed)).pos(0.52, 0.).size(0.4).draw();
+                            }
+                        }
                     });
+                    // BPM steps can be finer than 0.01x
+                    if (tm.speed - self.res.config.speed as f64).abs() > 1e-4 {
+                        self.apply_speed(tm)?;
+                    }
                     ui.dy(0.06);
                     let hw = 0.7;
                     let h = 0.06;
@@ -838,6 +1812,12 @@ impl GameScene {
                             if *ctrl == 0 {
                                 tm.seek_to(p as f64);
                                 self.music.seek_to(p)?;
+                                let time = p - (self.chart.offset + res.config.offset + self.info_offset);
+                                for video in &mut self.chart.extra.videos {
+                                    if let Err(err) = video.seek(time) {
+                                        warn!("failed to seek video: {err:?}");
+                                    }
+                                }
                             } else {
                                 *(if *ctrl == -1 {
                                     &mut self.exercise_range.start
@@ -847,6 +1827,16 @@ impl GameScene {
                             }
                             if matches!(touch.phase, TouchPhase::Cancelled | TouchPhase::Ended) {
                                 self.exercise_press = None;
+                                self.seek_preview = None;
+                                self.seek_preview_request = None;
+                            } else {
+                                self.seek_preview_request = Some(p);
+                                if let Some(preview) = &self.seek_preview {
+                                    let x = -hw + (p - sp) / (self.res.track_length - sp) * hw * 2.;
+                                    let (w, h) = (SEEK_PREVIEW_WIDTH, SEEK_PREVIEW_WIDTH * SEEK_PREVIEW_SIZE.1 as f32 / SEEK_PREVIEW_SIZE.0 as f32);
+                                    let r = Rect::new(x - w / 2., -eh - rad * 2. - h, w, h);
+                                    ui.fill_rect(r, (preview.target.output().texture, r));
+                                }
                             }
                         }
                     }
@@ -913,6 +1903,29 @@ impl GameScene {
     }
 
     fn tweak_offset(&mut self, ui: &mut Ui, ita: bool, tm: &mut TimeManager) {
+        if let Some((task, handle)) = &mut self.detect_task {
+            if let Some(result) = task.take() {
+                handle.cancel();
+                self.detect_task = None;
+                match result {
+                    Ok(onset) => {
+                        // align the first onset with the first note of the chart
+                        let first_note = self
+                            .chart
+                            .lines
+                            .iter()
+                            .flat_map(|it| it.notes.iter())
+                            .filter(|it| !it.fake)
+                            .map(|it| it.time)
+                            .reduce(f32::min)
+                            .unwrap_or_default();
+                        self.info_offset = onset - first_note - self.chart.offset - self.res.config.offset;
+                        show_message(tl!("offset-detected", "offset" => (self.info_offset * 1000.).round() as i32)).ok();
+                    }
+                    Err(err) => warn!("failed to detect offset: {err:?}"),
+                }
+            }
+        }
         let width = 0.55;
         let height = 0.3;
         ui.scope(|ui| {
@@ -927,6 +1940,10 @@ impl GameScene {
                 self.next_scene = Some(NextScene::PopWithResult(Box::new(Some(self.info_offset))));
             }
             ui.dx(-width / 1.22);
+            if ui.button("detect", Rect::new(0.02, 0., 0.1, 0.06), tl!("detect-offset")) && ita && self.detect_task.is_none() {
+                let clip = self.res.music.clone();
+                self.detect_task = Some((Task::new(spawn_task(move || Ok(detect_offset(&clip)))), show_message(tl!("detecting-offset")).duration(f32::INFINITY).handle()));
+            }
 
             ui.dy(0.20);
             let r = ui
@@ -938,7 +1955,21 @@ impl GameScene {
                 .draw();
             let d = 0.14;
             let mut bpm_list = self.chart.bpm_list.borrow_mut();
-            let beat = (15. / bpm_list.now_bpm(tm.now() as f32)).clamp(0.020, 0.500);
+            // use chart time so that BPM changes line up with what is heard
+            let bpm = bpm_list.now_bpm(self.res.time);
+            let beats = bpm_list.beat(self.res.time).max(0.);
+            ui.text(tl!(
+                "offset-position",
+                "bpm" => format!("{bpm:.1}"),
+                "bar" => (beats / BEATS_PER_BAR).floor() as u32 + 1,
+                "beat" => (beats % BEATS_PER_BAR).floor() as u32 + 1
+            ))
+            .pos(width / 2., r.bottom() + 0.01)
+            .anchor(0.5, 0.)
+            .size(0.4)
+            .color(semi_white(0.7))
+            .draw();
+            let beat = (15. / bpm).clamp(0.020, 0.500);
             if ui.button("lg_sub", Rect::new(d, r.center().y, 0., 0.).feather(0.026), "-") && ita {
                 self.info_offset -= beat;
             }
@@ -980,26 +2011,291 @@ impl GameScene {
             ui.dx(1. - width * 0.97);
             ui.dy(ui.top - height * 0.75);
             ui.slider(tl!("speed"), 0.1..2.0, 0.05, &mut self.res.config.speed, Some(0.36));
-            if (tm.speed - self.res.config.speed as f64).abs() > 0.01 {
-                debug!("recreate music");
-                self.music = self.res.audio.create_music(
-                    self.res.music.clone(),
-                    MusicParams {
-                        amplifier: self.res.config.volume_music as _,
-                        playback_rate: self.res.config.speed as _,
-                        ..Default::default()
-                    },
-                ).expect("failed to create music");
-                tm.pause();
-                self.music.pause();
-                let now = tm.now();
-                tm.speed = self.res.config.speed as _;
-                tm.seek_to(now);
-                self.music.seek_to(now as f32);
-                tm.resume();
-                self.music.play();
+        });
+        if (tm.speed - self.res.config.speed as f64).abs() > 0.01 {
+            self.apply_speed(tm).expect("failed to create music");
+        }
+    }
+
+    /// Switches the previewed effect preset in View mode. Past the last preset the preview is turned off.
+    fn cycle_preset_preview(preview: &mut Option<(usize, Option<Effect>, bool)>, res: &mut Resource, forward: bool) {
+        let names: Vec<_> = Effect::preset_names().into_iter().chain(Effect::rpe_preset_names()).collect();
+        let len = names.len();
+        let current = preview.as_ref().map_or(len, |it| it.0);
+        let next = if forward { (current + 1) % (len + 1) } else { (current + len) % (len + 1) };
+        let no_effect = preview.as_ref().map_or(res.no_effect, |it| it.2);
+        if next == len {
+            *preview = None;
+            if res.no_effect != no_effect {
+                // drops the chart target again on the next resize
+                res.no_effect = no_effect;
+                res.last_vp = (0, 0, 0, 0);
+            }
+            show_message(tl!("preset-preview-off")).ok();
+            return;
+        }
+        let name = names[next];
+        let shader = Effect::get_preset(name).or_else(|| Effect::get_rpe_preset(name)).unwrap();
+        let effect = match Effect::new(0.0..f32::INFINITY, shader, Vec::new(), Vec::new(), true) {
+            Ok(effect) => {
+                if res.no_effect {
+                    // effects need the chart target, which is created on the next resize
+                    res.no_effect = false;
+                    res.last_vp = (0, 0, 0, 0);
+                }
+                show_message(tl!("preset-preview", "name" => name)).ok();
+                Some(effect)
+            }
+            Err(err) => {
+                warn!("failed to create preset {name}: {err:?}");
+                show_message(tl!("preset-preview-failed", "name" => name)).error();
+                None
             }
+        };
+        *preview = Some((next, effect, no_effect));
+    }
+
+    fn render_ap_aura(ui: &mut Ui, color: Color, t: f32) {
+        let alpha = AP_AURA_ALPHA * (0.75 + 0.25 * (t * 3.).sin());
+        let edge = Color { a: color.a * alpha, ..color };
+        let clear = Color { a: 0., ..color };
+        let w = AP_AURA_WIDTH;
+        ui.fill_rect(Rect::new(-1., -1., 2., w), (edge, (0., -1.), clear, (0., -1. + w)));
+        ui.fill_rect(Rect::new(-1., 1. - w, 2., w), (edge, (0., 1.), clear, (0., 1. - w)));
+        ui.fill_rect(Rect::new(-1., -1., w, 2.), (edge, (-1., 0.), clear, (-1. + w, 0.)));
+        ui.fill_rect(Rect::new(1. - w, -1., w, 2.), (edge, (1., 0.), clear, (1. - w, 0.)));
+    }
+
+    fn render_frame_stats(ui: &mut Ui, stats: &mut FrameStats, draw_calls: usize, vertices: usize) {
+        let (x, y) = (-0.98, -ui.top + 0.14);
+        let (w, h) = (0.5, 0.12);
+        ui.fill_rect(Rect::new(x, y, w, h), Color::new(0., 0., 0., 0.4));
+        // bars are scaled so that the top of the panel means 30 FPS
+        let bar = w / FrameStats::SIZE as f32;
+        for (i, dt) in stats.intervals().enumerate() {
+            let bh = (dt * 30.).min(1.) * h;
+            let color = if dt > 1. / 30. {
+                Color::new(1., 0.3, 0.3, 0.8)
+            } else if dt > 1. / 55. {
+                Color::new(1., 0.85, 0.3, 0.8)
+            } else {
+                Color::new(0.4, 1., 0.5, 0.8)
+            };
+            ui.fill_rect(Rect::new(x + i as f32 * bar, y + h - bh, bar, bh), color);
+        }
+        ui.text(format!(
+            "{:.0} FPS  avg {:.0}  1% {:.0}  {draw_calls} draws  {vertices} verts",
+            stats.current_fps(),
+            stats.average_fps(),
+            stats.low_fps()
+        ))
+        .pos(x, y + h + 0.01)
+        .size(0.4)
+        .draw();
+    }
+
+    /// Renders the chart at `time` (in music time) into the seek preview, at most once per [`SEEK_PREVIEW_INTERVAL`].
+    fn render_seek_preview(&mut self, ui: &mut Ui, real_time: f64, time: f32) {
+        if let Some(preview) = &self.seek_preview {
+            if (preview.time - time).abs() < 1e-3 || real_time - preview.last_render < SEEK_PREVIEW_INTERVAL {
+                return;
+            }
+        }
+        let offset = self.offset();
+        let res = &mut self.res;
+        let preview = self.seek_preview.get_or_insert_with(|| SeekPreview {
+            target: MSRenderTarget::new(SEEK_PREVIEW_SIZE, res.config.sample_count.max(1)),
+            time: f32::NAN,
+            last_render: f64::NEG_INFINITY,
+        });
+        preview.time = time;
+        preview.last_render = real_time;
+        let old_time = res.time;
+        res.time = (time - offset).max(0.);
+        // only the animations are moved, updating would judge holds, emit particles and fire effect callbacks
+        self.chart.set_time(res.time);
+        // keep the chart from resolving into the main target
+        let chart_target = res.chart_target.take();
+        set_camera(&Camera2D {
+            zoom: vec2(1., -SEEK_PREVIEW_SIZE.0 as f32 / SEEK_PREVIEW_SIZE.1 as f32),
+            render_target: Some(preview.target.input()),
+            ..Default::default()
         });
+        clear_background(BLACK);
+        draw_background(*res.background, res.config.background_mode, true);
+        self.chart.render(ui, res);
+        unsafe { get_internal_gl() }.flush();
+        preview.target.blit();
+        res.chart_target = chart_target;
+        res.time = old_time;
+        self.chart.set_time(old_time);
+    }
+
+    fn update_bg_parallax(&mut self) {
+        let res = &self.res;
+        let strength = res.config.bg_parallax.clamp(0., 1.);
+        let (last_time, offset) = &mut self.bg_parallax;
+        if strength <= 0. {
+            *offset = Vec2::ZERO;
+        } else {
+            // the background drifts the way the visible lines go on average, as if it were far behind them
+            let (sum, count) = self
+                .chart
+                .lines
+                .iter()
+                .filter(|it| it.object.now_alpha() > 0.)
+                .fold((Vec2::ZERO, 0), |(sum, count), line| {
+                    let tr = line.object.translation.now();
+                    (sum + vec2(tr.x, -tr.y), count + 1)
+                });
+            let target = if count == 0 {
+                Vec2::ZERO
+            } else {
+                (sum / count as f32).clamp(Vec2::splat(-1.), Vec2::splat(1.)) * strength * BG_PARALLAX_RANGE
+            };
+            // seeking backwards or jumping far shouldn't throw the background around
+            let dt = (res.time - *last_time).clamp(0., 0.1);
+            *offset += (target - *offset) * (1. - (-dt * BG_PARALLAX_FOLLOW).exp());
+        }
+        *last_time = res.time;
+    }
+
+    fn seek_videos(&mut self, time: f32) {
+        for video in &mut self.chart.extra.videos {
+            if let Err(err) = video.seek(time) {
+                warn!("failed to seek video: {err:?}");
+            }
+        }
+    }
+
+    fn update_metronome(&mut self, time: f32) {
+        let sub = self.res.config.metronome_subdivision.max(1) as i64;
+        // recomputed from the BpmList every frame so that BPM changes are followed
+        let beat = self.chart.bpm_list.borrow_mut().beat(time);
+        let tick = (beat * sub as f32).floor() as i64;
+        if tick > self.metronome_tick {
+            let downbeat = tick % (sub * 4) == 0;
+            let amplifier = if downbeat {
+                1.
+            } else if tick % sub == 0 {
+                0.6
+            } else {
+                0.35
+            };
+            let res = &mut self.res;
+            if !res.config.disable_audio && res.config.volume_sfx > 1e-2 {
+                let _ = res.sfx_click.play(PlaySfxParams {
+                    amplifier: res.config.volume_sfx * amplifier,
+                });
+            }
+            self.metronome_flash = (time, downbeat);
+        }
+        self.metronome_tick = tick;
+    }
+
+    fn check_accuracy_target(&mut self, tm: &mut TimeManager) -> Result<()> {
+        let Some(target) = self.res.config.accuracy_target else {
+            return Ok(());
+        };
+        let judged: u32 = self.judge.counts().iter().sum();
+        let below = !self.res.config.autoplay() && judged >= ACCURACY_TARGET_MIN_NOTES && self.judge.real_time_accuracy() < target as f64;
+        if !below {
+            self.accuracy_warning = None;
+        } else if self.accuracy_warning.is_none() {
+            self.accuracy_warning = Some(tm.real_time());
+            if self.res.config.accuracy_target_pause && matches!(self.state, State::Playing) && !tm.paused() {
+                Self::pause_music(&mut self.music, &self.res)?;
+                tm.pause();
+            }
+        }
+        Ok(())
+    }
+
+    fn update_combo(&mut self, now: f32) {
+        let combo = self.judge.combo();
+        let last = std::mem::replace(&mut self.last_combo, combo);
+        // judges are only reset on retries and seeks, which reset `last_combo` as well
+        if self.res.config.combo_break_feedback && last >= 3 && combo < 3 && combo < last && matches!(self.state, State::Playing) {
+            self.combo_break = (now, last);
+            if let Some(sfx) = &mut self.res.sfx_combo_break {
+                play_sfx(sfx, &self.res.config);
+            }
+        }
+        if !self.res.config.combo_milestones.is_empty() {
+            self.update_combo_milestones(now, last, combo);
+        }
+    }
+
+    fn update_combo_milestones(&mut self, now: f32, last: u32, combo: u32) {
+        // combo can grow by more than one in a single frame, so check the whole range
+        if combo <= last || !self.res.config.combo_milestones.iter().any(|it| (last + 1..=combo).contains(it)) {
+            return;
+        }
+        self.combo_pop_time = now;
+        let res = &mut self.res;
+        if let Some(emitter) = res.emitter.as_mut().filter(|_| res.config.particle_enabled()) {
+            // same position as the combo number in `ui`, converted to chart coordinates
+            let y = (-1. + 2e-2 * 1.55) * res.config.chart_ratio / res.aspect_ratio;
+            let color = Color::from_hex(res.res_pack.info.color_perfect);
+            for i in 0..COMBO_BURST_COUNT {
+                let x = (i as f32 / (COMBO_BURST_COUNT - 1) as f32 - 0.5) * 0.2;
+                emitter.emit_at(vec2(x, y), 0., color);
+            }
+        }
+    }
+
+    /// Ramps the speed towards `config.finale_slowmo` near the end of the music, restoring it when seeking back.
+    fn update_finale_slowmo(&mut self, tm: &mut TimeManager) -> Result<()> {
+        let Some(target) = self.res.config.finale_slowmo else {
+            return Ok(());
+        };
+        // never slow down scored play
+        if self.mode != GameMode::View && self.res.config.interactive {
+            return Ok(());
+        }
+        let base = *self.finale_base_speed.get_or_insert(self.res.config.speed);
+        let p = ((tm.now() as f32 - (self.res.track_length - FINALE_SLOWMO_DURATION)) / FINALE_SLOWMO_DURATION).clamp(0., 1.);
+        let factor = 1. + (target.clamp(0., 1.) - 1.) * p;
+        let speed = ((base * factor / FINALE_SLOWMO_STEP).round() * FINALE_SLOWMO_STEP).max(MIN_LIVE_SPEED);
+        if (speed - self.res.config.speed).abs() > FINALE_SLOWMO_STEP / 2. {
+            self.res.config.speed = speed;
+            self.apply_speed(tm)?;
+        }
+        Ok(())
+    }
+
+    /// Recreates the music with the new playback rate, keeping the current position.
+    fn apply_speed(&mut self, tm: &mut TimeManager) -> Result<()> {
+        debug!("recreate music");
+        if self.res.config.speed < MIN_LIVE_SPEED {
+            self.res.config.speed = MIN_LIVE_SPEED;
+            show_message(tl!("speed-clamped", "min" => MIN_LIVE_SPEED)).warn();
+        }
+        let playing = !self.music.paused();
+        let pos = self.music.position();
+        let mut music = Self::new_music(&mut self.res)?;
+        music.seek_to(pos)?;
+        let mut old = std::mem::replace(&mut self.music, music);
+        if playing {
+            let _ = old.fade_out(SPEED_CROSSFADE);
+            self.old_music = Some((old, tm.real_time() + SPEED_CROSSFADE as f64));
+            self.music.play()?;
+            let _ = self.music.fade_in(SPEED_CROSSFADE);
+        }
+        let now = tm.now();
+        tm.speed = self.res.config.speed as _;
+        tm.seek_to(now);
+        Ok(())
+    }
+}
+
+impl Drop for GameScene {
+    fn drop(&mut self) {
+        // the preset callback belongs to the chart being played, it may already be gone when the thread exits
+        let _ = PRESET_FN.try_with(|it| *it.borrow_mut() = None);
+        for target in self.ui_targets.iter().flatten() {
+            target.delete();
+        }
     }
 }
 
@@ -1017,6 +2313,7 @@ impl Scene for GameScene {
         Ok(())
     }
 
+    /// Called by the window's pause/resume listener when it loses focus. Resuming is left to the player.
     fn pause(&mut self, tm: &mut TimeManager) -> Result<()> {
         if !tm.paused() {
             self.pause_rewind = PauseRewind {
@@ -1024,7 +2321,7 @@ impl Scene for GameScene {
                 duration: None,
                 dim: false
             };
-            self.music.pause()?;
+            Self::pause_music(&mut self.music, &self.res)?;
             tm.pause();
         }
         Ok(())
@@ -1039,14 +2336,30 @@ impl Scene for GameScene {
 
     fn update(&mut self, tm: &mut TimeManager) -> Result<()> {
         self.res.audio.recover_if_needed()?;
+        if let Some(dst) = self.seek_request.take() {
+            self.music.seek_to(dst)?;
+            tm.seek_to(dst as f64);
+            self.seek_videos(dst - self.offset());
+            self.pause_rewind = PauseRewind {
+                time: Some(tm.now()),
+                duration: Some(0.1),
+                dim: false,
+            };
+            self.res.config.disable_audio = true;
+        }
         if matches!(self.state, State::Playing) {
             tm.update(self.music.position() as f64);
+            // stays paused on refocus, the player resumes it themselves
+            if self.res.config.pause_on_blur && self.res.config.interactive && !tm.paused() && !window_focused() {
+                self.pause(tm)?;
+            }
         }
         if self.mode == GameMode::Exercise && tm.now() > self.exercise_range.end as f64 && !tm.paused() {
             let state = self.state.clone();
             reset!(self, self.res, tm);
             self.state = state;
             tm.seek_to(self.exercise_range.start as f64);
+            self.seek_videos(self.exercise_range.start - self.offset());
             tm.pause();
             self.music.pause()?;
         }
@@ -1092,7 +2405,16 @@ impl Scene for GameScene {
                     // TODO strengthen the protection
                     #[cfg(feature = "closed")]
                     if let Some(upload_fn) = &self.upload_fn {
-                        if !self.res.config.offline_mode && !self.res.config.autoplay() && self.res.config.speed >= 1.0 - 1e-3 {
+                        if !self.res.config.offline_mode
+                            && !self.res.config.autoplay()
+                            && !self.autoplay_used
+                            && self.res.config.interactive
+                            && !self.res.config.lenient_position
+                            && !self.res.config.forgiving_holds
+                            && self.res.config.simulated_input_latency_ms <= 0.
+                            && self.res.config.speed >= 1.0 - 1e-3
+                            && self.mode != GameMode::Practice
+                        {
                             if let Some(player) = &self.player {
                                 if let Some(chart) = &self.res.info.id {
                                     record_data = Some(encode_record(self, player.id, *chart));
@@ -1101,7 +2423,15 @@ impl Scene for GameScene {
                         }
                     }
                     let result = self.judge.result();
-                    let record = if self.res.config.autoplay() || self.res.config.speed < 1.0 - 1e-3 {
+                    let record = if self.res.config.autoplay()
+                        || self.autoplay_used
+                        || !self.res.config.interactive
+                        || self.res.config.lenient_position
+                        || self.res.config.forgiving_holds
+                        || self.res.config.simulated_input_latency_ms > 0.
+                        || self.res.config.speed < 1.0 - 1e-3
+                        || self.mode == GameMode::Practice
+                    {
                         None
                     } else {
                         Some(SimpleRecord {
@@ -1111,7 +2441,7 @@ impl Scene for GameScene {
                         })
                     };
                     self.next_scene = match self.mode {
-                        GameMode::Normal | GameMode::NoRetry | GameMode::View => Some(NextScene::Overlay(Box::new(EndingScene::new(
+                        GameMode::Normal | GameMode::NoRetry | GameMode::View | GameMode::Practice => Some(NextScene::Overlay(Box::new(EndingScene::new(
                             self.res.background.clone(),
                             self.res.illustration.clone(),
                             self.res.player.clone(),
@@ -1136,6 +2466,7 @@ impl Scene for GameScene {
                 self.res.track_length
             }
         };
+        self.apply_scene_fade(tm, time);
 
         let time = if self.mode == GameMode::TweakOffset {
             time.max(0.)
@@ -1145,16 +2476,39 @@ impl Scene for GameScene {
             (time - offset).max(0.)
         };
         self.res.time = time;
+        if self.res.config.metronome && matches!(self.state, State::Playing) && !tm.paused() {
+            self.update_metronome(time);
+        }
+        if self.res.config.finale_slowmo.is_some() && matches!(self.state, State::Playing) && !tm.paused() {
+            self.update_finale_slowmo(tm)?;
+        }
         if !tm.paused() /*&& self.pause_rewind.is_none()*/ && self.mode != GameMode::View {
+            if !self.key_bindings.is_empty() {
+                self.judge.feed_key_touches(self.key_touches());
+            }
             self.gl.quad_gl.viewport(self.res.camera.viewport);
             self.judge.update(&mut self.res, &mut self.chart, &mut self.bad_notes);
             self.gl.quad_gl.viewport(None);
         }
+        if matches!(self.state, State::Playing) {
+            let score = self.judge.score();
+            self.replay.push(time, score);
+            self.ghost_diff = self.ghost.as_ref().map(|ghost| score as i64 - ghost.score_at(time) as i64);
+        }
+        self.update_combo(tm.now() as f32);
+        self.check_accuracy_target(tm)?;
         if let Some(update) = &mut self.update_fn {
             update(self.res.time, &mut self.res, &mut self.judge);
         }
+        STATS_FN.with(|it| {
+            if let Some(f) = it.borrow_mut().as_mut() {
+                f(self.res.time, &self.judge.live_stats());
+            }
+        });
         let counts = self.judge.counts();
-        self.res.judge_line_color = if counts[2] + counts[3] == 0 {
+        self.res.judge_line_color = if !self.res.config.judge_line_feedback {
+            WHITE
+        } else if counts[2] + counts[3] == 0 {
             Color::from_hex(if counts[1] == 0 {
                 self.res.res_pack.info.color_perfect_line
             } else {
@@ -1164,7 +2518,16 @@ impl Scene for GameScene {
             WHITE
         };
         self.res.judge_line_color.a *= self.res.alpha;
+        if self.res.config.reactive_lines {
+            self.res.audio_level = match &self.audio_envelope {
+                // the envelope is indexed by the position in the music, so it can't lag behind
+                Some(envelope) => envelope.get((tm.now() * ENVELOPE_WINDOWS_PER_SEC as f64).max(0.) as usize).copied().unwrap_or_default(),
+                // nothing to analyze, pulse on every beat instead
+                None => (1. - self.chart.bpm_list.borrow_mut().beat(self.res.time).rem_euclid(1.)).powi(2),
+            };
+        }
         self.chart.update(&mut self.res);
+        self.update_bg_parallax();
         let res = &mut self.res;
         if !tm.paused() {
             for video in &mut self.chart.extra.videos {
@@ -1173,9 +2536,40 @@ impl Scene for GameScene {
                 }
             }
         }
+        if self.mode == GameMode::View && res.config.interactive {
+            // cycle through lines, `None` sits after the last one
+            let len = self.chart.lines.len();
+            let current = res.focus_line.unwrap_or(len);
+            let next = if is_key_pressed(KeyCode::PageDown) {
+                Some((current + 1) % (len + 1))
+            } else if is_key_pressed(KeyCode::PageUp) {
+                Some((current + len) % (len + 1))
+            } else {
+                None
+            };
+            if let Some(next) = next {
+                res.focus_line = if next == len { None } else { Some(next) };
+            }
+            if is_key_pressed(KeyCode::G) {
+                res.config.beat_grid ^= true;
+            }
+            if is_key_pressed(KeyCode::RightBracket) {
+                Self::cycle_preset_preview(&mut self.preset_preview, res, true);
+            } else if is_key_pressed(KeyCode::LeftBracket) {
+                Self::cycle_preset_preview(&mut self.preset_preview, res, false);
+            }
+            if self.autoplay_toggle_key.map_or(false, is_key_pressed) {
+                res.config.mods.toggle(Mods::AUTOPLAY);
+                // notes already judged stay judged, only input tracked under the previous mode is dropped
+                self.judge.reset_input(res);
+                self.autoplay_used |= res.config.autoplay();
+                show_message(if res.config.autoplay() { tl!("autoplay-on") } else { tl!("autoplay-off") }).ok();
+            }
+        }
         if res.config.interactive && is_key_pressed(KeyCode::Space) {
             if tm.paused() {
                 if matches!(self.state, State::Playing) {
+                    Self::unpause_music(&mut self.music, res, tm.now() as f32)?;
                     self.music.play()?;
                     tm.resume();
                     self.pause_rewind = PauseRewind {
@@ -1186,9 +2580,7 @@ impl Scene for GameScene {
                     res.config.disable_audio = true;
                 }
             } else if matches!(self.state, State::Playing) && !self.pause_rewind.dim { // State::BeforeMusic
-                if !self.music.paused() {
-                    self.music.pause()?;
-                }
+                Self::pause_music(&mut self.music, res)?;
                 self.pause_rewind = PauseRewind {
                     time: None,
                     duration: None,
@@ -1221,9 +2613,15 @@ impl Scene for GameScene {
                 self.should_exit = true;
             }
         }
+        if self.old_music.as_ref().map_or(false, |it| tm.real_time() >= it.1) {
+            self.old_music = None;
+        }
         for effect in &mut self.effects {
             effect.update(&self.res);
         }
+        if let Some((_, Some(effect), _)) = &mut self.preset_preview {
+            effect.update(&self.res);
+        }
         if let Some((id, text)) = take_input() {
             let offset = self.offset().min(0.);
             match id.as_str() {
@@ -1258,7 +2656,7 @@ impl Scene for GameScene {
     }
 
     fn touch(&mut self, tm: &mut TimeManager, touch: &Touch) -> Result<bool> {
-        if self.mode == GameMode::Exercise && tm.paused() {
+        if self.mode == GameMode::Exercise && tm.paused() && self.res.config.interactive {
             let touch = Touch {
                 position: touch.position * self.touch_scale(),
                 ..touch.clone()
@@ -1276,6 +2674,9 @@ impl Scene for GameScene {
     }
 
     fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
+        if let Some(time) = self.seek_preview_request {
+            self.render_seek_preview(ui, tm.real_time(), time);
+        }
         let res = &mut self.res;
 
         let time = tm.now() as f32;
@@ -1334,17 +2735,41 @@ impl Scene for GameScene {
         });
         if res.config.render_bg {
             clear_background(BLACK);
-            draw_background(*res.background, res.config.render_bg_dim);
+        }
+        if res.config.render_bg && !res.config.minimal_render {
+            let margin = res.config.bg_parallax.clamp(0., 1.) * BG_PARALLAX_RANGE;
+            draw_background_shifted(*res.background, res.config.background_mode, res.config.render_bg_dim, self.bg_parallax.1, margin);
         }
 
-        if res.config.render_bg_dim && res.config.chart_ratio >= 1. {
+        if res.config.render_bg_dim && res.config.chart_ratio >= 1. && !res.config.minimal_render {
             let dim_alpha = 0.7;
             //let alpha = res.alpha * (1. - dim_alpha) + dim_alpha;    
             let dim = Color::new(0.1, 0.1, 0.1, dim_alpha * res.alpha);
             let x_range = vp.0 as f32 / ui.viewport.2 as f32;
-            draw_rectangle(-1., -h,x_range * 2., h * 2., dim);
-            draw_rectangle(1., -h,-x_range * 2., h * 2., dim);
-            draw_rectangle(x_range * 2. - 1., -h, (1. - x_range * 2.) * 2., h * 2., Color::new(0., 0., 0., res.alpha * res.info.background_dim));
+            let bars = [Rect::new(-1., -h, x_range * 2., h * 2.), Rect::new(1. - x_range * 2., -h, x_range * 2., h * 2.)];
+            if let Some(letterbox) = &self.letterbox {
+                for bar in bars {
+                    draw_texture_ex(
+                        **letterbox,
+                        bar.x,
+                        bar.y,
+                        Color::new(1., 1., 1., res.alpha),
+                        DrawTextureParams {
+                            dest_size: Some(vec2(bar.w, bar.h)),
+                            ..Default::default()
+                        },
+                    );
+                }
+            } else {
+                let color = res.config.letterbox_color.map_or(dim, |it| {
+                    let color = Color::from_hex(it);
+                    Color { a: color.a * res.alpha, ..color }
+                });
+                for bar in bars {
+                    draw_rectangle(bar.x, bar.y, bar.w, bar.h, color);
+                }
+            }
+            draw_rectangle(x_range * 2. - 1., -h, (1. - x_range * 2.) * 2., h * 2., Color::new(0., 0., 0., res.alpha * res.background_dim()));
         }
 
         set_camera( &Camera2D {
@@ -1355,8 +2780,8 @@ impl Scene for GameScene {
         
         self.gl.quad_gl.render_pass(chart_onto.map(|it| it.render_pass));
         //self.gl.quad_gl.viewport(chart_target_vp);
-        if res.config.render_bg_dim && res.config.chart_ratio < 1. {
-            draw_rectangle(-1., -h, 2., h * 2., Color::new(0., 0., 0., res.alpha * res.info.background_dim));
+        if res.config.render_bg_dim && res.config.chart_ratio < 1. && !res.config.minimal_render {
+            draw_rectangle(-1., -h, 2., h * 2., Color::new(0., 0., 0., res.alpha * res.background_dim()));
         }
         self.chart.render(ui, res);
 
@@ -1370,36 +2795,35 @@ impl Scene for GameScene {
         self.bad_notes.retain(|dummy| dummy.render(res));
         let t = tm.real_time();
         let dt = (t - std::mem::replace(&mut self.last_update_time, t)) as f32;
-        if res.config.particle {
-            res.emitter.draw(dt);
+        if let Some(emitter) = res.emitter.as_mut().filter(|_| res.config.particle_enabled()) {
+            emitter.draw(dt);
         }
 
-        if !res.no_effect {
+        if !res.no_effect && !res.config.minimal_render {
             set_camera(&Camera2D {
                 zoom: vec2(1., asp2_chart),
                 ..Default::default()
             });
-            for effect in &self.chart.extra.effects {
+            // layered effects are already rendered along with the chart
+            for effect in self.chart.extra.effects.iter().filter(|it| it.z_index.is_none()) {
                 effect.render(res);
             }
         }
         
         {
-            set_camera(&Camera2D {
-                zoom: if res.config.chart_ratio < 1. { vec2(asp2_ui_window * ratio, -1. * ratio) } else { vec2(asp2_ui * ratio, -1. * ratio) },
-                viewport: if res.config.chart_ratio < 1. { viewport_window } else { viewport_chart },
-                render_target: self.res.chart_target.as_ref().map(|it| it.output()).or(self.res.camera.render_target),
-                ..Default::default()
-            });
-            self.ui(ui, tm)?;
+            let zoom = if res.config.chart_ratio < 1. { vec2(asp2_ui_window * ratio, -1. * ratio) } else { vec2(asp2_ui * ratio, -1. * ratio) };
+            let viewport = if res.config.chart_ratio < 1. { viewport_window } else { viewport_chart };
+            let onto = self.res.chart_target.as_ref().map(|it| it.output()).or(self.res.camera.render_target);
+            self.ui_pass(0, ui, tm, zoom, viewport, onto, Self::ui)?;
         }
 
-        if !self.res.no_effect && !self.effects.is_empty() {
+        let preview = self.preset_preview.as_ref().and_then(|it| it.1.as_ref());
+        if !self.res.no_effect && !self.res.config.minimal_render && (!self.effects.is_empty() || preview.is_some()) {
             set_camera(&Camera2D {
                 zoom: vec2(1., asp2_window),
                 ..Default::default()
             });
-            for effect in &self.effects {
+            for effect in self.effects.iter().chain(preview) {
                 effect.render(&mut self.res);
             }
         }
@@ -1411,6 +2835,12 @@ impl Scene for GameScene {
                 render_target: self.res.chart_target.as_ref().map(|it| it.output()).or(self.res.camera.render_target),
                 ..Default::default()
             });
+            let counts = self.judge.counts();
+            if self.res.config.ap_aura && counts[0] != 0 && counts[1] + counts[2] + counts[3] == 0 {
+                let mut color = Color::from_hex(self.res.res_pack.info.color_perfect_line);
+                color.a *= self.res.alpha;
+                Self::render_ap_aura(ui, color, tm.real_time() as f32);
+            }
             if tm.paused() {
                 draw_rectangle(-1., -1., 2., 2., Color::new(0., 0., 0., 0.6));
             }
@@ -1427,23 +2857,45 @@ impl Scene for GameScene {
                 self.tweak_offset(ui, Self::interactive(&self.res, &self.state), tm);
             }
             if self.res.config.touch_debug {
-                for touch in Judge::get_touches(1.0) {
+                let touches = Judge::get_touches(1.0);
+                self.touch_trails.retain(|id, _| touches.iter().any(|it| it.id == *id));
+                for touch in &touches {
+                    if matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+                        self.touch_trails.remove(&touch.id);
+                    } else {
+                        let trail = self.touch_trails.entry(touch.id).or_default();
+                        if trail.len() == TOUCH_TRAIL_LENGTH {
+                            trail.pop_front();
+                        }
+                        trail.push_back(touch.position);
+                    }
                     ui.fill_circle(touch.position.x, touch.position.y, 0.04, Color { a: 0.4, ..RED });
                 }
+                for trail in self.touch_trails.values() {
+                    for (i, (a, b)) in trail.iter().zip(trail.iter().skip(1)).enumerate() {
+                        let alpha = (i + 1) as f32 / trail.len() as f32;
+                        draw_line(a.x, a.y, b.x, b.y, 0.01, Color { a: 0.6 * alpha, ..RED });
+                    }
+                }
+            } else if !self.touch_trails.is_empty() {
+                self.touch_trails.clear();
             }
         }
         
         {
+            let onto = self.res.chart_target.as_ref().map(|it| it.output()).or(self.res.camera.render_target);
+            self.ui_pass(1, ui, tm, vec2(1., -asp2_chart), viewport_chart, onto, Self::overlay_ui)?;
+        }
+
+        if let Some(filter) = self.color_filter.as_ref().filter(|_| self.res.chart_target.is_some()) {
             set_camera(&Camera2D {
-                zoom: vec2(1., -asp2_chart),
-                viewport: viewport_chart,
-                render_target: self.res.chart_target.as_ref().map(|it| it.output()).or(self.res.camera.render_target),
+                zoom: vec2(1., asp2_window),
                 ..Default::default()
             });
-            self.overlay_ui(ui, tm)?;
+            filter.render(&mut self.res);
         }
 
-        if msaa || !self.res.no_effect {
+        if msaa || !self.res.no_effect || self.color_filter.is_some() {
             // render the texture onto screen
             if let Some(target) = &self.res.chart_target {
                 self.gl.flush();
@@ -1472,6 +2924,9 @@ impl Scene for GameScene {
         if self.res.config.adjust_time {
             push_frame_time(&mut self.res.frame_times, tm.real_time());
         }
+        if self.res.config.show_fps {
+            self.frame_stats.push(tm.real_time());
+        }
         
         Ok(())
     }
@@ -1483,8 +2938,12 @@ impl Scene for GameScene {
             }
             tm.speed = 1.0;
             tm.adjust_time = false;
+            let time = tm.now() as f32;
             match self.mode {
-                GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View => NextScene::Pop,
+                GameMode::Normal | GameMode::Practice if matches!(self.state, State::Playing) && time > PRACTICE_LEAD => {
+                    NextScene::PopWithResult(Box::new(ResumePoint(time)))
+                }
+                GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View | GameMode::Practice => NextScene::Pop,
                 GameMode::TweakOffset => NextScene::PopWithResult(Box::new(None::<f32>)),
             }
         } else if let Some(next_scene) = self.next_scene.take() {
@@ -2,7 +2,7 @@ use super::{draw_background, ending::RecordUpdateState, game::GameMode, GameScen
 use crate::{
     config::Config,
     core::Resource,
-    ext::{draw_illustration, draw_parallelogram, draw_text_aligned, draw_text_aligned_fix, poll_future, LocalTask, SafeTexture, BLACK_TEXTURE},
+    ext::{draw_illustration, draw_parallelogram, draw_text_aligned, draw_text_aligned_fix, level_color_with, poll_future, LocalTask, SafeTexture, BLACK_TEXTURE},
     fs::FileSystem,
     info::ChartInfo,
     judge::Judge,
@@ -14,7 +14,7 @@ use ::rand::{seq::SliceRandom, thread_rng};
 use anyhow::{Context, Result};
 use macroquad::prelude::*;
 use regex::Regex;
-use std::sync::Arc;
+use std::{cell::Cell, rc::Rc, sync::Arc};
 use tracing::warn;
 
 const BEFORE_TIME: f32 = 1.;
@@ -23,6 +23,8 @@ const WAIT_TIME: f32 = 0.;
 
 pub type UploadFn = Arc<dyn Fn(Vec<u8>) -> Task<Result<RecordUpdateState>>>;
 pub type UpdateFn = Box<dyn FnMut(f32, &mut Resource, &mut Judge)>;
+/// Receives the loading progress (0..1) and the current phase: `video`, `parsing`, `textures` or `audio`.
+pub type ProgressFn = Box<dyn FnMut(f32, &str)>;
 
 pub struct BasicPlayer {
     pub avatar: Option<SafeTexture>,
@@ -40,6 +42,7 @@ pub struct LoadingScene {
     finish_time: f32,
     target: Option<RenderTarget>,
     charter: String,
+    progress: Rc<Cell<f32>>,
 }
 
 impl LoadingScene {
@@ -53,10 +56,16 @@ impl LoadingScene {
         player: Option<BasicPlayer>,
         upload_fn: Option<UploadFn>,
         update_fn: Option<UpdateFn>,
+        mut progress_fn: Option<ProgressFn>,
+        practice_start: Option<f32>,
     ) -> Result<Self> {
-        async fn load(fs: &mut Box<dyn FileSystem>, config: &Config, path: &str) -> Result<(Texture2D, Texture2D)> {
+        /// Returns the illustration and the blurred background, which is `None` if the illustration should be used directly.
+        async fn load(fs: &mut Box<dyn FileSystem>, config: &Config, path: &str) -> Result<(Texture2D, Option<Texture2D>)> {
             let image = image::load_from_memory(&fs.load_file(path).await?).context("Failed to decode image")?;
             let (w, h) = (image.width(), image.height());
+            if !config.blur_background {
+                return Ok((Texture2D::from_rgba8(w as _, h as _, &image.into_rgba8()), None));
+            }
             let size = w as usize * h as usize;
 
             let mut blurred_rgb = image.to_rgb8();
@@ -73,11 +82,11 @@ impl LoadingScene {
             }
             Ok((
                 Texture2D::from_rgba8(w as _, h as _, &image.into_rgba8()),
-                Texture2D::from_image(&Image {
+                Some(Texture2D::from_image(&Image {
                     width: w as _,
                     height: h as _,
                     bytes: blurred,
-                }),
+                })),
             ))
         }
 
@@ -89,12 +98,38 @@ impl LoadingScene {
             }
         };
         let (illustration, background): (SafeTexture, SafeTexture) = background
-            .map(|(ill, back)| (ill.into(), back.into()))
+            .map(|(ill, back)| {
+                let ill: SafeTexture = ill.into();
+                let back = back.map_or_else(|| ill.clone(), SafeTexture::from);
+                (ill, back)
+            })
             .unwrap_or_else(|| (BLACK_TEXTURE.clone(), BLACK_TEXTURE.clone()));
         if info.tip.is_none() {
             info.tip = Some(crate::config::TIPS.choose(&mut thread_rng()).unwrap().to_owned());
         }
-        let future = Box::pin(GameScene::new(mode, info.clone(), config.clone(), fs, player, background.clone(), illustration.clone(), upload_fn, update_fn));
+        let progress = Rc::new(Cell::new(0.));
+        let report: ProgressFn = {
+            let progress = Rc::clone(&progress);
+            Box::new(move |fraction, phase| {
+                progress.set(fraction);
+                if let Some(f) = progress_fn.as_mut() {
+                    f(fraction, phase);
+                }
+            })
+        };
+        let future = Box::pin(GameScene::new(
+            mode,
+            info.clone(),
+            config.clone(),
+            fs,
+            player,
+            background.clone(),
+            illustration.clone(),
+            upload_fn,
+            update_fn,
+            Some(report),
+            practice_start,
+        ));
         let charter = Regex::new(r"\[!:[0-9]+:([^:]*)\]").unwrap().replace_all(&info.charter, "$1").to_string();
 
         Ok(Self {
@@ -107,6 +142,7 @@ impl LoadingScene {
             finish_time: f32::INFINITY,
             target: None,
             charter,
+            progress,
         })
     }
 }
@@ -129,6 +165,7 @@ impl Scene for LoadingScene {
                     }
                     Some(game_scene) => {
                         self.load_task = None;
+                        self.progress.set(1.);
                         self.next_scene =
                             Some(game_scene.map_or_else(|e| NextScene::PopWithResult(Box::new(e)), |it| NextScene::Replace(Box::new(it))));
                         self.finish_time = if self.config.disable_loading { 0. } else { tm.now() as f32 + BEFORE_TIME };
@@ -153,7 +190,7 @@ impl Scene for LoadingScene {
             ..Default::default()
         });
         if self.config.render_bg {
-            draw_background(*self.background, self.config.render_bg_dim);
+            draw_background(*self.background, self.config.background_mode, self.config.render_bg_dim);
         }
         let dx = if now > self.finish_time {
             let p = ((now - self.finish_time) / TRANSITION_TIME).min(1.);
@@ -194,11 +231,14 @@ impl Scene for LoadingScene {
         let mut ct = sub.center();
         ct.x += sub.w * 0.01;
         ct.y += sub.h * 0.05;
-        draw_parallelogram(sub, None, WHITE, true);
+        let badge = level_color_with(&self.info.level, &self.config.level_colors);
+        // dark tiers get white text
+        let badge_text = if badge.r * 0.299 + badge.g * 0.587 + badge.b * 0.114 > 0.6 { BLACK } else { WHITE };
+        draw_parallelogram(sub, None, badge, true);
         //draw_text_aligned(ui, &(self.info.difficulty as u32).to_string(), ct.x, ct.y + sub.h * 0.05, (0.5, 1.), 0.88, BLACK);
         if self.config.difficulty.len() > 0 {
             draw_text_aligned_fix(ui, &self.config.difficulty
-                , ct.x, ct.y + sub.h * 0.05, (0.5, 1.), 0.90, BLACK, main.w * 0.18
+                , ct.x, ct.y + sub.h * 0.05, (0.5, 1.), 0.90, badge_text, main.w * 0.18
             );
         } else {
             let first_str = Regex::new(r"[0-9?]+").unwrap();
@@ -215,7 +255,7 @@ impl Scene for LoadingScene {
                     //self.info.level.split_whitespace().rev().nth(0).and_then(|word| word.find('.').map(|pos| &word[(pos + 1)..])).unwrap_or("?")
                     "?"
                 )
-                , ct.x, ct.y + sub.h * 0.05, (0.5, 1.), 0.90, BLACK, main.w * 0.18
+                , ct.x, ct.y + sub.h * 0.05, (0.5, 1.), 0.90, badge_text, main.w * 0.18
             );
         }
         //难度
@@ -223,7 +263,7 @@ impl Scene for LoadingScene {
             .split_whitespace()
             .next()
             .unwrap_or("?")
-            , ct.x, ct.y + sub.h * 0.09, (0.5, 0.), 0.30, BLACK, main.w * 0.16
+            , ct.x, ct.y + sub.h * 0.09, (0.5, 0.), 0.30, badge_text, main.w * 0.16
         );
         let (text_chart, text_illustration) = if self.config.chinese {("谱师", "画师")} else {("Chart", "Illustration")};
         let t = draw_text_aligned(ui, text_chart, main.x + main.w / 6.1, main.y + main.h * 1.32, (0., 0.), 0.253, WHITE);
@@ -239,13 +279,7 @@ impl Scene for LoadingScene {
         let he = 0.35;
         let r = Rect::new(t.x - t.w * we, t.y - t.h * he, t.w * (1. + we * 2.2), t.h * (1. + he * 2.2));
 
-        let p = 0.6;
-        let s = 0.2;
-        let t = ((now - 0.3).max(0.) % (p * 2. + s)) / p;
-        let st = (t - 1.).clamp(0., 1.).powi(3);
-        let en = 1. - (1. - t.min(1.)).powi(3);
-
-        let mut r = Rect::new(r.x + r.w * st, r.y, r.w * (en - st), r.h);
+        let mut r = Rect::new(r.x, r.y, r.w * self.progress.get().clamp(0., 1.), r.h);
         ui.fill_rect(r, WHITE);
         r.x += dx;
         ui.scissor(Some(r));
@@ -5,7 +5,7 @@ use phire::{
     core::ChartExtra,
     fs::FileSystem,
     info::ChartFormat,
-    parse::{parse_pec, parse_phigros, parse_rpe},
+    parse::{parse_osu, parse_pec, parse_phigros, parse_rpe, OSU_HEADER},
 };
 use std::{
     any::Any,
@@ -73,6 +73,8 @@ fn main() -> Result<()> {
             } else {
                 ChartFormat::Pgr
             }
+        } else if text.starts_with(OSU_HEADER) {
+            ChartFormat::Osu
         } else {
             ChartFormat::Pec
         }
@@ -86,6 +88,7 @@ fn main() -> Result<()> {
         ChartFormat::Rpe => pollster::block_on(parse_rpe(&String::from_utf8_lossy(&bytes), fs.as_mut(), extra)),
         ChartFormat::Pgr => parse_phigros(&String::from_utf8_lossy(&bytes), extra),
         ChartFormat::Pec => parse_pec(&String::from_utf8_lossy(&bytes), extra),
+        ChartFormat::Osu => parse_osu(&String::from_utf8_lossy(&bytes), extra),
         ChartFormat::Pbc => {
             let mut r = BinaryReader::new(Cursor::new(&bytes));
             r.read()